@@ -0,0 +1,899 @@
+//! A mutex and a reader-writer lock which can be acquired both by blocking
+//! threads and by async tasks.
+//!
+//! Blocking threads wait on the futex, as usual. Async tasks register a
+//! `Waker` in a list, and the unlocking thread wakes both kinds of waiter.
+
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering::{Acquire, Relaxed, Release},
+};
+use core::task::{Context, Poll, Waker};
+use crate::futex_mutex::Mutex as FutexMutex;
+use crate::futex_backend::SyscallBackend;
+use crate::wait_wake::{futex_wait_timespec, futex_wake_all};
+use crate::ReprCMutex;
+
+/// A list of wakers for the async tasks waiting on a lock.
+struct WakerList {
+    // The number of registered tasks, so that unlocking doesn't need to lock
+    // `slots` when there are none.
+    len: AtomicU32,
+    slots: ReprCMutex<Vec<Option<Slot>>>,
+}
+
+enum Slot {
+    /// The task is waiting to be woken.
+    Waiting(Waker),
+    /// The task has been woken, and hasn't been polled since.
+    Notified,
+}
+
+impl WakerList {
+    const fn new() -> Self {
+        Self {
+            len: AtomicU32::new(0),
+            slots: ReprCMutex::new(Vec::new()),
+        }
+    }
+
+    /// Register `waker` to be woken, in the slot `key` if we have one, or in
+    /// a new slot, which is stored in `key`.
+    fn register(&self, key: &mut Option<usize>, waker: &Waker) {
+        let mut slots = self.slots.lock();
+        match *key {
+            Some(k) => match &mut slots[k] {
+                Some(Slot::Waiting(w)) if w.will_wake(waker) => {}
+                slot => *slot = Some(Slot::Waiting(waker.clone())),
+            },
+            None => {
+                let slot = Some(Slot::Waiting(waker.clone()));
+                let k = match slots.iter().position(Option::is_none) {
+                    Some(k) => {
+                        slots[k] = slot;
+                        k
+                    }
+                    None => {
+                        slots.push(slot);
+                        slots.len() - 1
+                    }
+                };
+                *key = Some(k);
+                self.len.fetch_add(1, Relaxed);
+            }
+        }
+    }
+
+    /// Remove the slot `key`, returning `true` if it had been notified.
+    fn remove(&self, key: usize) -> bool {
+        let mut slots = self.slots.lock();
+        let slot = slots[key].take();
+        self.len.fetch_sub(1, Relaxed);
+        matches!(slot, Some(Slot::Notified))
+    }
+
+    /// Wake one registered task which hasn't already been woken.
+    fn wake_one(&self) {
+        if self.len.load(Relaxed) == 0 {
+            return;
+        }
+        let waker = {
+            let mut slots = self.slots.lock();
+            slots.iter_mut().find_map(|slot| match slot.take() {
+                Some(Slot::Waiting(waker)) => {
+                    *slot = Some(Slot::Notified);
+                    Some(waker)
+                }
+                other => {
+                    *slot = other;
+                    None
+                }
+            })
+        };
+        // Wake outside the lock, in case the waker polls the task inline.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Wake all registered tasks.
+    fn wake_all(&self) {
+        if self.len.load(Relaxed) == 0 {
+            return;
+        }
+        let wakers: Vec<Waker> = {
+            let mut slots = self.slots.lock();
+            slots
+                .iter_mut()
+                .filter_map(|slot| match slot.take() {
+                    Some(Slot::Waiting(waker)) => {
+                        *slot = Some(Slot::Notified);
+                        Some(waker)
+                    }
+                    other => {
+                        *slot = other;
+                        None
+                    }
+                })
+                .collect()
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// A mutual exclusion primitive which can be locked by blocking threads and
+/// by async tasks.
+///
+/// [`lock`] blocks the current thread like [`Mutex::lock`], and
+/// [`lock_async`] returns a future which resolves once the lock is
+/// acquired. Both can be used on the same mutex at the same time.
+///
+/// The uncontended paths are the same single compare-and-swap as [`Mutex`]'s.
+/// When the mutex is contended, blocked threads wait on the futex, and async
+/// tasks register their `Waker`s, and unlocking wakes one of each.
+///
+/// Since `Waker`s can't be shared between processes, there is no `shm`
+/// version of this type.
+///
+/// [`lock`]: Self::lock
+/// [`lock_async`]: Self::lock_async
+/// [`Mutex`]: crate::Mutex
+/// [`Mutex::lock`]: crate::Mutex::lock
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::AsyncMutex;
+///
+/// let mutex = AsyncMutex::new(0);
+/// *mutex.lock() += 1;
+///
+/// async fn increment(mutex: &AsyncMutex<i32>) {
+///     *mutex.lock_async().await += 1;
+/// }
+/// assert_eq!(*mutex.lock(), 1);
+/// ```
+pub struct AsyncMutex<T: ?Sized> {
+    raw: FutexMutex<false>,
+    wakers: WakerList,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for AsyncMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for AsyncMutex<T> {}
+
+impl<T> AsyncMutex<T> {
+    /// Creates a new mutex in an unlocked state ready for use.
+    #[inline]
+    pub const fn new(val: T) -> Self {
+        Self {
+            raw: FutexMutex::new(),
+            wakers: WakerList::new(),
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> AsyncMutex<T> {
+    /// Acquires the mutex, blocking the current thread until it is able to
+    /// do so.
+    ///
+    /// Attempts to lock a mutex in the thread which already holds the lock
+    /// will result in a deadlock.
+    #[inline]
+    pub fn lock(&self) -> AsyncMutexGuard<'_, T> {
+        self.raw.lock();
+        AsyncMutexGuard {
+            lock: self,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a future which acquires the mutex, resolving once it is able
+    /// to do so.
+    ///
+    /// If the future is dropped before it resolves, it gives up its place
+    /// in line.
+    #[inline]
+    pub fn lock_async(&self) -> AsyncMutexLockFuture<'_, T> {
+        AsyncMutexLockFuture {
+            lock: self,
+            key: None,
+        }
+    }
+
+    /// Attempts to acquire the mutex without blocking.
+    #[inline]
+    pub fn try_lock(&self) -> Option<AsyncMutexGuard<'_, T>> {
+        if self.raw.try_lock() {
+            Some(AsyncMutexGuard {
+                lock: self,
+                marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the mutex mutably, no actual locking needs to
+    /// take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        if self.raw.unlock_contended() {
+            // The mutex woke one blocked thread, if there were any. Wake one
+            // task too, so that neither kind of waiter can starve the other.
+            self.wakers.wake_one();
+        }
+    }
+}
+
+impl<T: Default> Default for AsyncMutex<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T> From<T> for AsyncMutex<T> {
+    #[inline]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for AsyncMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("AsyncMutex");
+        match self.try_lock() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// A future which acquires an [`AsyncMutex`].
+///
+/// This is returned by [`AsyncMutex::lock_async`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AsyncMutexLockFuture<'a, T: ?Sized> {
+    lock: &'a AsyncMutex<T>,
+    // Our slot in the mutex's waker list, once we've registered.
+    key: Option<usize>,
+}
+
+impl<'a, T: ?Sized> Future for AsyncMutexLockFuture<'a, T> {
+    type Output = AsyncMutexGuard<'a, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let lock = self.lock;
+
+        if self.key.is_none() && lock.raw.try_lock() {
+            return Poll::Ready(AsyncMutexGuard {
+                lock,
+                marker: PhantomData,
+            });
+        }
+
+        // Register before marking the mutex as contended, so that the
+        // unlocking thread sees our waker.
+        lock.wakers.register(&mut self.key, cx.waker());
+
+        if lock.raw.try_lock_contended() {
+            // We got it. Since we marked it as contended, our unlock will
+            // wake the next waiter, so we don't need to pass on any
+            // notification we received.
+            let key = self.key.take().unwrap();
+            lock.wakers.remove(key);
+            return Poll::Ready(AsyncMutexGuard {
+                lock,
+                marker: PhantomData,
+            });
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<'a, T: ?Sized> Drop for AsyncMutexLockFuture<'a, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            if self.lock.wakers.remove(key) {
+                // We were woken to take the lock, but we're giving up, so
+                // pass the notification on to another task.
+                self.lock.wakers.wake_one();
+            }
+        }
+    }
+}
+
+impl<'a, T: ?Sized> fmt::Debug for AsyncMutexLockFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncMutexLockFuture").finish_non_exhaustive()
+    }
+}
+
+/// An RAII guard for an [`AsyncMutex`].
+///
+/// When this structure is dropped, the mutex is unlocked. Unlike
+/// [`MutexGuard`], this may be sent to another thread, so that it can be
+/// held across `.await` points in multi-threaded executors.
+///
+/// [`MutexGuard`]: crate::MutexGuard
+#[must_use = "if unused the AsyncMutex will immediately unlock"]
+pub struct AsyncMutexGuard<'a, T: ?Sized> {
+    lock: &'a AsyncMutex<T>,
+    marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T: ?Sized> Deref for AsyncMutexGuard<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: We hold the lock.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for AsyncMutexGuard<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: We hold the lock.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for AsyncMutexGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: We hold the lock.
+        unsafe { self.lock.unlock() }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for AsyncMutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+// The `AsyncRwLock` state uses the same layout as the futex `RwLock`, but
+// writers wait on the state too, and unlocking wakes all waiters and lets
+// them race, rather than handing the lock to a writer.
+const READ_LOCKED: u32 = 1;
+const MASK: u32 = (1 << 30) - 1;
+const WRITE_LOCKED: u32 = MASK;
+const MAX_READERS: u32 = MASK - 1;
+const READERS_WAITING: u32 = 1 << 30;
+const WRITERS_WAITING: u32 = 1 << 31;
+
+#[inline]
+fn is_unlocked(state: u32) -> bool {
+    state & MASK == 0
+}
+
+#[inline]
+fn is_read_lockable(state: u32) -> bool {
+    // As with the futex `RwLock`, readers don't take the lock while there are
+    // waiters, so that they can't starve writers.
+    state & MASK < MAX_READERS && state & (READERS_WAITING | WRITERS_WAITING) == 0
+}
+
+#[inline]
+fn has_reached_max_readers(state: u32) -> bool {
+    state & MASK == MAX_READERS
+}
+
+/// A reader-writer lock which can be locked by blocking threads and by async
+/// tasks.
+///
+/// [`read`] and [`write`] block the current thread like [`RwLock::read`] and
+/// [`RwLock::write`], and [`read_async`] and [`write_async`] return futures
+/// which resolve once the lock is acquired. Both can be used on the same lock
+/// at the same time.
+///
+/// The uncontended paths are the same single compare-and-swap as
+/// [`RwLock`]'s. When the lock is contended, blocked threads wait on the
+/// futex, and async tasks register their `Waker`s, and unlocking wakes all of
+/// them.
+///
+/// Since `Waker`s can't be shared between processes, there is no `shm`
+/// version of this type.
+///
+/// [`read`]: Self::read
+/// [`write`]: Self::write
+/// [`read_async`]: Self::read_async
+/// [`write_async`]: Self::write_async
+/// [`RwLock`]: crate::RwLock
+/// [`RwLock::read`]: crate::RwLock::read
+/// [`RwLock::write`]: crate::RwLock::write
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::AsyncRwLock;
+///
+/// let lock = AsyncRwLock::new(5);
+/// {
+///     let r1 = lock.read();
+///     let r2 = lock.try_read().unwrap();
+///     assert_eq!(*r1 + *r2, 10);
+/// }
+///
+/// async fn increment(lock: &AsyncRwLock<i32>) {
+///     *lock.write_async().await += 1;
+/// }
+/// *lock.write() += 1;
+/// assert_eq!(*lock.read(), 6);
+/// ```
+pub struct AsyncRwLock<T: ?Sized> {
+    state: AtomicU32,
+    wakers: WakerList,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for AsyncRwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for AsyncRwLock<T> {}
+
+impl<T> AsyncRwLock<T> {
+    /// Creates a new reader-writer lock in an unlocked state ready for use.
+    #[inline]
+    pub const fn new(val: T) -> Self {
+        Self {
+            state: AtomicU32::new(0),
+            wakers: WakerList::new(),
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Consumes this lock, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> AsyncRwLock<T> {
+    /// Locks this lock with shared read access, blocking the current thread
+    /// until it can be acquired.
+    #[inline]
+    pub fn read(&self) -> AsyncRwLockReadGuard<'_, T> {
+        if !self.try_read_raw() {
+            self.read_contended();
+        }
+        AsyncRwLockReadGuard {
+            lock: self,
+            marker: PhantomData,
+        }
+    }
+
+    /// Locks this lock with exclusive write access, blocking the current
+    /// thread until it can be acquired.
+    #[inline]
+    pub fn write(&self) -> AsyncRwLockWriteGuard<'_, T> {
+        if !self.try_write_raw() {
+            self.write_contended();
+        }
+        AsyncRwLockWriteGuard {
+            lock: self,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a future which locks this lock with shared read access,
+    /// resolving once it can be acquired.
+    #[inline]
+    pub fn read_async(&self) -> AsyncRwLockReadFuture<'_, T> {
+        AsyncRwLockReadFuture {
+            lock: self,
+            key: None,
+        }
+    }
+
+    /// Returns a future which locks this lock with exclusive write access,
+    /// resolving once it can be acquired.
+    #[inline]
+    pub fn write_async(&self) -> AsyncRwLockWriteFuture<'_, T> {
+        AsyncRwLockWriteFuture {
+            lock: self,
+            key: None,
+        }
+    }
+
+    /// Attempts to acquire this lock with shared read access without
+    /// blocking.
+    #[inline]
+    pub fn try_read(&self) -> Option<AsyncRwLockReadGuard<'_, T>> {
+        if self.try_read_raw() {
+            Some(AsyncRwLockReadGuard {
+                lock: self,
+                marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to acquire this lock with exclusive write access without
+    /// blocking.
+    #[inline]
+    pub fn try_write(&self) -> Option<AsyncRwLockWriteGuard<'_, T>> {
+        if self.try_write_raw() {
+            Some(AsyncRwLockWriteGuard {
+                lock: self,
+                marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the lock mutably, no actual locking needs to
+    /// take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    #[inline]
+    fn try_read_raw(&self) -> bool {
+        self.state
+            .fetch_update(Acquire, Relaxed, |s| is_read_lockable(s).then(|| s + READ_LOCKED))
+            .is_ok()
+    }
+
+    #[inline]
+    fn try_write_raw(&self) -> bool {
+        self.state
+            .fetch_update(Acquire, Relaxed, |s| is_unlocked(s).then(|| s + WRITE_LOCKED))
+            .is_ok()
+    }
+
+    #[cold]
+    fn read_contended(&self) {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            match self.try_read_or_flag(state) {
+                Ok(true) => return,
+                Ok(false) => {}
+                Err(s) => {
+                    state = s;
+                    continue;
+                }
+            }
+
+            futex_wait_timespec::<false, SyscallBackend>(
+                &self.state,
+                state | READERS_WAITING,
+                None,
+            );
+            state = self.state.load(Relaxed);
+        }
+    }
+
+    #[cold]
+    fn write_contended(&self) {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            match self.try_write_or_flag(state) {
+                Ok(true) => return,
+                Ok(false) => {}
+                Err(s) => {
+                    state = s;
+                    continue;
+                }
+            }
+
+            futex_wait_timespec::<false, SyscallBackend>(
+                &self.state,
+                state | WRITERS_WAITING,
+                None,
+            );
+            state = self.state.load(Relaxed);
+        }
+    }
+
+    /// Given the current `state`, read-lock the lock if it's read-lockable,
+    /// or otherwise set the readers waiting bit.
+    ///
+    /// Returns `Ok(true)` if we locked it, `Ok(false)` if the bit is set, or
+    /// the new state if it changed in the meantime.
+    fn try_read_or_flag(&self, state: u32) -> Result<bool, u32> {
+        if is_read_lockable(state) {
+            return self
+                .state
+                .compare_exchange_weak(state, state + READ_LOCKED, Acquire, Relaxed)
+                .map(|_| true);
+        }
+
+        if has_reached_max_readers(state) {
+            panic!("too many active read locks on AsyncRwLock");
+        }
+
+        if state & READERS_WAITING == 0 {
+            self.state
+                .compare_exchange(state, state | READERS_WAITING, Relaxed, Relaxed)?;
+        }
+        Ok(false)
+    }
+
+    /// Like `try_read_or_flag`, but for writers.
+    fn try_write_or_flag(&self, state: u32) -> Result<bool, u32> {
+        if is_unlocked(state) {
+            return self
+                .state
+                .compare_exchange_weak(state, state + WRITE_LOCKED, Acquire, Relaxed)
+                .map(|_| true);
+        }
+
+        if state & WRITERS_WAITING == 0 {
+            self.state
+                .compare_exchange(state, state | WRITERS_WAITING, Relaxed, Relaxed)?;
+        }
+        Ok(false)
+    }
+
+    #[inline]
+    unsafe fn read_unlock(&self) {
+        let state = self.state.fetch_sub(READ_LOCKED, Release) - READ_LOCKED;
+        if is_unlocked(state) && state & (READERS_WAITING | WRITERS_WAITING) != 0 {
+            self.wake_all();
+        }
+    }
+
+    #[inline]
+    unsafe fn write_unlock(&self) {
+        let state = self.state.fetch_sub(WRITE_LOCKED, Release) - WRITE_LOCKED;
+        if state & (READERS_WAITING | WRITERS_WAITING) != 0 {
+            self.wake_all();
+        }
+    }
+
+    /// Clear the waiting bits and wake up all waiters, to race for the lock.
+    /// Any that don't get it set the bits again before waiting.
+    #[cold]
+    fn wake_all(&self) {
+        self.state.fetch_and(MASK, Relaxed);
+        futex_wake_all::<false, SyscallBackend>(&self.state);
+        self.wakers.wake_all();
+    }
+}
+
+impl<T: Default> Default for AsyncRwLock<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T> From<T> for AsyncRwLock<T> {
+    #[inline]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for AsyncRwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("AsyncRwLock");
+        match self.try_read() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// A future which locks an [`AsyncRwLock`] with shared read access.
+///
+/// This is returned by [`AsyncRwLock::read_async`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AsyncRwLockReadFuture<'a, T: ?Sized> {
+    lock: &'a AsyncRwLock<T>,
+    key: Option<usize>,
+}
+
+impl<'a, T: ?Sized> Future for AsyncRwLockReadFuture<'a, T> {
+    type Output = AsyncRwLockReadGuard<'a, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let lock = self.lock;
+
+        if self.key.is_none() && lock.try_read_raw() {
+            return Poll::Ready(AsyncRwLockReadGuard {
+                lock,
+                marker: PhantomData,
+            });
+        }
+
+        // Register before setting the waiting bit, so that the unlocking
+        // thread sees our waker.
+        lock.wakers.register(&mut self.key, cx.waker());
+
+        let mut state = lock.state.load(Relaxed);
+        loop {
+            match lock.try_read_or_flag(state) {
+                Ok(true) => {
+                    let key = self.key.take().unwrap();
+                    lock.wakers.remove(key);
+                    return Poll::Ready(AsyncRwLockReadGuard {
+                        lock,
+                        marker: PhantomData,
+                    });
+                }
+                Ok(false) => return Poll::Pending,
+                Err(s) => state = s,
+            }
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for AsyncRwLockReadFuture<'a, T> {
+    fn drop(&mut self) {
+        // Unlocking wakes everyone, so there's no notification to pass on.
+        if let Some(key) = self.key {
+            self.lock.wakers.remove(key);
+        }
+    }
+}
+
+impl<'a, T: ?Sized> fmt::Debug for AsyncRwLockReadFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncRwLockReadFuture").finish_non_exhaustive()
+    }
+}
+
+/// A future which locks an [`AsyncRwLock`] with exclusive write access.
+///
+/// This is returned by [`AsyncRwLock::write_async`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AsyncRwLockWriteFuture<'a, T: ?Sized> {
+    lock: &'a AsyncRwLock<T>,
+    key: Option<usize>,
+}
+
+impl<'a, T: ?Sized> Future for AsyncRwLockWriteFuture<'a, T> {
+    type Output = AsyncRwLockWriteGuard<'a, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let lock = self.lock;
+
+        if self.key.is_none() && lock.try_write_raw() {
+            return Poll::Ready(AsyncRwLockWriteGuard {
+                lock,
+                marker: PhantomData,
+            });
+        }
+
+        lock.wakers.register(&mut self.key, cx.waker());
+
+        let mut state = lock.state.load(Relaxed);
+        loop {
+            match lock.try_write_or_flag(state) {
+                Ok(true) => {
+                    let key = self.key.take().unwrap();
+                    lock.wakers.remove(key);
+                    return Poll::Ready(AsyncRwLockWriteGuard {
+                        lock,
+                        marker: PhantomData,
+                    });
+                }
+                Ok(false) => return Poll::Pending,
+                Err(s) => state = s,
+            }
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for AsyncRwLockWriteFuture<'a, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.lock.wakers.remove(key);
+        }
+    }
+}
+
+impl<'a, T: ?Sized> fmt::Debug for AsyncRwLockWriteFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncRwLockWriteFuture").finish_non_exhaustive()
+    }
+}
+
+/// An RAII guard for shared read access to an [`AsyncRwLock`].
+///
+/// When this structure is dropped, the shared access is released.
+#[must_use = "if unused the AsyncRwLock will immediately unlock"]
+pub struct AsyncRwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a AsyncRwLock<T>,
+    marker: PhantomData<&'a T>,
+}
+
+impl<'a, T: ?Sized> Deref for AsyncRwLockReadGuard<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: We hold a read lock.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for AsyncRwLockReadGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: We hold a read lock.
+        unsafe { self.lock.read_unlock() }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for AsyncRwLockReadGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// An RAII guard for exclusive write access to an [`AsyncRwLock`].
+///
+/// When this structure is dropped, the exclusive access is released.
+#[must_use = "if unused the AsyncRwLock will immediately unlock"]
+pub struct AsyncRwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a AsyncRwLock<T>,
+    marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T: ?Sized> Deref for AsyncRwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: We hold the write lock.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for AsyncRwLockWriteGuard<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: We hold the write lock.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for AsyncRwLockWriteGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: We hold the write lock.
+        unsafe { self.lock.write_unlock() }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for AsyncRwLockWriteGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,239 @@
+//! The atomic type and futex operations used by the state machines derived
+//! from std's futex code: `futex_mutex`, `futex_rwlock`, `futex_condvar`,
+//! and `futex_once`.
+//!
+//! Normally these are `core`'s `AtomicU32` and the futex operations of the
+//! state machine's [`FutexBackend`]. Under `cfg(loom)`, they're loom's
+//! atomics and a loom-aware emulation of the futex operations, so that loom
+//! can model the state machines.
+//!
+//! [`FutexBackend`]: crate::FutexBackend
+
+#[cfg(not(loom))]
+pub(crate) use core::hint::spin_loop;
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic::AtomicU32;
+#[cfg(not(loom))]
+pub(crate) use crate::wait_wake::{futex_wait, futex_wait_timespec, futex_wake, futex_wake_all};
+
+#[cfg(loom)]
+pub(crate) use ::loom::hint::spin_loop;
+#[cfg(loom)]
+pub(crate) use self::loom::{futex_wait, futex_wait_timespec, futex_wake, futex_wake_all, AtomicU32};
+
+/// The number of times to spin before waiting on a futex.
+///
+/// Under loom, every iteration is another interleaving to explore, so spin
+/// only once.
+#[cfg(not(loom))]
+pub(crate) const SPIN_LIMIT: u32 = 100; // Chosen by fair dice roll.
+#[cfg(loom)]
+pub(crate) const SPIN_LIMIT: u32 = 1;
+
+// The emulated futex operations take the same generic parameters as the
+// real ones, but don't use the backend.
+#[cfg(loom)]
+#[allow(clippy::extra_unused_type_parameters)]
+mod loom {
+    extern crate std;
+
+    use ::loom::sync::atomic::AtomicU32 as LoomAtomicU32;
+    use ::loom::sync::{Condvar, Mutex};
+    use ::loom::thread::{self, ThreadId};
+    use core::sync::atomic::Ordering::{self, SeqCst};
+    use core::time::Duration;
+    use crate::futex_backend::FutexBackend;
+    use rustix::time::Timespec;
+    use std::sync::{Mutex as StdMutex, OnceLock};
+    use std::vec::Vec;
+
+    /// An `AtomicU32` which can be created in a `const` context.
+    ///
+    /// loom's atomics can only be created while a model is running, so this
+    /// creates one the first time it's used, which must be within the model.
+    /// Like all loom objects, an `AtomicU32` can't be used in more than one
+    /// run of a model, so it can't be in a plain `static`.
+    pub(crate) struct AtomicU32 {
+        init: u32,
+        atomic: OnceLock<LoomAtomicU32>,
+        /// The loom threads which have synchronized with the creation of
+        /// `atomic`. This isn't visible to loom.
+        synced: StdMutex<Vec<ThreadId>>,
+    }
+
+    ::loom::lazy_static! {
+        /// Held while creating a loom atomic, and acquired by each thread
+        /// before its first access to it, so that the creation happens
+        /// before all accesses, as if it had been created in `new`.
+        static ref CREATION: Mutex<()> = Mutex::new(());
+    }
+
+    impl AtomicU32 {
+        pub(crate) const fn new(v: u32) -> Self {
+            Self {
+                init: v,
+                atomic: OnceLock::new(),
+                synced: StdMutex::new(Vec::new()),
+            }
+        }
+
+        fn atomic(&self) -> &LoomAtomicU32 {
+            // loom threads only switch at loom operations, so the `synced`
+            // lock is never held while another loom thread runs.
+            let id = thread::current().id();
+            if !self.synced.lock().unwrap().contains(&id) {
+                let _creation = CREATION.lock().unwrap();
+                self.atomic.get_or_init(|| LoomAtomicU32::new(self.init));
+                self.synced.lock().unwrap().push(id);
+            }
+            self.atomic.get().unwrap()
+        }
+
+        pub(crate) fn get_mut(&mut self) -> &mut u32 {
+            // We have exclusive access, so move the value out of the loom
+            // atomic. A new one is created the next time it's shared.
+            if let Some(atomic) = self.atomic.take() {
+                self.init = atomic.into_inner();
+                self.synced.get_mut().unwrap().clear();
+            }
+            &mut self.init
+        }
+
+        pub(crate) fn load(&self, order: Ordering) -> u32 {
+            self.atomic().load(order)
+        }
+
+        pub(crate) fn swap(&self, val: u32, order: Ordering) -> u32 {
+            self.atomic().swap(val, order)
+        }
+
+        pub(crate) fn compare_exchange(
+            &self,
+            current: u32,
+            new: u32,
+            success: Ordering,
+            failure: Ordering,
+        ) -> Result<u32, u32> {
+            self.atomic().compare_exchange(current, new, success, failure)
+        }
+
+        pub(crate) fn compare_exchange_weak(
+            &self,
+            current: u32,
+            new: u32,
+            success: Ordering,
+            failure: Ordering,
+        ) -> Result<u32, u32> {
+            self.atomic()
+                .compare_exchange_weak(current, new, success, failure)
+        }
+
+        pub(crate) fn fetch_add(&self, val: u32, order: Ordering) -> u32 {
+            self.atomic().fetch_add(val, order)
+        }
+
+        pub(crate) fn fetch_sub(&self, val: u32, order: Ordering) -> u32 {
+            self.atomic().fetch_sub(val, order)
+        }
+
+        pub(crate) fn fetch_update<F>(
+            &self,
+            set_order: Ordering,
+            fetch_order: Ordering,
+            f: F,
+        ) -> Result<u32, u32>
+        where
+            F: FnMut(u32) -> Option<u32>,
+        {
+            self.atomic().fetch_update(set_order, fetch_order, f)
+        }
+    }
+
+    /// The emulated kernel futex state: the addresses of the waiting threads'
+    /// futexes, with an ID for each waiter, in the order they started
+    /// waiting.
+    struct Futexes {
+        waiters: Mutex<(usize, Vec<(usize, usize)>)>,
+        condvar: Condvar,
+    }
+
+    ::loom::lazy_static! {
+        static ref FUTEXES: Futexes = Futexes {
+            waiters: Mutex::new((0, Vec::new())),
+            condvar: Condvar::new(),
+        };
+    }
+
+    /// Emulates `futex_wait`. The backend is ignored.
+    ///
+    /// loom doesn't model time, so waits with a timeout time out immediately
+    /// if the futex holds the expected value.
+    pub(crate) fn futex_wait<const SHM: bool, B: FutexBackend>(
+        futex: &AtomicU32,
+        expected: u32,
+        timeout: Option<Duration>,
+    ) -> bool {
+        wait(futex, expected, timeout.is_some())
+    }
+
+    /// Emulates `futex_wait_timespec`, in the same way as [`futex_wait`].
+    pub(crate) fn futex_wait_timespec<const SHM: bool, B: FutexBackend>(
+        futex: &AtomicU32,
+        expected: u32,
+        timespec: Option<&Timespec>,
+    ) -> bool {
+        wait(futex, expected, timespec.is_some())
+    }
+
+    fn wait(futex: &AtomicU32, expected: u32, timed: bool) -> bool {
+        let addr = futex as *const AtomicU32 as usize;
+        let mut waiters = FUTEXES.waiters.lock().unwrap();
+
+        // The kernel checks the value with a full barrier, while holding the
+        // lock that wakers take, so a concurrent change followed by a wake
+        // can't be missed.
+        if futex.load(SeqCst) != expected {
+            return true;
+        }
+        if timed {
+            return false;
+        }
+
+        let id = waiters.0;
+        waiters.0 += 1;
+        waiters.1.push((addr, id));
+        while waiters.1.iter().any(|&(_, w)| w == id) {
+            waiters = FUTEXES.condvar.wait(waiters).unwrap();
+        }
+        true
+    }
+
+    /// Emulates `futex_wake`.
+    pub(crate) fn futex_wake<const SHM: bool, B: FutexBackend>(futex: &AtomicU32) -> bool {
+        wake(futex, 1) != 0
+    }
+
+    /// Emulates `futex_wake_all`.
+    pub(crate) fn futex_wake_all<const SHM: bool, B: FutexBackend>(futex: &AtomicU32) {
+        wake(futex, usize::MAX);
+    }
+
+    fn wake(futex: &AtomicU32, count: usize) -> usize {
+        let addr = futex as *const AtomicU32 as usize;
+        let mut waiters = FUTEXES.waiters.lock().unwrap();
+
+        let mut woken = 0;
+        waiters.1.retain(|&(a, _)| {
+            if a == addr && woken < count {
+                woken += 1;
+                false
+            } else {
+                true
+            }
+        });
+        if woken != 0 {
+            FUTEXES.condvar.notify_all();
+        }
+        woken
+    }
+}
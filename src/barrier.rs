@@ -0,0 +1,307 @@
+//! A futex-based barrier, with an API derived from Rust's
+//! library/std/src/sync/barrier.rs at revision
+//! 22a5267c83a3e17f2b763279eb24bb632c45dc6b.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering::{AcqRel, Acquire, Relaxed},
+};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::wait_wake::{deadline, futex_wait_timespec, futex_wake_all};
+
+// The state is a single futex word holding both the number of threads that
+// have arrived in the current generation and a generation counter. Keeping
+// them in one word lets a thread whose wait times out withdraw its arrival
+// atomically with respect to the barrier completing.
+//
+// The count only needs enough bits to hold `n`, and the generation counter
+// gets all the bits above it. A waiter detects that its generation has
+// completed by seeing a different generation. When at most `n` threads use
+// the barrier, a generation can't complete while a thread is still waiting
+// in the previous one, so this is exact. With more than `n` threads, the
+// others can complete generations while a waiter sleeps, and if enough of
+// them complete to wrap the counter back to the waiter's generation before
+// the waiter wakes up and sees the change, it keeps sleeping. Making the
+// counter as wide as possible makes that take at least 2^8, and for typical
+// `n`, over 2^24, generations.
+
+/// The largest number of threads a barrier can block, so that the
+/// generation counter has at least 8 bits.
+const MAX_THREADS: u32 = (1 << 24) - 1;
+
+/// A barrier enables multiple threads to synchronize the beginning
+/// of some computation.
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::Barrier;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let n = 10;
+/// let mut handles = Vec::with_capacity(n);
+/// let barrier = Arc::new(Barrier::new(n));
+/// for _ in 0..n {
+///     let c = Arc::clone(&barrier);
+///     // The same messages will be printed together.
+///     // You will NOT see any interleaving.
+///     handles.push(thread::spawn(move || {
+///         println!("before wait");
+///         c.wait();
+///         println!("after wait");
+///     }));
+/// }
+/// // Wait for other threads to finish.
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// ```
+//#[stable(feature = "rust1", since = "1.0.0")]
+#[repr(C)]
+pub struct Barrier<const SHM: bool, B = SyscallBackend> {
+    state: AtomicU32,
+    num_threads: u32,
+    backend: PhantomData<B>,
+}
+
+/// A `BarrierWaitResult` is returned by [`Barrier::wait()`] and its timed
+/// variants when all threads in the [`Barrier`] have rendezvoused.
+///
+/// [`Barrier`]: crate::generic::Barrier
+/// [`Barrier::wait()`]: crate::generic::Barrier::wait
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::Barrier;
+///
+/// let barrier = Barrier::new(1);
+/// let barrier_wait_result = barrier.wait();
+/// ```
+//#[stable(feature = "rust1", since = "1.0.0")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult(bool);
+
+//#[stable(feature = "std_debug", since = "1.16.0")]
+impl<const SHM: bool, B: FutexBackend> fmt::Debug for Barrier<SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Barrier").finish_non_exhaustive()
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> Barrier<SHM, B> {
+    /// Creates a new barrier that can block a given number of threads.
+    ///
+    /// A barrier will block `n`-1 threads which call [`wait()`] and then wake
+    /// up all threads at once when the `n`th thread calls [`wait()`].
+    ///
+    /// [`wait()`]: Barrier::wait
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than 2<sup>24</sup>-1.
+    ///
+    /// Any number of threads may use the barrier, however if more than `n`
+    /// threads use it at once, a thread may miss the completion of its
+    /// generation, and keep waiting, if so many later generations complete
+    /// before it wakes up that the barrier's generation counter wraps
+    /// around. The counter has at least 8 bits, and more when `n` is small;
+    /// for example, it has 28 bits when `n` is 10.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::Barrier;
+    ///
+    /// let barrier = Barrier::new(10);
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    //#[rustc_const_stable(feature = "const_barrier", since = "1.78.0")]
+    #[must_use]
+    #[inline]
+    pub const fn new(n: usize) -> Self {
+        assert!(n <= MAX_THREADS as usize, "too many threads for Barrier");
+        Self {
+            state: AtomicU32::new(0),
+            num_threads: n as u32,
+            backend: PhantomData,
+        }
+    }
+
+    /// Blocks the current thread until all threads have rendezvoused here.
+    ///
+    /// Barriers are re-usable after all threads have rendezvoused once, and can
+    /// be used continuously.
+    ///
+    /// A single (arbitrary) thread will receive a [`BarrierWaitResult`] that
+    /// returns `true` from [`BarrierWaitResult::is_leader()`] when returning
+    /// from this function, and all other threads will receive a result that
+    /// will return `false` from [`BarrierWaitResult::is_leader()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::Barrier;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// let n = 10;
+    /// let mut handles = Vec::with_capacity(n);
+    /// let barrier = Arc::new(Barrier::new(n));
+    /// for _ in 0..n {
+    ///     let c = Arc::clone(&barrier);
+    ///     // The same messages will be printed together.
+    ///     // You will NOT see any interleaving.
+    ///     handles.push(thread::spawn(move || {
+    ///         println!("before wait");
+    ///         c.wait();
+    ///         println!("after wait");
+    ///     }));
+    /// }
+    /// // Wait for other threads to finish.
+    /// for handle in handles {
+    ///     handle.join().unwrap();
+    /// }
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    pub fn wait(&self) -> BarrierWaitResult {
+        match self.wait_optional_deadline(None) {
+            Some(result) => result,
+            None => unreachable!(),
+        }
+    }
+
+    /// Like [`wait()`], but gives up and returns `None` if not all threads
+    /// have rendezvoused within `timeout`.
+    ///
+    /// When this times out, the current thread's arrival is withdrawn, so the
+    /// barrier still waits for `n` other threads.
+    ///
+    /// [`wait()`]: Barrier::wait
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::Barrier;
+    /// use std::time::Duration;
+    ///
+    /// let barrier = Barrier::new(2);
+    /// assert!(barrier.wait_timeout(Duration::from_millis(10)).is_none());
+    /// ```
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<BarrierWaitResult> {
+        // Overflows are rounded up to an infinite timeout.
+        self.wait_optional_deadline(deadline(timeout).as_ref())
+    }
+
+    /// Like [`wait_timeout()`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`wait_timeout()`]: Barrier::wait_timeout
+    pub fn wait_until(&self, deadline: &Timespec) -> Option<BarrierWaitResult> {
+        self.wait_optional_deadline(Some(deadline))
+    }
+
+    /// Returns the amount to add to the state to advance to the next
+    /// generation. The bits below it hold the number of threads which have
+    /// arrived, and the bits above it hold the generation counter.
+    #[inline]
+    fn generation_one(&self) -> u32 {
+        1 << (u32::BITS - self.num_threads.leading_zeros())
+    }
+
+    fn wait_optional_deadline(&self, deadline: Option<&Timespec>) -> Option<BarrierWaitResult> {
+        let generation_one = self.generation_one();
+        let count_mask = generation_one - 1;
+        let generation_mask = !count_mask;
+
+        // Register our arrival.
+        let mut state = self.state.load(Relaxed);
+        let generation = loop {
+            let count = state & count_mask;
+            if count + 1 >= self.num_threads {
+                // We're the last to arrive. Reset the count and advance to the
+                // next generation, then wake everyone up.
+                let next = (state & generation_mask).wrapping_add(generation_one);
+                match self.state.compare_exchange_weak(state, next, AcqRel, Relaxed) {
+                    Ok(_) => {
+                        futex_wake_all::<SHM, B>(&self.state);
+                        return Some(BarrierWaitResult(true));
+                    }
+                    Err(s) => {
+                        state = s;
+                        continue;
+                    }
+                }
+            }
+            match self.state.compare_exchange_weak(state, state + 1, AcqRel, Relaxed) {
+                Ok(_) => break state & generation_mask,
+                Err(s) => state = s,
+            }
+        };
+
+        // Wait for the generation to change.
+        loop {
+            let state = self.state.load(Acquire);
+            if state & generation_mask != generation {
+                return Some(BarrierWaitResult(false));
+            }
+
+            if !futex_wait_timespec::<SHM, B>(&self.state, state, deadline) {
+                return self.withdraw(generation, generation_mask);
+            }
+        }
+    }
+
+    /// Withdraw our arrival after timing out, unless the barrier completed
+    /// in the meantime.
+    #[cold]
+    fn withdraw(&self, generation: u32, generation_mask: u32) -> Option<BarrierWaitResult> {
+        let mut state = self.state.load(Acquire);
+        loop {
+            if state & generation_mask != generation {
+                return Some(BarrierWaitResult(false));
+            }
+            match self.state.compare_exchange_weak(state, state - 1, Acquire, Acquire) {
+                Ok(_) => return None,
+                Err(s) => state = s,
+            }
+        }
+    }
+}
+
+//#[stable(feature = "std_debug", since = "1.16.0")]
+impl fmt::Debug for BarrierWaitResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BarrierWaitResult").field("is_leader", &self.is_leader()).finish()
+    }
+}
+
+impl BarrierWaitResult {
+    /// Returns `true` if this thread is the "leader thread" for the call to
+    /// [`Barrier::wait()`].
+    ///
+    /// Only one thread will have `true` returned from their result, all other
+    /// threads will have `false` returned.
+    ///
+    /// [`Barrier::wait()`]: crate::generic::Barrier::wait
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::Barrier;
+    ///
+    /// let barrier = Barrier::new(1);
+    /// let barrier_wait_result = barrier.wait();
+    /// println!("{:?}", barrier_wait_result.is_leader());
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    #[must_use]
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
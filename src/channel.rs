@@ -0,0 +1,561 @@
+//! Bounded multi-producer multi-consumer channels.
+//!
+//! The buffer is protected by a [`Mutex`], which is only held briefly and is
+//! never waited on while full or empty. Threads blocked on a full or empty
+//! channel instead wait directly on futex words, which are bumped whenever a
+//! value is received or sent, or the channel is disconnected.
+//!
+//! [`Mutex`]: crate::Mutex
+//!
+//! # Examples
+//!
+//! ```
+//! use rustix_futex_sync::channel;
+//! use std::thread;
+//!
+//! let (tx, rx) = channel::bounded(2);
+//! let t = thread::spawn(move || {
+//!     for i in 0..10 {
+//!         tx.send(i).unwrap();
+//!     }
+//! });
+//!
+//! let received = rx.iter().collect::<Vec<_>>();
+//! assert_eq!(received, (0..10).collect::<Vec<_>>());
+//! t.join().unwrap();
+//! ```
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering::SeqCst};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::SyscallBackend;
+use crate::Mutex;
+use crate::wait_wake::{deadline, futex_wait_timespec, futex_wake, futex_wake_all};
+
+/// Creates a channel which can hold at most `cap` values at a time.
+///
+/// If `cap` is zero, the channel is a rendezvous channel: each send blocks
+/// until a receiver takes the value.
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    let chan = Arc::new(Channel {
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(cap.max(1)),
+            cap,
+            head: 0,
+            senders: 1,
+            receivers: 1,
+        }),
+        sent: AtomicU32::new(0),
+        received: AtomicU32::new(0),
+        recv_waiters: AtomicU32::new(0),
+        send_waiters: AtomicU32::new(0),
+    });
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+struct Channel<T> {
+    state: Mutex<State<T>>,
+    /// Bumped whenever a value is sent or the channel is disconnected, for
+    /// receivers to wait on.
+    sent: AtomicU32,
+    /// Bumped whenever a value is received or the channel is disconnected,
+    /// for senders to wait on.
+    received: AtomicU32,
+    /// The number of receivers waiting on `sent`. In a zero-capacity
+    /// channel, `try_send` checks this for a receiver to hand a value to.
+    recv_waiters: AtomicU32,
+    /// The number of senders waiting on `received`.
+    send_waiters: AtomicU32,
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    cap: usize,
+    /// The number of values which have been received, which is the index of
+    /// the value at the front of `queue`.
+    head: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+impl<T> State<T> {
+    /// The number of values `queue` may hold. In a zero-capacity channel, a
+    /// value is held in the queue while its sender waits for it to be taken.
+    fn limit(&self) -> usize {
+        self.cap.max(1)
+    }
+}
+
+impl<T> Channel<T> {
+    /// Bump `seq` and wake a thread waiting on it, or all of them if `all`
+    /// is set.
+    fn notify(seq: &AtomicU32, waiters: &AtomicU32, all: bool) {
+        seq.fetch_add(1, SeqCst);
+        if waiters.load(SeqCst) != 0 {
+            if all {
+                futex_wake_all::<false, SyscallBackend>(seq);
+            } else {
+                futex_wake::<false, SyscallBackend>(seq);
+            }
+        }
+    }
+
+    /// Wait for `seq` to change from `expected`. Returns `false` on timeout.
+    fn wait(seq: &AtomicU32, waiters: &AtomicU32, expected: u32, deadline: Option<&Timespec>) -> bool {
+        waiters.fetch_add(1, SeqCst);
+        let woken = futex_wait_timespec::<false, SyscallBackend>(seq, expected, deadline);
+        waiters.fetch_sub(1, SeqCst);
+        woken
+    }
+
+    fn send(&self, t: T, deadline: Option<&Timespec>) -> Result<(), SendTimeoutError<T>> {
+        let mut timed_out = false;
+        loop {
+            let mut state = self.state.lock();
+            if state.receivers == 0 {
+                return Err(SendTimeoutError::Disconnected(t));
+            }
+            if state.queue.len() < state.limit() {
+                let index = state.head + state.queue.len() as u64;
+                let rendezvous = state.cap == 0;
+                state.queue.push_back(t);
+                drop(state);
+                Self::notify(&self.sent, &self.recv_waiters, false);
+                return if rendezvous {
+                    self.wait_taken(index, deadline)
+                } else {
+                    Ok(())
+                };
+            }
+            if timed_out {
+                return Err(SendTimeoutError::Timeout(t));
+            }
+            let expected = self.received.load(SeqCst);
+            drop(state);
+            timed_out = !Self::wait(&self.received, &self.send_waiters, expected, deadline);
+        }
+    }
+
+    /// In a zero-capacity channel, wait for the value at `index` to be taken
+    /// by a receiver, or take it back on timeout or disconnection.
+    fn wait_taken(&self, index: u64, deadline: Option<&Timespec>) -> Result<(), SendTimeoutError<T>> {
+        let mut timed_out = false;
+        loop {
+            let mut state = self.state.lock();
+            if state.head > index {
+                return Ok(());
+            }
+            if state.receivers == 0 || timed_out {
+                // Our value hasn't been taken, so it's the only one in the
+                // queue.
+                let t = state.queue.pop_back().unwrap();
+                let disconnected = state.receivers == 0;
+                drop(state);
+                // Let another sender have the slot.
+                Self::notify(&self.received, &self.send_waiters, true);
+                return Err(if disconnected {
+                    SendTimeoutError::Disconnected(t)
+                } else {
+                    SendTimeoutError::Timeout(t)
+                });
+            }
+            let expected = self.received.load(SeqCst);
+            drop(state);
+            timed_out = !Self::wait(&self.received, &self.send_waiters, expected, deadline);
+        }
+    }
+
+    fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.state.lock();
+        if state.receivers == 0 {
+            return Err(TrySendError::Disconnected(t));
+        }
+        // In a zero-capacity channel, only hand off a value to a receiver
+        // that is already waiting for one.
+        if state.queue.len() >= state.limit()
+            || (state.cap == 0 && self.recv_waiters.load(SeqCst) == 0)
+        {
+            return Err(TrySendError::Full(t));
+        }
+        state.queue.push_back(t);
+        drop(state);
+        Self::notify(&self.sent, &self.recv_waiters, false);
+        Ok(())
+    }
+
+    fn recv(&self, deadline: Option<&Timespec>) -> Result<T, RecvTimeoutError> {
+        let mut timed_out = false;
+        loop {
+            let expected = self.sent.load(SeqCst);
+            match self.try_recv() {
+                Ok(t) => return Ok(t),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) if timed_out => return Err(RecvTimeoutError::Timeout),
+                Err(TryRecvError::Empty) => {}
+            }
+            timed_out = !Self::wait(&self.sent, &self.recv_waiters, expected, deadline);
+        }
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.state.lock();
+        match state.queue.pop_front() {
+            Some(t) => {
+                state.head += 1;
+                let rendezvous = state.cap == 0;
+                drop(state);
+                // In a zero-capacity channel, wake the value's sender, as
+                // well as any senders waiting for the slot.
+                Self::notify(&self.received, &self.send_waiters, rendezvous);
+                Ok(t)
+            }
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn len(&self) -> usize {
+        let state = self.state.lock();
+        // In a zero-capacity channel, a value waiting to be taken isn't
+        // considered to be in the channel.
+        if state.cap == 0 {
+            0
+        } else {
+            state.queue.len()
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.state.lock().cap
+    }
+}
+
+/// The sending half of a channel created by [`bounded`].
+///
+/// Senders can be cloned to send from multiple threads. When all senders are
+/// dropped, the channel is disconnected, and receivers get an error once the
+/// values already in the channel have been received.
+pub struct Sender<T> {
+    chan: Arc<Channel<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value, blocking while the channel is full.
+    ///
+    /// In a zero-capacity channel, this blocks until a receiver takes the
+    /// value.
+    ///
+    /// If all receivers have been dropped, this returns the value in an
+    /// error.
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.chan.send(t, None).map_err(|err| match err {
+            SendTimeoutError::Disconnected(t) => SendError(t),
+            SendTimeoutError::Timeout(_) => unreachable!(),
+        })
+    }
+
+    /// Attempts to send a value without blocking.
+    ///
+    /// In a zero-capacity channel, this only succeeds if a receiver is
+    /// currently waiting.
+    pub fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        self.chan.try_send(t)
+    }
+
+    /// Like [`send`], but gives up if the value can't be sent within
+    /// `timeout`.
+    ///
+    /// [`send`]: Self::send
+    pub fn send_timeout(&self, t: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        let t = match self.chan.try_send(t) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Disconnected(t)) => return Err(SendTimeoutError::Disconnected(t)),
+            Err(TrySendError::Full(t)) => t,
+        };
+        // Overflows are rounded up to an infinite timeout.
+        self.chan.send(t, deadline(timeout).as_ref())
+    }
+
+    /// Like [`send_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`send_timeout`]: Self::send_timeout
+    pub fn send_until(&self, t: T, deadline: &Timespec) -> Result<(), SendTimeoutError<T>> {
+        self.chan.send(t, Some(deadline))
+    }
+
+    /// Returns the number of values in the channel.
+    pub fn len(&self) -> usize {
+        self.chan.len()
+    }
+
+    /// Returns `true` if the channel is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the capacity of the channel.
+    pub fn capacity(&self) -> usize {
+        self.chan.capacity()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.state.lock().senders += 1;
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.chan.state.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            Channel::<T>::notify(&self.chan.sent, &self.chan.recv_waiters, true);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a channel created by [`bounded`].
+///
+/// Receivers can be cloned to receive from multiple threads; each value is
+/// received by only one of them. When all receivers are dropped, the channel
+/// is disconnected, and senders get an error.
+pub struct Receiver<T> {
+    chan: Arc<Channel<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives a value, blocking while the channel is empty.
+    ///
+    /// If the channel is empty and all senders have been dropped, this
+    /// returns an error.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.chan.recv(None).map_err(|err| match err {
+            RecvTimeoutError::Disconnected => RecvError,
+            RecvTimeoutError::Timeout => unreachable!(),
+        })
+    }
+
+    /// Attempts to receive a value without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Like [`recv`], but gives up if no value is received within
+    /// `timeout`.
+    ///
+    /// [`recv`]: Self::recv
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match self.chan.try_recv() {
+            Ok(t) => return Ok(t),
+            Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+            Err(TryRecvError::Empty) => {}
+        }
+        // Overflows are rounded up to an infinite timeout.
+        self.chan.recv(deadline(timeout).as_ref())
+    }
+
+    /// Like [`recv_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`recv_timeout`]: Self::recv_timeout
+    pub fn recv_until(&self, deadline: &Timespec) -> Result<T, RecvTimeoutError> {
+        self.chan.recv(Some(deadline))
+    }
+
+    /// Returns an iterator which receives values until the channel is
+    /// disconnected.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Returns the number of values in the channel.
+    pub fn len(&self) -> usize {
+        self.chan.len()
+    }
+
+    /// Returns `true` if the channel is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the capacity of the channel.
+    pub fn capacity(&self) -> usize {
+        self.chan.capacity()
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.chan.state.lock().receivers += 1;
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.chan.state.lock();
+        state.receivers -= 1;
+        if state.receivers == 0 {
+            drop(state);
+            Channel::<T>::notify(&self.chan.received, &self.chan.send_waiters, true);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+/// An iterator over values received on a [`Receiver`], created by
+/// [`Receiver::iter`].
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+/// An error returned from [`Sender::send`] when the channel is
+/// disconnected, holding the value that couldn't be sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// An error returned from [`Sender::try_send`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel is full.
+    Full(T),
+    /// The channel is disconnected.
+    Disconnected(T),
+}
+
+/// An error returned from [`Sender::send_timeout`] and
+/// [`Sender::send_until`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SendTimeoutError<T> {
+    /// The value couldn't be sent before the timeout.
+    Timeout(T),
+    /// The channel is disconnected.
+    Disconnected(T),
+}
+
+/// An error returned from [`Receiver::recv`] when the channel is empty and
+/// disconnected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecvError;
+
+/// An error returned from [`Receiver::try_recv`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The channel is empty.
+    Empty,
+    /// The channel is empty and disconnected.
+    Disconnected,
+}
+
+/// An error returned from [`Receiver::recv_timeout`] and
+/// [`Receiver::recv_until`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    /// No value was received before the timeout.
+    Timeout,
+    /// The channel is empty and disconnected.
+    Disconnected,
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "sending on a closed channel".fmt(f)
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TrySendError::Full(..) => "Full(..)".fmt(f),
+            TrySendError::Disconnected(..) => "Disconnected(..)".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TrySendError::Full(..) => "sending on a full channel".fmt(f),
+            TrySendError::Disconnected(..) => "sending on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SendTimeoutError::Timeout(..) => "Timeout(..)".fmt(f),
+            SendTimeoutError::Disconnected(..) => "Disconnected(..)".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SendTimeoutError::Timeout(..) => "timed out waiting on send operation".fmt(f),
+            SendTimeoutError::Disconnected(..) => "sending on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "receiving on a closed channel".fmt(f)
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TryRecvError::Empty => "receiving on an empty channel".fmt(f),
+            TryRecvError::Disconnected => "receiving on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RecvTimeoutError::Timeout => "timed out waiting on receive operation".fmt(f),
+            RecvTimeoutError::Disconnected => "receiving on a closed channel".fmt(f),
+        }
+    }
+}
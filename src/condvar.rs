@@ -8,6 +8,7 @@ mod tests;
 */
 
 use core::fmt;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
 use crate::generic::{MutexGuard, RawCondvar};
 use core::time::Duration;
 
@@ -114,11 +115,12 @@ impl WaitTimeoutResult {
 /// ```
 //#[stable(feature = "rust1", since = "1.0.0")]
 #[repr(transparent)]
-pub struct Condvar<const SHM: bool> {
-    inner: RawCondvar<SHM>,
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Condvar<const SHM: bool, B = SyscallBackend> {
+    inner: RawCondvar<SHM, B>,
 }
 
-impl<const SHM: bool> Condvar<SHM> {
+impl<const SHM: bool, B: FutexBackend> Condvar<SHM, B> {
     /// Creates a new condition variable which is ready to be waited on and
     /// notified.
     ///
@@ -194,7 +196,7 @@ impl<const SHM: bool> Condvar<SHM> {
     /// }
     /// ```
     //#[stable(feature = "rust1", since = "1.0.0")]
-    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T, SHM>) -> MutexGuard<'a, T, SHM> {
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T, SHM, B>) -> MutexGuard<'a, T, SHM, B> {
         unsafe {
             self.inner.wait(MutexGuard::mutex(&guard).raw());
         }
@@ -247,9 +249,9 @@ impl<const SHM: bool> Condvar<SHM> {
     //#[stable(feature = "wait_until", since = "1.42.0")]
     pub fn wait_while<'a, T, F>(
         &self,
-        mut guard: MutexGuard<'a, T, SHM>,
+        mut guard: MutexGuard<'a, T, SHM, B>,
         mut condition: F,
-    ) -> MutexGuard<'a, T, SHM>
+    ) -> MutexGuard<'a, T, SHM, B>
     where
         F: FnMut(&mut T) -> bool,
     {
@@ -317,9 +319,9 @@ impl<const SHM: bool> Condvar<SHM> {
     #[deprecated(since = "1.6.0", note = "replaced by `rustix_futex_sync::Condvar::wait_timeout`")]
     pub fn wait_timeout_ms<'a, T>(
         &self,
-        guard: MutexGuard<'a, T, SHM>,
+        guard: MutexGuard<'a, T, SHM, B>,
         ms: u32,
-    ) -> (MutexGuard<'a, T, SHM>, bool) {
+    ) -> (MutexGuard<'a, T, SHM, B>, bool) {
         let res = self.wait_timeout(guard, Duration::from_millis(ms as u64));
         (res.0, !res.1.timed_out())
     }
@@ -389,9 +391,9 @@ impl<const SHM: bool> Condvar<SHM> {
     //#[stable(feature = "wait_timeout", since = "1.5.0")]
     pub fn wait_timeout<'a, T>(
         &self,
-        guard: MutexGuard<'a, T, SHM>,
+        guard: MutexGuard<'a, T, SHM, B>,
         dur: Duration,
-    ) -> (MutexGuard<'a, T, SHM>, WaitTimeoutResult) {
+    ) -> (MutexGuard<'a, T, SHM, B>, WaitTimeoutResult) {
         let result = unsafe {
             self.inner
                 .wait_timeout(MutexGuard::mutex(&guard).raw(), dur)
@@ -455,10 +457,10 @@ impl<const SHM: bool> Condvar<SHM> {
     //#[stable(feature = "wait_timeout_until", since = "1.42.0")]
     pub fn wait_timeout_while<'a, T, F>(
         &self,
-        mut guard: MutexGuard<'a, T, SHM>,
+        mut guard: MutexGuard<'a, T, SHM, B>,
         dur: Duration,
         mut condition: F,
-    ) -> (MutexGuard<'a, T, SHM>, WaitTimeoutResult)
+    ) -> (MutexGuard<'a, T, SHM, B>, WaitTimeoutResult)
     where
         F: FnMut(&mut T) -> bool,
     {
@@ -582,14 +584,14 @@ impl<const SHM: bool> Condvar<SHM> {
 }
 
 //#[stable(feature = "std_debug", since = "1.16.0")]
-impl<const SHM: bool> fmt::Debug for Condvar<SHM> {
+impl<const SHM: bool, B: FutexBackend> fmt::Debug for Condvar<SHM, B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Condvar").finish_non_exhaustive()
     }
 }
 
 //#[stable(feature = "condvar_default", since = "1.10.0")]
-impl<const SHM: bool> Default for Condvar<SHM> {
+impl<const SHM: bool, B: FutexBackend> Default for Condvar<SHM, B> {
     /// Creates a `Condvar` which is ready to be waited on and notified.
     fn default() -> Self {
         Condvar::new()
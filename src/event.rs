@@ -0,0 +1,237 @@
+//! A futex-based event.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering::{Acquire, Relaxed, Release},
+};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::wait_wake::{deadline, futex_wait_timespec, futex_wake, futex_wake_all};
+
+/// The event is set.
+const SET: u32 = 1;
+/// Some threads may be waiting for the event to be set.
+const WAITERS: u32 = 2;
+/// The event is auto-reset. This bit never changes after construction.
+const AUTO_RESET: u32 = 4;
+
+/// An event which threads can wait to be set.
+///
+/// A manual-reset event, created with [`new`], stays set once [`set`] is
+/// called, releasing all current and future waiters until it is [`reset`].
+/// An auto-reset event, created with [`new_auto_reset`], releases exactly one
+/// waiter each time it is set, and is reset again as that waiter returns. If
+/// there are no waiters, it stays set until the next thread waits on it.
+///
+/// `Event` is guaranteed to be a `repr(transparent)` wrapper around a single
+/// `AtomicU32`, and an all-zeros `Event` is a valid manual-reset event which
+/// is not set.
+///
+/// [`new`]: Self::new
+/// [`new_auto_reset`]: Self::new_auto_reset
+/// [`set`]: Self::set
+/// [`reset`]: Self::reset
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::Event;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let ready = Arc::new(Event::new());
+/// let ready2 = Arc::clone(&ready);
+///
+/// let t = thread::spawn(move || {
+///     ready2.wait();
+/// });
+///
+/// ready.set();
+/// t.join().unwrap();
+/// assert!(ready.is_set());
+/// ```
+#[repr(transparent)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Event<const SHM: bool, B = SyscallBackend> {
+    state: AtomicU32,
+    backend: PhantomData<B>,
+}
+
+impl<const SHM: bool, B: FutexBackend> Event<SHM, B> {
+    /// Creates a new manual-reset event which is not set.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(0),
+            backend: PhantomData,
+        }
+    }
+
+    /// Creates a new auto-reset event which is not set.
+    #[inline]
+    pub const fn new_auto_reset() -> Self {
+        Self {
+            state: AtomicU32::new(AUTO_RESET),
+            backend: PhantomData,
+        }
+    }
+
+    /// Returns `true` if this is an auto-reset event.
+    #[inline]
+    pub fn is_auto_reset(&self) -> bool {
+        self.state.load(Relaxed) & AUTO_RESET != 0
+    }
+
+    /// Returns `true` if the event is currently set.
+    #[inline]
+    pub fn is_set(&self) -> bool {
+        self.state.load(Acquire) & SET != 0
+    }
+
+    /// Sets the event.
+    ///
+    /// For a manual-reset event, this wakes all waiting threads. For an
+    /// auto-reset event, this wakes one waiting thread, if there are any.
+    /// Setting an event which is already set has no effect.
+    pub fn set(&self) {
+        let mut state = self.state.load(Relaxed);
+        if state & AUTO_RESET == 0 {
+            loop {
+                // Clear the `WAITERS` flag; we'll wake them all below.
+                let new = (state | SET) & !WAITERS;
+                if new == state {
+                    return;
+                }
+                match self.state.compare_exchange_weak(state, new, Release, Relaxed) {
+                    Ok(_) => break,
+                    Err(s) => state = s,
+                }
+            }
+            if state & WAITERS != 0 {
+                futex_wake_all::<SHM, B>(&self.state);
+            }
+        } else {
+            let prev = self.state.fetch_or(SET, Release);
+            if prev & (SET | WAITERS) == WAITERS {
+                self.wake_one();
+            }
+        }
+    }
+
+    /// Resets the event, so that threads calling [`wait`] block until it is
+    /// set again.
+    ///
+    /// [`wait`]: Self::wait
+    #[inline]
+    pub fn reset(&self) {
+        self.state.fetch_and(!SET, Relaxed);
+    }
+
+    /// Blocks the current thread until the event is set.
+    ///
+    /// For an auto-reset event, this also resets the event.
+    #[inline]
+    pub fn wait(&self) {
+        self.wait_optional_deadline(None);
+    }
+
+    /// Like [`wait`], but gives up if the event is not set within `timeout`.
+    ///
+    /// Returns `true` if the event was set, and `false` on timeout.
+    ///
+    /// [`wait`]: Self::wait
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        if self.try_wait() {
+            return true;
+        }
+        // Overflows are rounded up to an infinite timeout.
+        self.wait_optional_deadline(deadline(timeout).as_ref())
+    }
+
+    /// Like [`wait_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`wait_timeout`]: Self::wait_timeout
+    #[inline]
+    pub fn wait_until(&self, deadline: &Timespec) -> bool {
+        self.wait_optional_deadline(Some(deadline))
+    }
+
+    /// Returns `true` if the event is set, resetting it if it's auto-reset.
+    #[inline]
+    fn try_wait(&self) -> bool {
+        let mut state = self.state.load(Acquire);
+        loop {
+            if state & SET == 0 {
+                return false;
+            }
+            if state & AUTO_RESET == 0 {
+                return true;
+            }
+            match self.state.compare_exchange_weak(state, state & !SET, Acquire, Acquire) {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    fn wait_optional_deadline(&self, deadline: Option<&Timespec>) -> bool {
+        loop {
+            if self.try_wait() {
+                return true;
+            }
+
+            // Set the `WAITERS` flag, if it isn't set already.
+            let state = self.state.load(Relaxed);
+            if state & SET != 0 {
+                continue;
+            }
+            if state & WAITERS == 0
+                && self
+                    .state
+                    .compare_exchange_weak(state, state | WAITERS, Relaxed, Relaxed)
+                    .is_err()
+            {
+                continue;
+            }
+
+            if !futex_wait_timespec::<SHM, B>(&self.state, state | WAITERS, deadline) {
+                // We timed out. Make one last check before giving up.
+                return self.try_wait();
+            }
+        }
+    }
+
+    /// Wake one waiter of an auto-reset event.
+    #[cold]
+    fn wake_one(&self) {
+        if !futex_wake::<SHM, B>(&self.state) {
+            // Nobody was waiting. Clear the `WAITERS` flag, unless the state
+            // has changed since we set it. Any thread that starts waiting
+            // from here on will find the event set and not block.
+            self.state
+                .compare_exchange(WAITERS | SET | AUTO_RESET, SET | AUTO_RESET, Relaxed, Relaxed)
+                .ok();
+        }
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> Default for Event<SHM, B> {
+    /// Creates a new manual-reset event which is not set.
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> fmt::Debug for Event<SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Event")
+            .field("set", &self.is_set())
+            .field("auto_reset", &self.is_auto_reset())
+            .finish()
+    }
+}
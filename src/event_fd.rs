@@ -0,0 +1,212 @@
+//! An event backed by an eventfd.
+
+use core::fmt;
+use core::time::Duration;
+use rustix::event::{eventfd, poll, EventfdFlags, PollFd, PollFlags};
+use rustix::fd::{AsFd, BorrowedFd, OwnedFd};
+use rustix::io;
+use rustix::time::{clock_gettime, ClockId, Timespec};
+use crate::wait_wake::deadline;
+
+/// An event which threads can wait to be set, backed by an eventfd.
+///
+/// This has the same behavior as [`Event`], but since it's a file
+/// descriptor, it can also be waited for with `poll`, `epoll`, or an async
+/// reactor, through its [`AsFd`] implementation. The file descriptor is
+/// readable while the event is set.
+///
+/// For an auto-reset `EventFd`, reading from the file descriptor resets the
+/// event, so reactors waiting for it should [`reset`] it, rather than
+/// [`wait`] for it, once it becomes readable.
+///
+/// [`Event`]: crate::generic::Event
+/// [`reset`]: Self::reset
+/// [`wait`]: Self::wait
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::EventFd;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let ready = Arc::new(EventFd::new()?);
+/// let ready2 = Arc::clone(&ready);
+///
+/// let t = thread::spawn(move || {
+///     ready2.wait();
+/// });
+///
+/// ready.set();
+/// t.join().unwrap();
+/// assert!(ready.is_set());
+/// # Ok::<(), rustix::io::Errno>(())
+/// ```
+pub struct EventFd {
+    fd: OwnedFd,
+    auto_reset: bool,
+}
+
+impl EventFd {
+    /// Creates a new manual-reset event which is not set.
+    pub fn new() -> io::Result<Self> {
+        Self::with_auto_reset(false)
+    }
+
+    /// Creates a new auto-reset event which is not set.
+    pub fn new_auto_reset() -> io::Result<Self> {
+        Self::with_auto_reset(true)
+    }
+
+    fn with_auto_reset(auto_reset: bool) -> io::Result<Self> {
+        let fd = eventfd(0, EventfdFlags::CLOEXEC | EventfdFlags::NONBLOCK)?;
+        Ok(Self { fd, auto_reset })
+    }
+
+    /// Returns `true` if this is an auto-reset event.
+    #[inline]
+    pub fn is_auto_reset(&self) -> bool {
+        self.auto_reset
+    }
+
+    /// Returns `true` if the event is currently set.
+    pub fn is_set(&self) -> bool {
+        self.poll(Some(&Timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        }))
+    }
+
+    /// Sets the event.
+    ///
+    /// For a manual-reset event, this wakes all waiting threads. For an
+    /// auto-reset event, this wakes one waiting thread, if there are any.
+    /// Setting an event which is already set has no effect.
+    pub fn set(&self) {
+        match retry_on_intr(|| io::write(&self.fd, &1_u64.to_ne_bytes())) {
+            // The counter is saturated, so the event is already set.
+            Ok(_) | Err(io::Errno::AGAIN) => {}
+            Err(err) => unexpected(err),
+        }
+    }
+
+    /// Resets the event, so that threads calling [`wait`] block until it is
+    /// set again.
+    ///
+    /// [`wait`]: Self::wait
+    pub fn reset(&self) {
+        self.try_consume();
+    }
+
+    /// Blocks the current thread until the event is set.
+    ///
+    /// For an auto-reset event, this also resets the event.
+    #[inline]
+    pub fn wait(&self) {
+        self.wait_optional_deadline(None);
+    }
+
+    /// Like [`wait`], but gives up if the event is not set within `timeout`.
+    ///
+    /// Returns `true` if the event was set, and `false` on timeout.
+    ///
+    /// [`wait`]: Self::wait
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        // Overflows are rounded up to an infinite timeout.
+        self.wait_optional_deadline(deadline(timeout).as_ref())
+    }
+
+    /// Like [`wait_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`wait_timeout`]: Self::wait_timeout
+    #[inline]
+    pub fn wait_until(&self, deadline: &Timespec) -> bool {
+        self.wait_optional_deadline(Some(deadline))
+    }
+
+    fn wait_optional_deadline(&self, deadline: Option<&Timespec>) -> bool {
+        loop {
+            if self.auto_reset {
+                // Multiple waiters may be woken, but only one of them reads
+                // a nonzero count.
+                if self.try_consume() {
+                    return true;
+                }
+            } else if self.is_set() {
+                return true;
+            }
+
+            if !self.poll(deadline.map(remaining).as_ref()) {
+                // We timed out. Make one last check before giving up.
+                return if self.auto_reset {
+                    self.try_consume()
+                } else {
+                    self.is_set()
+                };
+            }
+        }
+    }
+
+    /// Reads and clears the counter, returning `true` if it was nonzero.
+    fn try_consume(&self) -> bool {
+        let mut buf = [0_u8; 8];
+        match retry_on_intr(|| io::read(&self.fd, &mut buf)) {
+            Ok(_) => true,
+            Err(io::Errno::AGAIN) => false,
+            Err(err) => unexpected(err),
+        }
+    }
+
+    /// Wait for the eventfd to become readable, with a relative timeout.
+    /// Returns `true` if it's readable.
+    fn poll(&self, timeout: Option<&Timespec>) -> bool {
+        let mut fds = [PollFd::new(&self.fd, PollFlags::IN)];
+        match retry_on_intr(|| poll(&mut fds, timeout)) {
+            Ok(n) => n != 0,
+            Err(err) => unexpected(err),
+        }
+    }
+}
+
+/// Compute the time remaining until `deadline`, or zero if it has passed.
+fn remaining(deadline: &Timespec) -> Timespec {
+    let now = clock_gettime(ClockId::Monotonic);
+    let deadline = Duration::new(deadline.tv_sec as u64, deadline.tv_nsec as _);
+    let now = Duration::new(now.tv_sec as u64, now.tv_nsec as _);
+    let left = deadline.saturating_sub(now);
+    Timespec {
+        tv_sec: left.as_secs() as i64,
+        tv_nsec: left.subsec_nanos() as _,
+    }
+}
+
+fn retry_on_intr<T>(mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    loop {
+        match f() {
+            Err(io::Errno::INTR) => continue,
+            result => return result,
+        }
+    }
+}
+
+#[cold]
+fn unexpected(err: io::Errno) -> ! {
+    panic!("unexpected error from eventfd: {:?}", err)
+}
+
+impl AsFd for EventFd {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl fmt::Debug for EventFd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventFd")
+            .field("fd", &self.fd)
+            .field("auto_reset", &self.auto_reset)
+            .finish()
+    }
+}
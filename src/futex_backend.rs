@@ -0,0 +1,91 @@
+//! Pluggable futex backends.
+
+use core::sync::atomic::AtomicU32;
+use rustix::time::Timespec;
+
+/// The futex operations that this crate's primitives are built on.
+///
+/// Each primitive in [`generic`] takes a `B: FutexBackend` type parameter,
+/// which defaults to [`SyscallBackend`]. Implement this trait to run the
+/// primitives on something else, such as a userspace simulation for
+/// deterministic testing, or an OS with futex-like system calls under a
+/// different ABI.
+///
+/// The operations don't take `self`, so that the primitives can be created
+/// in `const` contexts and stay the same size. A backend with state can keep
+/// it in a `static`.
+///
+/// `shared` is the primitive's `SHM` parameter: `true` for the types in the
+/// `shm` module, which may be used on memory shared with other processes,
+/// and `false` otherwise.
+///
+/// Implementations don't need to be perfectly precise; spurious wakeups are
+/// handled by the primitives. They do need to avoid lost wakeups: a `wake`
+/// that follows a change to the futex's value must wake any thread that
+/// saw the old value in `wait`.
+///
+/// [`generic`]: crate::generic
+///
+/// # Examples
+///
+/// A backend that spins instead of sleeping:
+///
+/// ```
+/// use rustix_futex_sync::generic::RawMutex;
+/// use rustix_futex_sync::lock_api;
+/// use rustix_futex_sync::{FutexBackend, Timespec};
+/// use std::sync::atomic::{AtomicU32, Ordering};
+///
+/// struct Spin;
+///
+/// impl FutexBackend for Spin {
+///     fn wait(futex: &AtomicU32, expected: u32, _deadline: Option<&Timespec>, _shared: bool) -> bool {
+///         while futex.load(Ordering::SeqCst) == expected {
+///             std::thread::yield_now();
+///         }
+///         true
+///     }
+///
+///     fn wake(_futex: &AtomicU32, n: u32, _shared: bool) -> u32 {
+///         n
+///     }
+/// }
+///
+/// let mutex = lock_api::Mutex::<RawMutex<false, Spin>, _>::new(0);
+/// *mutex.lock() += 1;
+/// assert_eq!(*mutex.lock(), 1);
+/// ```
+pub trait FutexBackend {
+    /// Wait for a [`wake`] on `futex`.
+    ///
+    /// Returns immediately if `futex` doesn't hold `expected`. `deadline`, if
+    /// present, is a time on the [`ClockId::Monotonic`] clock to stop waiting
+    /// at.
+    ///
+    /// Returns `false` on timeout, and `true` in all other cases.
+    ///
+    /// [`wake`]: Self::wake
+    /// [`ClockId::Monotonic`]: rustix::time::ClockId::Monotonic
+    fn wait(futex: &AtomicU32, expected: u32, deadline: Option<&Timespec>, shared: bool) -> bool;
+
+    /// Wake up at most `n` threads that are waiting on `futex`.
+    ///
+    /// Returns the number of threads woken, or, if the backend can't tell,
+    /// any number that is nonzero if a thread may have been woken.
+    fn wake(futex: &AtomicU32, n: u32, shared: bool) -> u32;
+
+    /// Wake up all threads that are waiting on `futex`.
+    #[inline]
+    fn wake_all(futex: &AtomicU32, shared: bool) {
+        Self::wake(futex, u32::MAX, shared);
+    }
+}
+
+/// The default [`FutexBackend`], which uses Linux's `futex` system call, via
+/// [`rustix::thread::futex`].
+///
+/// When `shared` is false, this sets the `FUTEX_PRIVATE_FLAG` flag.
+///
+/// Under Miri, which can't run the `futex` system call, this instead uses an
+/// emulation built on `std`'s `Mutex` and `Condvar`.
+pub struct SyscallBackend(());
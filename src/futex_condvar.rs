@@ -2,24 +2,41 @@
 //! library/std/src/sys/sync/condvar/futex.rs at revision
 //! 22a5267c83a3e17f2b763279eb24bb632c45dc6b.
 
-use core::sync::atomic::{AtomicU32, Ordering::Relaxed};
-use super::wait_wake::{futex_wait, futex_wake, futex_wake_all};
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering::Relaxed;
+use super::atomic::{futex_wait, futex_wake, futex_wake_all, AtomicU32};
 use core::time::Duration;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
 use super::generic::RawMutex;
 use super::lock_api::RawMutex as _;
 
 #[repr(transparent)]
-pub struct Condvar<const SHM: bool> {
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Condvar<const SHM: bool, B = SyscallBackend> {
     // The value of this atomic is simply incremented on every notification.
     // This is used by `.wait()` to not miss any notifications after
     // unlocking the mutex and before waiting for notifications.
     futex: AtomicU32,
+    backend: PhantomData<B>,
 }
 
-impl<const SHM: bool> Condvar<SHM> {
+impl<const SHM: bool, B: FutexBackend> Default for Condvar<SHM, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> Condvar<SHM, B> {
     #[inline]
     pub const fn new() -> Self {
-        Self { futex: AtomicU32::new(0) }
+        Self { futex: AtomicU32::new(0), backend: PhantomData }
+    }
+
+    #[cfg(feature = "io_uring")]
+    #[inline]
+    pub(crate) fn futex(&self) -> &AtomicU32 {
+        &self.futex
     }
 
     // All the memory orderings here are `Relaxed`,
@@ -27,23 +44,29 @@ impl<const SHM: bool> Condvar<SHM> {
 
     pub fn notify_one(&self) {
         self.futex.fetch_add(1, Relaxed);
-        futex_wake::<SHM>(&self.futex);
+        futex_wake::<SHM, B>(&self.futex);
     }
 
     pub fn notify_all(&self) {
         self.futex.fetch_add(1, Relaxed);
-        futex_wake_all::<SHM>(&self.futex);
+        futex_wake_all::<SHM, B>(&self.futex);
     }
 
-    pub unsafe fn wait(&self, mutex: &RawMutex<SHM>) {
+    /// # Safety
+    ///
+    /// `mutex` must be locked by the current thread.
+    pub unsafe fn wait(&self, mutex: &RawMutex<SHM, B>) {
         self.wait_optional_timeout(mutex, None);
     }
 
-    pub unsafe fn wait_timeout(&self, mutex: &RawMutex<SHM>, timeout: Duration) -> bool {
+    /// # Safety
+    ///
+    /// `mutex` must be locked by the current thread.
+    pub unsafe fn wait_timeout(&self, mutex: &RawMutex<SHM, B>, timeout: Duration) -> bool {
         self.wait_optional_timeout(mutex, Some(timeout))
     }
 
-    unsafe fn wait_optional_timeout(&self, mutex: &RawMutex<SHM>, timeout: Option<Duration>) -> bool {
+    unsafe fn wait_optional_timeout(&self, mutex: &RawMutex<SHM, B>, timeout: Option<Duration>) -> bool {
         // Examine the notification counter _before_ we unlock the mutex.
         let futex_value = self.futex.load(Relaxed);
 
@@ -52,7 +75,7 @@ impl<const SHM: bool> Condvar<SHM> {
 
         // Wait, but only if there hasn't been any
         // notification since we unlocked the mutex.
-        let r = futex_wait::<SHM>(&self.futex, futex_value, timeout);
+        let r = futex_wait::<SHM, B>(&self.futex, futex_value, timeout);
 
         // Lock the mutex again.
         mutex.lock();
@@ -2,28 +2,29 @@
 //! library/std/src/sys/sync/mutex/futex.rs at revision
 //! 22a5267c83a3e17f2b763279eb24bb632c45dc6b.
 
-use core::sync::atomic::{
-    self,
-    Ordering::{Acquire, Relaxed, Release},
-};
-use super::wait_wake::{futex_wait_timespec, futex_wake};
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use super::atomic::{self, futex_wait_timespec, futex_wake, spin_loop, SPIN_LIMIT};
 
 type Atomic = atomic::AtomicU32;
 type State = u32;
 
 #[repr(transparent)]
-pub struct Mutex<const SHM: bool> {
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Mutex<const SHM: bool, B = SyscallBackend> {
     futex: Atomic,
+    backend: PhantomData<B>,
 }
 
 const UNLOCKED: State = 0;
 const LOCKED: State = 1; // locked, no other threads waiting
 const CONTENDED: State = 2; // locked, and other threads waiting (contended)
 
-impl<const SHM: bool> Mutex<SHM> {
+impl<const SHM: bool, B: FutexBackend> Mutex<SHM, B> {
     #[inline]
     pub const fn new() -> Self {
-        Self { futex: Atomic::new(UNLOCKED) }
+        Self { futex: Atomic::new(UNLOCKED), backend: PhantomData }
     }
 
     #[inline]
@@ -31,6 +32,23 @@ impl<const SHM: bool> Mutex<SHM> {
         self.futex.compare_exchange(UNLOCKED, LOCKED, Acquire, Relaxed).is_ok()
     }
 
+    /// Like `try_lock`, but if the lock is held, mark it as contended. This
+    /// is for waiters that aren't blocked in `futex_wait`, which need the
+    /// unlocking thread to take the slow path to wake them.
+    #[cfg(any(feature = "alloc", feature = "io_uring"))]
+    #[inline]
+    pub fn try_lock_contended(&self) -> bool {
+        self.futex.swap(CONTENDED, Acquire) == UNLOCKED
+    }
+
+    /// Returns the futex word, and the value that waiters wait for it to
+    /// change from.
+    #[cfg(feature = "io_uring")]
+    #[inline]
+    pub fn contended_futex(&self) -> (&Atomic, State) {
+        (&self.futex, CONTENDED)
+    }
+
     #[inline]
     pub fn lock(&self) {
         if self.futex.compare_exchange(UNLOCKED, LOCKED, Acquire, Relaxed).is_err() {
@@ -62,7 +80,7 @@ impl<const SHM: bool> Mutex<SHM> {
             }
 
             // Wait for the futex to change state, assuming it is still CONTENDED.
-            futex_wait_timespec::<SHM>(&self.futex, CONTENDED, None);
+            futex_wait_timespec::<SHM, B>(&self.futex, CONTENDED, None);
 
             // Spin again after waking up.
             state = self.spin();
@@ -70,7 +88,7 @@ impl<const SHM: bool> Mutex<SHM> {
     }
 
     fn spin(&self) -> State {
-        let mut spin = 100;
+        let mut spin = SPIN_LIMIT;
         loop {
             // We only use `load` (and not `swap` or `compare_exchange`)
             // while spinning, to be easier on the caches.
@@ -82,7 +100,7 @@ impl<const SHM: bool> Mutex<SHM> {
                 return state;
             }
 
-            core::hint::spin_loop();
+            spin_loop();
             spin -= 1;
         }
     }
@@ -98,8 +116,21 @@ impl<const SHM: bool> Mutex<SHM> {
         }
     }
 
+    /// Like `unlock`, but returns `true` if the mutex was contended, in which
+    /// case waiters that aren't blocked in `futex_wait` should be woken too.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub unsafe fn unlock_contended(&self) -> bool {
+        if self.futex.swap(UNLOCKED, Release) == CONTENDED {
+            self.wake();
+            true
+        } else {
+            false
+        }
+    }
+
     #[cold]
     fn wake(&self) {
-        futex_wake::<SHM>(&self.futex);
+        futex_wake::<SHM, B>(&self.futex);
     }
 }
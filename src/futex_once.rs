@@ -1,18 +1,18 @@
 //! The following is derived from Rust's
 //! library/std/src/sys/sync/once/futex.rs at revision
-//! 22a5267c83a3e17f2b763279eb24bb632c45dc6b.
+//! 59807616e1fa2540724bfbac14d7976d7e4a3860.
 
 use core::cell::Cell;
+use core::marker::PhantomData;
 use crate as public;
-use core::sync::atomic::{
-    AtomicU32,
-    Ordering::{Acquire, Relaxed, Release},
-};
-//use super::once::ExclusiveState;
-use super::wait_wake::{futex_wait, futex_wake_all};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use rustix::time::Timespec;
+use super::atomic::{futex_wait_timespec, futex_wake_all, AtomicU32};
+use super::once::ExclusiveState;
 
 // On some platforms, the OS is very nice and handles the waiter queue for us.
-// This means we only need one atomic value with 5 states:
+// This means we only need one atomic value with 4 states:
 
 /// No initialization has run yet, and no thread is currently using the Once.
 const INCOMPLETE: u32 = 0;
@@ -23,85 +23,195 @@ const POISONED: u32 = 1;
 /// Some thread is currently attempting to run initialization. It may succeed,
 /// so all future threads need to wait for it to finish.
 const RUNNING: u32 = 2;
-/// Some thread is currently attempting to run initialization and there are threads
-/// waiting for it to finish.
-const QUEUED: u32 = 3;
 /// Initialization has completed and all future calls should finish immediately.
-const COMPLETE: u32 = 4;
+const COMPLETE: u32 = 3;
 
-// Threads wait by setting the state to QUEUED and calling `futex_wait` on the state
+// An additional bit indicates whether there are waiting threads:
+
+/// May only be set if the state is not COMPLETE.
+const QUEUED: u32 = 4;
+
+// Threads wait by setting the QUEUED bit and calling `futex_wait` on the state
 // variable. When the running thread finishes, it will wake all waiting threads using
 // `futex_wake_all`.
 
+const STATE_MASK: u32 = 0b11;
+
 pub struct OnceState {
-    //poisoned: bool,
+    poisoned: bool,
+    recovered: bool,
     set_state_to: Cell<u32>,
 }
 
 impl OnceState {
-/*
+    /// Creates the state for an initialization run by `RobustOnce`, which
+    /// uses the same `POISONED` and `COMPLETE` values.
+    #[cfg(feature = "shm")]
+    #[inline]
+    pub(crate) fn new(poisoned: bool, recovered: bool) -> Self {
+        OnceState { poisoned, recovered, set_state_to: Cell::new(COMPLETE) }
+    }
+
+    #[cfg(feature = "shm")]
+    #[inline]
+    pub(crate) fn set_state_to(&self) -> u32 {
+        self.set_state_to.get()
+    }
+
     #[inline]
     pub fn is_poisoned(&self) -> bool {
         self.poisoned
     }
 
     #[inline]
-    pub fn poison(&self) {
-        self.set_state_to.set(POISONED);
+    pub fn is_recovered(&self) -> bool {
+        self.recovered
     }
-*/
 
     #[inline]
-    pub fn set_incomplete(&self) {
-        self.set_state_to.set(INCOMPLETE);
+    pub fn poison(&self) {
+        self.set_state_to.set(POISONED);
     }
 }
 
-struct CompletionGuard<'a, const SHM: bool> {
-    state: &'a AtomicU32,
+struct CompletionGuard<'a, const SHM: bool, B: FutexBackend> {
+    state_and_queued: &'a AtomicU32,
     set_state_on_drop_to: u32,
+    backend: PhantomData<B>,
 }
 
-impl<'a, const SHM: bool> Drop for CompletionGuard<'a, SHM> {
+impl<'a, const SHM: bool, B: FutexBackend> Drop for CompletionGuard<'a, SHM, B> {
     fn drop(&mut self) {
         // Use release ordering to propagate changes to all threads checking
         // up on the Once. `futex_wake_all` does its own synchronization, hence
         // we do not need `AcqRel`.
-        if self.state.swap(self.set_state_on_drop_to, Release) == QUEUED {
-            futex_wake_all::<SHM>(&self.state);
+        if self.state_and_queued.swap(self.set_state_on_drop_to, Release) & QUEUED != 0 {
+            futex_wake_all::<SHM, B>(self.state_and_queued);
         }
     }
 }
 
 #[repr(transparent)]
-pub struct Once<const SHM: bool> {
-    state: AtomicU32,
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Once<const SHM: bool, B = SyscallBackend> {
+    state_and_queued: AtomicU32,
+    backend: PhantomData<B>,
 }
 
-impl<const SHM: bool> Once<SHM> {
+impl<const SHM: bool, B: FutexBackend> Once<SHM, B> {
     #[inline]
-    pub const fn new() -> Once<SHM> {
-        Once { state: AtomicU32::new(INCOMPLETE) }
+    pub const fn new() -> Once<SHM, B> {
+        Once { state_and_queued: AtomicU32::new(INCOMPLETE), backend: PhantomData }
     }
 
     #[inline]
     pub fn is_completed(&self) -> bool {
         // Use acquire ordering to make all initialization changes visible to the
         // current thread.
-        self.state.load(Acquire) == COMPLETE
+        self.state_and_queued.load(Acquire) == COMPLETE
     }
 
-    /*
     #[inline]
-    pub(crate) fn state(&mut self) -> ExclusiveState {
-        match *self.state.get_mut() {
+    pub fn state(&mut self) -> ExclusiveState {
+        // A waiter which timed out may have left the QUEUED bit set.
+        match *self.state_and_queued.get_mut() & STATE_MASK {
             INCOMPLETE => ExclusiveState::Incomplete,
             POISONED => ExclusiveState::Poisoned,
             COMPLETE => ExclusiveState::Complete,
             _ => unreachable!("invalid Once state"),
         }
     }
-    */
+
+    #[inline]
+    pub fn set_state(&mut self, new_state: ExclusiveState) {
+        // No thread can be waiting, so this also clears any QUEUED bit.
+        *self.state_and_queued.get_mut() = match new_state {
+            ExclusiveState::Incomplete => INCOMPLETE,
+            ExclusiveState::Poisoned => POISONED,
+            ExclusiveState::Complete => COMPLETE,
+        };
+    }
+
+    /// Wait for the `Once` to be completed, or until `deadline`, an absolute
+    /// time on the monotonic clock, has passed.
+    ///
+    /// Returns `false` on timeout, and `true` if the `Once` is complete.
+    #[cold]
+    #[track_caller]
+    pub fn wait(&self, ignore_poisoning: bool, deadline: Option<&Timespec>) -> bool {
+        let mut state_and_queued = self.state_and_queued.load(Acquire);
+        loop {
+            let state = state_and_queued & STATE_MASK;
+            let queued = state_and_queued & QUEUED != 0;
+            match state {
+                COMPLETE => return true,
+                POISONED if !ignore_poisoning => {
+                    // Panic to propagate the poison.
+                    panic!("Once instance has previously been poisoned");
+                }
+                _ => {
+                    // Set the QUEUED bit if it has not already been set.
+                    if !queued {
+                        state_and_queued += QUEUED;
+                        if let Err(new) = self.state_and_queued.compare_exchange_weak(
+                            state,
+                            state_and_queued,
+                            Relaxed,
+                            Acquire,
+                        ) {
+                            state_and_queued = new;
+                            continue;
+                        }
+                    }
+
+                    let woken = futex_wait_timespec::<SHM, B>(&self.state_and_queued, state_and_queued, deadline);
+                    state_and_queued = self.state_and_queued.load(Acquire);
+                    if !woken && state_and_queued != COMPLETE {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Like `wait`, but instead of waiting, returns the futex word and the
+    /// value to wait for it to change from, for waiters which wait somewhere
+    /// other than `futex_wait`, such as in io_uring.
+    ///
+    /// Returns `None` if the `Once` is complete.
+    #[cfg(feature = "io_uring")]
+    #[track_caller]
+    pub fn prepare_wait(&self, ignore_poisoning: bool) -> Option<(&AtomicU32, u32)> {
+        let mut state_and_queued = self.state_and_queued.load(Acquire);
+        loop {
+            let state = state_and_queued & STATE_MASK;
+            let queued = state_and_queued & QUEUED != 0;
+            match state {
+                COMPLETE => return None,
+                POISONED if !ignore_poisoning => {
+                    // Panic to propagate the poison.
+                    panic!("Once instance has previously been poisoned");
+                }
+                _ => {
+                    // Set the QUEUED bit if it has not already been set.
+                    if !queued {
+                        state_and_queued += QUEUED;
+                        if let Err(new) = self.state_and_queued.compare_exchange_weak(
+                            state,
+                            state_and_queued,
+                            Relaxed,
+                            Acquire,
+                        ) {
+                            state_and_queued = new;
+                            continue;
+                        }
+                    }
+
+                    return Some((&self.state_and_queued, state_and_queued));
+                }
+            }
+        }
+    }
 
     // This uses FnMut to match the API of the generic implementation. As this
     // implementation is quite light-weight, it is generic over the closure and
@@ -109,30 +219,40 @@ impl<const SHM: bool> Once<SHM> {
     #[cold]
     #[track_caller]
     pub fn call(&self, ignore_poisoning: bool, f: &mut impl FnMut(&public::OnceState)) {
-        let mut state = self.state.load(Acquire);
+        let mut state_and_queued = self.state_and_queued.load(Acquire);
         loop {
+            let state = state_and_queued & STATE_MASK;
+            let queued = state_and_queued & QUEUED != 0;
             match state {
+                COMPLETE => return,
                 POISONED if !ignore_poisoning => {
                     // Panic to propagate the poison.
                     panic!("Once instance has previously been poisoned");
                 }
                 INCOMPLETE | POISONED => {
                     // Try to register the current thread as the one running.
-                    if let Err(new) =
-                        self.state.compare_exchange_weak(state, RUNNING, Acquire, Acquire)
-                    {
-                        state = new;
+                    let next = RUNNING + if queued { QUEUED } else { 0 };
+                    if let Err(new) = self.state_and_queued.compare_exchange_weak(
+                        state_and_queued,
+                        next,
+                        Acquire,
+                        Acquire,
+                    ) {
+                        state_and_queued = new;
                         continue;
                     }
                     // `waiter_queue` will manage other waiting threads, and
                     // wake them up on drop.
-                    let mut waiter_queue =
-                        //CompletionGuard { state: &self.state, set_state_on_drop_to: POISONED };
-                        CompletionGuard::<SHM> { state: &self.state, set_state_on_drop_to: INCOMPLETE };
+                    let mut waiter_queue = CompletionGuard::<SHM, B> {
+                        state_and_queued: &self.state_and_queued,
+                        set_state_on_drop_to: POISONED,
+                        backend: PhantomData,
+                    };
                     // Run the function, letting it know if we're poisoned or not.
                     let f_state = public::OnceState {
                         inner: OnceState {
-                            //poisoned: state == POISONED,
+                            poisoned: state == POISONED,
+                            recovered: false,
                             set_state_to: Cell::new(COMPLETE),
                         },
                     };
@@ -140,20 +260,27 @@ impl<const SHM: bool> Once<SHM> {
                     waiter_queue.set_state_on_drop_to = f_state.inner.set_state_to.get();
                     return;
                 }
-                RUNNING | QUEUED => {
-                    // Set the state to QUEUED if it is not already.
-                    if state == RUNNING {
-                        if let Err(new) = self.state.compare_exchange_weak(RUNNING, QUEUED, Relaxed, Acquire) {
-                            state = new;
+                _ => {
+                    // All other values must be RUNNING.
+                    assert!(state == RUNNING);
+
+                    // Set the QUEUED bit if it is not already set.
+                    if !queued {
+                        state_and_queued += QUEUED;
+                        if let Err(new) = self.state_and_queued.compare_exchange_weak(
+                            state,
+                            state_and_queued,
+                            Relaxed,
+                            Acquire,
+                        ) {
+                            state_and_queued = new;
                             continue;
                         }
                     }
 
-                    futex_wait::<SHM>(&self.state, QUEUED, None);
-                    state = self.state.load(Acquire);
+                    futex_wait_timespec::<SHM, B>(&self.state_and_queued, state_and_queued, None);
+                    state_and_queued = self.state_and_queued.load(Acquire);
                 }
-                COMPLETE => return,
-                _ => unreachable!("state is never set to invalid values"),
             }
         }
     }
@@ -2,14 +2,16 @@
 //! library/std/src/sys/sync/rwlock/futex.rs at revision
 //! 22a5267c83a3e17f2b763279eb24bb632c45dc6b.
 
-use core::sync::atomic::{
-    AtomicU32,
-    Ordering::{Acquire, Relaxed, Release},
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use super::atomic::{
+    futex_wait_timespec, futex_wake, futex_wake_all, spin_loop, AtomicU32, SPIN_LIMIT,
 };
-use super::wait_wake::{futex_wait_timespec, futex_wake, futex_wake_all};
 
 #[repr(C)]
-pub struct RwLock<const SHM: bool> {
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct RwLock<const SHM: bool, B = SyscallBackend> {
     // The state consists of a 30-bit reader counter, a 'readers waiting' flag, and a 'writers waiting' flag.
     // Bits 0..30:
     //   0: Unlocked
@@ -21,6 +23,7 @@ pub struct RwLock<const SHM: bool> {
     // The 'condition variable' to notify writers through.
     // Incremented on every signal.
     writer_notify: AtomicU32,
+    backend: PhantomData<B>,
 }
 
 const READ_LOCKED: u32 = 1;
@@ -66,10 +69,14 @@ fn has_reached_max_readers(state: u32) -> bool {
     state & MASK == MAX_READERS
 }
 
-impl<const SHM: bool> RwLock<SHM> {
+impl<const SHM: bool, B: FutexBackend> RwLock<SHM, B> {
     #[inline]
     pub const fn new() -> Self {
-        Self { state: AtomicU32::new(0), writer_notify: AtomicU32::new(0) }
+        Self {
+            state: AtomicU32::new(0),
+            writer_notify: AtomicU32::new(0),
+            backend: PhantomData,
+        }
     }
 
     #[inline]
@@ -139,7 +146,7 @@ impl<const SHM: bool> RwLock<SHM> {
             }
 
             // Wait for the state to change.
-            futex_wait_timespec::<SHM>(&self.state, state | READERS_WAITING, None);
+            futex_wait_timespec::<SHM, B>(&self.state, state | READERS_WAITING, None);
 
             // Spin again after waking up.
             state = self.spin_read();
@@ -220,7 +227,7 @@ impl<const SHM: bool> RwLock<SHM> {
             }
 
             // Wait for the state to change.
-            futex_wait_timespec::<SHM>(&self.writer_notify, seq, None);
+            futex_wait_timespec::<SHM, B>(&self.writer_notify, seq, None);
 
             // Spin again after waking up.
             state = self.spin_write();
@@ -274,10 +281,8 @@ impl<const SHM: bool> RwLock<SHM> {
         }
 
         // If readers are waiting, wake them all up.
-        if state == READERS_WAITING {
-            if self.state.compare_exchange(state, 0, Relaxed, Relaxed).is_ok() {
-                futex_wake_all::<SHM>(&self.state);
-            }
+        if state == READERS_WAITING && self.state.compare_exchange(state, 0, Relaxed, Relaxed).is_ok() {
+            futex_wake_all::<SHM, B>(&self.state);
         }
     }
 
@@ -288,7 +293,7 @@ impl<const SHM: bool> RwLock<SHM> {
     /// writer that was about to go to sleep.
     fn wake_writer(&self) -> bool {
         self.writer_notify.fetch_add(1, Release);
-        futex_wake::<SHM>(&self.writer_notify)
+        futex_wake::<SHM, B>(&self.writer_notify)
         // Note that FreeBSD and DragonFlyBSD don't tell us whether they woke
         // up any threads or not, and always return `false` here. That still
         // results in correct behaviour: it just means readers get woken up as
@@ -298,13 +303,13 @@ impl<const SHM: bool> RwLock<SHM> {
     /// Spin for a while, but stop directly at the given condition.
     #[inline]
     fn spin_until(&self, f: impl Fn(u32) -> bool) -> u32 {
-        let mut spin = 100; // Chosen by fair dice roll.
+        let mut spin = SPIN_LIMIT;
         loop {
             let state = self.state.load(Relaxed);
             if f(state) || spin == 0 {
                 return state;
             }
-            core::hint::spin_loop();
+            spin_loop();
             spin -= 1;
         }
     }
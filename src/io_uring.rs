@@ -0,0 +1,368 @@
+//! Futex operations for io_uring.
+//!
+//! Linux 6.7 added `IORING_OP_FUTEX_WAIT`, `IORING_OP_FUTEX_WAKE`, and
+//! `IORING_OP_FUTEX_WAITV`, which let a completion-based runtime wait for a
+//! futex without blocking a thread. The functions in this module perform the
+//! first half of the contended path of [`RawMutex`], [`RawCondvar`], and
+//! [`Once`], and return a [`FutexWait`] describing the futex word to wait
+//! on, in place of the `futex_wait` call the blocking path would make. Once
+//! the wait completes, the caller resumes the operation, by calling
+//! [`mutex_lock_after_wait`] for a mutex, or the same function again
+//! otherwise.
+//!
+//! The futex words are the same ones the blocking paths use, so io_uring
+//! waiters and blocked threads can wait on the same primitive, and are woken
+//! by the same notifications.
+//!
+//! The SQEs built here are zero-initialized apart from the futex fields. The
+//! caller fills in `user_data` and any other fields its runtime uses, and
+//! must keep the futex word, and for `IORING_OP_FUTEX_WAITV` the array of
+//! waits, alive until the operation completes.
+//!
+//! [`RawMutex`]: crate::generic::RawMutex
+//! [`RawCondvar`]: crate::generic::RawCondvar
+//! [`Once`]: crate::generic::Once
+//!
+//! # Examples
+//!
+//! ```
+//! use rustix_futex_sync::io_uring;
+//! use rustix_futex_sync::lock_api::RawMutex as _;
+//! use rustix_futex_sync::RawMutex;
+//!
+//! let mutex = RawMutex::INIT;
+//! mutex.lock();
+//!
+//! // The mutex is locked, so we get a futex to wait on.
+//! let wait = io_uring::mutex_lock(&mutex).unwrap();
+//! let sqe = wait.to_sqe();
+//! // ... Submit `sqe`, and when it completes, call `mutex_lock_after_wait`.
+//! # let _ = sqe;
+//! ```
+
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering::Relaxed};
+use rustix::fd::RawFd;
+use rustix::io_uring::{addr3_struct, io_uring_ptr, io_uring_sqe, FutexWaitFlags, IoringOp};
+use rustix::thread::futex;
+use crate::generic::{Once, RawCondvar, RawMutex};
+
+/// The futex2 bitset which matches all waiters, `FUTEX_BITSET_MATCH_ANY`.
+const MATCH_ANY: u64 = u32::MAX as u64;
+
+/// The most waits `IORING_OP_FUTEX_WAITV` accepts, `FUTEX_WAITV_MAX`.
+pub const WAITV_MAX: usize = 128;
+
+/// A futex wait: the futex word to wait on, and the value it's expected to
+/// hold.
+///
+/// This can be submitted as an `IORING_OP_FUTEX_WAIT` with [`to_sqe`], or as
+/// one of the waits of an `IORING_OP_FUTEX_WAITV` with [`to_waitv`].
+///
+/// [`to_sqe`]: Self::to_sqe
+/// [`to_waitv`]: Self::to_waitv
+#[derive(Clone, Copy)]
+pub struct FutexWait<'a> {
+    futex: &'a AtomicU32,
+    expected: u32,
+    shm: bool,
+}
+
+impl<'a> FutexWait<'a> {
+    fn new<const SHM: bool>(futex: &'a AtomicU32, expected: u32) -> Self {
+        Self {
+            futex,
+            expected,
+            shm: SHM,
+        }
+    }
+
+    /// Returns the futex word.
+    #[inline]
+    pub fn futex(&self) -> &'a AtomicU32 {
+        self.futex
+    }
+
+    /// Returns the address of the futex word.
+    #[inline]
+    pub fn addr(&self) -> *mut u32 {
+        self.futex.as_ptr()
+    }
+
+    /// Returns the value the wait expects the futex word to hold. If it holds
+    /// a different value, the wait completes immediately with `EAGAIN`.
+    #[inline]
+    pub fn expected(&self) -> u32 {
+        self.expected
+    }
+
+    /// Returns `true` if the futex is a shared-memory futex, which is waited
+    /// on without `FUTEX2_PRIVATE`.
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        self.shm
+    }
+
+    /// Returns the futex2 flags for the wait.
+    #[inline]
+    pub fn flags(&self) -> FutexWaitFlags {
+        flags(self.shm)
+    }
+
+    /// Returns `true` if the futex word no longer holds the expected value,
+    /// so there's no need to wait.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.futex.load(Relaxed) != self.expected
+    }
+
+    /// Builds an `IORING_OP_FUTEX_WAIT` SQE for this wait.
+    pub fn to_sqe(&self) -> io_uring_sqe {
+        let mut sqe = sqe(IoringOp::FutexWait, self.futex, self.shm);
+        sqe.off_or_addr2.off = u64::from(self.expected);
+        sqe
+    }
+
+    /// Builds a wait entry for use with [`waitv_sqe`].
+    pub fn to_waitv(&self) -> futex::Wait {
+        let mut wait = futex::Wait::new();
+        wait.val = u64::from(self.expected);
+        wait.uaddr = futex::WaitPtr::new(self.addr().cast());
+        wait.flags = self.flags();
+        wait
+    }
+}
+
+impl<'a> fmt::Debug for FutexWait<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FutexWait")
+            .field("addr", &self.addr())
+            .field("expected", &self.expected)
+            .field("shared", &self.shm)
+            .finish()
+    }
+}
+
+/// A futex wake: the futex word to wake waiters of, and how many to wake.
+///
+/// This can be submitted as an `IORING_OP_FUTEX_WAKE` with [`to_sqe`].
+///
+/// [`to_sqe`]: Self::to_sqe
+#[derive(Clone, Copy)]
+pub struct FutexWake<'a> {
+    futex: &'a AtomicU32,
+    count: u32,
+    shm: bool,
+}
+
+impl<'a> FutexWake<'a> {
+    fn new<const SHM: bool>(futex: &'a AtomicU32, count: u32) -> Self {
+        Self {
+            futex,
+            count,
+            shm: SHM,
+        }
+    }
+
+    /// Returns the futex word.
+    #[inline]
+    pub fn futex(&self) -> &'a AtomicU32 {
+        self.futex
+    }
+
+    /// Returns the address of the futex word.
+    #[inline]
+    pub fn addr(&self) -> *mut u32 {
+        self.futex.as_ptr()
+    }
+
+    /// Returns the maximum number of waiters to wake.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns `true` if the futex is a shared-memory futex, which is woken
+    /// without `FUTEX2_PRIVATE`.
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        self.shm
+    }
+
+    /// Returns the futex2 flags for the wake.
+    #[inline]
+    pub fn flags(&self) -> FutexWaitFlags {
+        flags(self.shm)
+    }
+
+    /// Builds an `IORING_OP_FUTEX_WAKE` SQE for this wake.
+    pub fn to_sqe(&self) -> io_uring_sqe {
+        let mut sqe = sqe(IoringOp::FutexWake, self.futex, self.shm);
+        sqe.off_or_addr2.off = u64::from(self.count);
+        sqe
+    }
+}
+
+impl<'a> fmt::Debug for FutexWake<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FutexWake")
+            .field("addr", &self.addr())
+            .field("count", &self.count)
+            .field("shared", &self.shm)
+            .finish()
+    }
+}
+
+/// Builds an `IORING_OP_FUTEX_WAITV` SQE, which completes when any of the
+/// futexes in `waits` is woken, with the index of that futex.
+///
+/// `waits` must stay alive until the operation completes.
+///
+/// # Panics
+///
+/// Panics if `waits` has more than [`WAITV_MAX`] entries.
+pub fn waitv_sqe(waits: &[futex::Wait]) -> io_uring_sqe {
+    assert!(waits.len() <= WAITV_MAX, "too many futex waits");
+
+    let mut sqe = io_uring_sqe {
+        opcode: IoringOp::FutexWaitv,
+        ..Default::default()
+    };
+    sqe.addr_or_splice_off_in.addr = io_uring_ptr::new(waits.as_ptr() as *mut _);
+    sqe.len.len = waits.len() as u32;
+    sqe
+}
+
+fn flags(shm: bool) -> FutexWaitFlags {
+    if shm {
+        FutexWaitFlags::SIZE_U32
+    } else {
+        FutexWaitFlags::SIZE_U32 | FutexWaitFlags::PRIVATE
+    }
+}
+
+fn sqe(opcode: IoringOp, futex: &AtomicU32, shm: bool) -> io_uring_sqe {
+    let mut addr3 = addr3_struct::default();
+    addr3.addr3 = MATCH_ANY;
+
+    let mut sqe = io_uring_sqe {
+        opcode,
+        fd: flags(shm).bits() as RawFd,
+        ..Default::default()
+    };
+    sqe.addr_or_splice_off_in.addr = io_uring_ptr::new(futex.as_ptr().cast());
+    sqe.addr3_or_cmd.addr3 = addr3;
+    sqe
+}
+
+/// Attempts to lock `mutex` on its contended path.
+///
+/// Returns `None` if the mutex is now locked. Otherwise, returns the futex
+/// wait to submit, after which the caller should call
+/// [`mutex_lock_after_wait`].
+///
+/// This marks the mutex as contended, so the thread which unlocks it will
+/// wake a waiter.
+pub fn mutex_lock<const SHM: bool>(mutex: &RawMutex<SHM>) -> Option<FutexWait<'_>> {
+    if mutex.futex_mutex().try_lock() {
+        return None;
+    }
+
+    mutex_lock_after_wait(mutex)
+}
+
+/// Attempts to lock `mutex` again, after a wait returned by [`mutex_lock`]
+/// or by this function has completed.
+///
+/// Returns `None` if the mutex is now locked. Otherwise, returns the futex
+/// wait to submit, after which the caller should call this function again.
+///
+/// Unlike [`mutex_lock`], this never locks the mutex as uncontended. Other
+/// threads may have been waiting alongside this one, and an unlock only
+/// wakes one waiter, so the thread which locks the mutex after a wait must
+/// leave it marked as contended, so that its unlock wakes the next one.
+pub fn mutex_lock_after_wait<const SHM: bool>(mutex: &RawMutex<SHM>) -> Option<FutexWait<'_>> {
+    let mutex = mutex.futex_mutex();
+    if mutex.try_lock_contended() {
+        return None;
+    }
+
+    let (futex, expected) = mutex.contended_futex();
+    Some(FutexWait::new::<SHM>(futex, expected))
+}
+
+/// Unlocks `mutex` and returns the futex wait for a notification on
+/// `condvar`.
+///
+/// After the wait completes, successfully or not, the caller should lock
+/// `mutex` again, for example with [`mutex_lock`], and check its condition,
+/// as with [`Condvar::wait`].
+///
+/// [`Condvar::wait`]: crate::generic::Condvar::wait
+///
+/// # Safety
+///
+/// `mutex` must be locked by the current thread.
+pub unsafe fn condvar_wait<'a, const SHM: bool>(
+    condvar: &'a RawCondvar<SHM>,
+    mutex: &RawMutex<SHM>,
+) -> FutexWait<'a> {
+    use crate::lock_api::RawMutex as _;
+
+    // Examine the notification counter _before_ we unlock the mutex.
+    let futex = condvar.futex();
+    let expected = futex.load(Relaxed);
+
+    mutex.unlock();
+
+    FutexWait::new::<SHM>(futex, expected)
+}
+
+/// Notifies `condvar`, and returns the futex wake which wakes up one of its
+/// waiters.
+///
+/// This is like [`RawCondvar::notify_one`], except that the caller submits
+/// the wake, rather than the current thread performing it.
+///
+/// [`RawCondvar::notify_one`]: crate::generic::RawCondvar::notify_one
+pub fn condvar_notify_one<const SHM: bool>(condvar: &RawCondvar<SHM>) -> FutexWake<'_> {
+    let futex = condvar.futex();
+    futex.fetch_add(1, Relaxed);
+    FutexWake::new::<SHM>(futex, 1)
+}
+
+/// Notifies `condvar`, and returns the futex wake which wakes up all of its
+/// waiters.
+///
+/// This is like [`RawCondvar::notify_all`], except that the caller submits
+/// the wake, rather than the current thread performing it.
+///
+/// [`RawCondvar::notify_all`]: crate::generic::RawCondvar::notify_all
+pub fn condvar_notify_all<const SHM: bool>(condvar: &RawCondvar<SHM>) -> FutexWake<'_> {
+    let futex = condvar.futex();
+    futex.fetch_add(1, Relaxed);
+    FutexWake::new::<SHM>(futex, i32::MAX as u32)
+}
+
+/// Returns the futex wait for `once`'s initialization to complete, or `None`
+/// if it has completed.
+///
+/// After the wait completes, the caller should call this function again.
+/// This is the io_uring counterpart of [`Once::wait`].
+///
+/// # Panics
+///
+/// If `once` has been poisoned because an initialization closure has
+/// panicked, this function will also panic. Use [`once_wait_force`] if this
+/// behavior is not desired.
+#[track_caller]
+pub fn once_wait<const SHM: bool>(once: &Once<SHM>) -> Option<FutexWait<'_>> {
+    let (futex, expected) = once.prepare_wait(false)?;
+    Some(FutexWait::new::<SHM>(futex, expected))
+}
+
+/// Like [`once_wait`], but ignores poisoning.
+pub fn once_wait_force<const SHM: bool>(once: &Once<SHM>) -> Option<FutexWait<'_>> {
+    let (futex, expected) = once.prepare_wait(true)?;
+    Some(FutexWait::new::<SHM>(futex, expected))
+}
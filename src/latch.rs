@@ -0,0 +1,202 @@
+//! A futex-based latch.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering::{Acquire, Relaxed, Release},
+};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::wait_wake::{deadline, futex_wait_timespec, futex_wake_all};
+
+/// Some threads may be waiting for the count to reach zero.
+const WAITERS: u32 = 1 << 31;
+/// The bits holding the count.
+const COUNT_MASK: u32 = WAITERS - 1;
+
+/// A counter which threads can wait to reach zero.
+///
+/// This is also known as a wait group: a thread which starts some work calls
+/// [`add`], and calls [`count_down`] when the work is done. Meanwhile, other
+/// threads can call [`wait`] to block until all the work is done. All waiters
+/// are woken when the count reaches zero, and not before.
+///
+/// Unlike C++'s `std::latch`, a `Latch` can be reused by calling [`add`]
+/// after its count has reached zero.
+///
+/// `Latch` is guaranteed to be a `repr(transparent)` wrapper around a single
+/// `AtomicU32`, and an all-zeros `Latch` is a valid latch with a count of
+/// zero.
+///
+/// [`add`]: Self::add
+/// [`count_down`]: Self::count_down
+/// [`wait`]: Self::wait
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::Latch;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let latch = Arc::new(Latch::new(0));
+/// for _ in 0..4 {
+///     latch.add(1);
+///     let latch = Arc::clone(&latch);
+///     thread::spawn(move || {
+///         // ...
+///         latch.count_down();
+///     });
+/// }
+/// latch.wait();
+/// assert_eq!(latch.count(), 0);
+/// ```
+#[repr(transparent)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Latch<const SHM: bool, B = SyscallBackend> {
+    state: AtomicU32,
+    backend: PhantomData<B>,
+}
+
+impl<const SHM: bool, B: FutexBackend> Latch<SHM, B> {
+    /// The maximum count a `Latch` can hold.
+    pub const MAX_COUNT: u32 = COUNT_MASK;
+
+    /// Creates a new latch with the given count.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is greater than [`MAX_COUNT`].
+    ///
+    /// [`MAX_COUNT`]: Self::MAX_COUNT
+    #[inline]
+    pub const fn new(count: u32) -> Self {
+        assert!(count <= COUNT_MASK, "count too large for Latch");
+        Self {
+            state: AtomicU32::new(count),
+            backend: PhantomData,
+        }
+    }
+
+    /// Returns the current count.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.state.load(Acquire) & COUNT_MASK
+    }
+
+    /// Increments the count by `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this would make the count greater than [`MAX_COUNT`].
+    ///
+    /// [`MAX_COUNT`]: Self::MAX_COUNT
+    pub fn add(&self, n: u32) {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            assert!(n <= COUNT_MASK - (state & COUNT_MASK), "count too large for Latch");
+            match self.state.compare_exchange_weak(state, state + n, Relaxed, Relaxed) {
+                Ok(_) => return,
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    /// Decrements the count by one, waking all waiting threads if it
+    /// reaches zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count is already zero.
+    pub fn count_down(&self) {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            let count = state & COUNT_MASK;
+            assert!(count != 0, "count_down on a Latch with a count of zero");
+            // If we're bringing the count to zero, clear the `WAITERS` flag;
+            // we'll wake them all below.
+            let new = if count == 1 { 0 } else { state - 1 };
+            match self.state.compare_exchange_weak(state, new, Release, Relaxed) {
+                Ok(_) => break,
+                Err(s) => state = s,
+            }
+        }
+
+        if state == (WAITERS | 1) {
+            futex_wake_all::<SHM, B>(&self.state);
+        }
+    }
+
+    /// Blocks the current thread until the count reaches zero.
+    #[inline]
+    pub fn wait(&self) {
+        self.wait_optional_deadline(None);
+    }
+
+    /// Like [`wait`], but gives up if the count doesn't reach zero within
+    /// `timeout`.
+    ///
+    /// Returns `true` if the count reached zero, and `false` on timeout.
+    ///
+    /// [`wait`]: Self::wait
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        if self.count() == 0 {
+            return true;
+        }
+        // Overflows are rounded up to an infinite timeout.
+        self.wait_optional_deadline(deadline(timeout).as_ref())
+    }
+
+    /// Like [`wait_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`wait_timeout`]: Self::wait_timeout
+    #[inline]
+    pub fn wait_until(&self, deadline: &Timespec) -> bool {
+        self.wait_optional_deadline(Some(deadline))
+    }
+
+    fn wait_optional_deadline(&self, deadline: Option<&Timespec>) -> bool {
+        let mut state = self.state.load(Acquire);
+        loop {
+            if state & COUNT_MASK == 0 {
+                return true;
+            }
+
+            // Set the `WAITERS` flag, if it isn't set already.
+            if state & WAITERS == 0 {
+                if let Err(s) =
+                    self.state
+                        .compare_exchange_weak(state, state | WAITERS, Acquire, Acquire)
+                {
+                    state = s;
+                    continue;
+                }
+            }
+
+            if !futex_wait_timespec::<SHM, B>(&self.state, state | WAITERS, deadline) {
+                // We timed out. Make one last check before giving up.
+                return self.count() == 0;
+            }
+            state = self.state.load(Acquire);
+        }
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> Default for Latch<SHM, B> {
+    /// Creates a new latch with a count of zero.
+    #[inline]
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> fmt::Debug for Latch<SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Latch")
+            .field("count", &self.count())
+            .finish()
+    }
+}
@@ -0,0 +1,259 @@
+//! The following is derived from Rust's
+//! library/std/src/sync/lazy_lock.rs at revision
+//! 22a5267c83a3e17f2b763279eb24bb632c45dc6b, reimplemented on top of
+//! [`OnceLock`].
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::Deref;
+use core::panic::{RefUnwindSafe, UnwindSafe};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::generic::OnceLock;
+
+/// A value which is initialized on the first access.
+///
+/// This type is a thread-safe [`LazyCell`], and can be used in statics.
+/// Since initialization may be called from multiple threads, any
+/// dereferencing call will block the calling thread if another
+/// initialization routine is currently running.
+///
+/// [`LazyCell`]: core::cell::LazyCell
+///
+/// # Shared memory
+///
+/// The initializer `F` is stored inline in the `LazyLock`. When a
+/// `shm::LazyLock` is placed in memory shared between processes, `F` is
+/// therefore placed in shared memory too, and function pointers, and
+/// references captured by closures, are only meaningful in the address space
+/// of the process that constructed the `LazyLock`. The rule is:
+///
+///  - Only the constructing process, or processes which have the same
+///    executable and data mapped at the same addresses (such as children
+///    created with `fork` after the `LazyLock` was constructed), may call
+///    [`force`] or dereference the `LazyLock` before it is initialized.
+///  - Once it is initialized, the initializer is dropped and never called
+///    again, so any process may dereference the `LazyLock`, provided that
+///    `T` itself is valid in shared memory.
+///
+/// A common way to follow this rule is to [`force`] the `LazyLock` in the
+/// process that creates the shared memory, before any other process attaches
+/// to it.
+///
+/// [`force`]: LazyLock::force
+///
+/// # Examples
+///
+/// Initialize static variables with `LazyLock`.
+///
+/// ```
+/// use rustix_futex_sync::LazyLock;
+///
+/// // Note: static items do not call [`Drop`] on program termination, so this won't be deallocated.
+/// // this is fine, as the OS can deallocate the terminated program faster than we can free memory
+/// // but tools like valgrind might report "memory leaks" as it isn't obvious this is intentional.
+/// static DEEP_THOUGHT: LazyLock<String> = LazyLock::new(|| {
+/// # mod another_crate {
+/// #     pub fn great_question() -> String { "42".to_string() }
+/// # }
+///     // M3 Ultra takes about 16 million years in --release config
+///     another_crate::great_question()
+/// });
+///
+/// // The `String` is built, stored in the `LazyLock`, and returned as `&String`.
+/// let _ = &*DEEP_THOUGHT;
+/// ```
+///
+/// Initialize fields with `LazyLock`.
+///
+/// ```
+/// use rustix_futex_sync::LazyLock;
+///
+/// #[derive(Debug)]
+/// struct UseCellLock {
+///     number: LazyLock<u32>,
+/// }
+/// fn main() {
+///     let lock: LazyLock<u32> = LazyLock::new(|| 0u32);
+///
+///     let data = UseCellLock { number: lock };
+///     println!("{}", *data.number);
+/// }
+/// ```
+//#[stable(feature = "lazy_cell", since = "1.80.0")]
+pub struct LazyLock<T, F, const SHM: bool, B: FutexBackend = SyscallBackend> {
+    cell: OnceLock<T, SHM, B>,
+    // This is `Some` until the initializer is called. It's only accessed
+    // from within `cell`'s initialization, which is exclusive, or through
+    // exclusive access to the whole `LazyLock`.
+    init: UnsafeCell<Option<F>>,
+}
+
+impl<T, F: FnOnce() -> T, const SHM: bool, B: FutexBackend> LazyLock<T, F, SHM, B> {
+    /// Creates a new lazy value with the given initializing function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::LazyLock;
+    ///
+    /// let hello = "Hello, World!".to_string();
+    ///
+    /// let lazy = LazyLock::new(|| hello.to_uppercase());
+    ///
+    /// assert_eq!(&*lazy, "HELLO, WORLD!");
+    /// ```
+    #[inline]
+    //#[stable(feature = "lazy_cell", since = "1.80.0")]
+    pub const fn new(f: F) -> Self {
+        LazyLock { cell: OnceLock::new(), init: UnsafeCell::new(Some(f)) }
+    }
+
+    /// Consumes this `LazyLock` returning the stored value.
+    ///
+    /// Returns `Ok(value)` if `Lazy` is initialized and `Err(f)` otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a previous initialization panicked, since in that case
+    /// neither a value nor an initializer is available.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::LazyLock;
+    ///
+    /// let hello = "Hello, World!".to_string();
+    ///
+    /// let lazy = LazyLock::new(|| hello.to_uppercase());
+    ///
+    /// assert_eq!(&*lazy, "HELLO, WORLD!");
+    /// assert_eq!(LazyLock::into_inner(lazy).ok(), Some("HELLO, WORLD!".to_string()));
+    /// ```
+    //#[unstable(feature = "lazy_cell_into_inner", issue = "125623")]
+    pub fn into_inner(this: Self) -> Result<T, F> {
+        let LazyLock { cell, init } = this;
+        match cell.into_inner() {
+            Some(value) => Ok(value),
+            None => Err(init.into_inner().unwrap_or_else(|| panic_poisoned())),
+        }
+    }
+
+    /// Forces the evaluation of this lazy value and returns a reference to
+    /// result. This is equivalent to the `Deref` impl, but is explicit.
+    ///
+    /// This method will block the calling thread if another initialization
+    /// routine is currently running.
+    ///
+    /// # Panics
+    ///
+    /// If the initialization closure panics, the panic is propagated to the
+    /// caller, and all future calls to `force` will panic too, since the
+    /// initializer has been consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::LazyLock;
+    ///
+    /// let lazy = LazyLock::new(|| 92);
+    ///
+    /// assert_eq!(LazyLock::force(&lazy), &92);
+    /// assert_eq!(&*lazy, &92);
+    /// ```
+    #[inline]
+    //#[stable(feature = "lazy_cell", since = "1.80.0")]
+    pub fn force(this: &Self) -> &T {
+        this.cell.get_or_init(|| {
+            // SAFETY: We're inside `cell`'s initialization, so no other
+            // thread is accessing `init`.
+            match unsafe { (*this.init.get()).take() } {
+                Some(f) => f(),
+                None => panic_poisoned(),
+            }
+        })
+    }
+}
+
+impl<T, F, const SHM: bool, B: FutexBackend> LazyLock<T, F, SHM, B> {
+    /// Returns a reference to the value if initialized, or `None` if not.
+    ///
+    /// This method never blocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::LazyLock;
+    ///
+    /// let lazy = LazyLock::new(|| 92);
+    ///
+    /// assert_eq!(LazyLock::get(&lazy), None);
+    /// let _ = LazyLock::force(&lazy);
+    /// assert_eq!(LazyLock::get(&lazy), Some(&92));
+    /// ```
+    #[inline]
+    //#[unstable(feature = "lazy_get", issue = "129333")]
+    pub fn get(this: &Self) -> Option<&T> {
+        this.cell.get()
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn panic_poisoned() -> ! {
+    panic!("LazyLock instance has previously been poisoned")
+}
+
+//#[stable(feature = "lazy_cell", since = "1.80.0")]
+impl<T, F: FnOnce() -> T, const SHM: bool, B: FutexBackend> Deref for LazyLock<T, F, SHM, B> {
+    type Target = T;
+
+    /// Dereferences the value.
+    ///
+    /// This method will block the calling thread if another initialization
+    /// routine is currently running.
+    #[inline]
+    fn deref(&self) -> &T {
+        LazyLock::force(self)
+    }
+}
+
+//#[stable(feature = "lazy_cell", since = "1.80.0")]
+impl<T: Default, const SHM: bool, B: FutexBackend> Default for LazyLock<T, fn() -> T, SHM, B> {
+    /// Creates a new lazy value using `Default` as the initializing function.
+    #[inline]
+    fn default() -> Self {
+        LazyLock::new(T::default)
+    }
+}
+
+//#[stable(feature = "lazy_cell", since = "1.80.0")]
+impl<T: fmt::Debug, F, const SHM: bool, B: FutexBackend> fmt::Debug for LazyLock<T, F, SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_tuple("LazyLock");
+        match self.cell.get() {
+            Some(v) => d.field(v),
+            None => d.field(&format_args!("<uninit>")),
+        };
+        d.finish()
+    }
+}
+
+// We never create a `&F` from a `&LazyLock<T, F>` so it is fine
+// to not impl `Sync` for `F`.
+//#[stable(feature = "lazy_cell", since = "1.80.0")]
+unsafe impl<T: Sync + Send, F: Send, const SHM: bool, B: FutexBackend> Sync
+    for LazyLock<T, F, SHM, B>
+{
+}
+// auto-derived `Send` impl is OK.
+
+//#[stable(feature = "lazy_cell", since = "1.80.0")]
+impl<T: RefUnwindSafe + UnwindSafe, F: UnwindSafe, const SHM: bool, B: FutexBackend> RefUnwindSafe
+    for LazyLock<T, F, SHM, B>
+{
+}
+//#[stable(feature = "lazy_cell", since = "1.80.0")]
+impl<T: UnwindSafe, F: UnwindSafe, const SHM: bool, B: FutexBackend> UnwindSafe
+    for LazyLock<T, F, SHM, B>
+{
+}
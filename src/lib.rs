@@ -2,6 +2,19 @@
 #![no_std]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+// Under loom, the futex state machines' atomics aren't all-zeros when
+// initialized, and can't be shared with other processes or the kernel.
+#[cfg(all(
+    loom,
+    any(feature = "shm", feature = "bytemuck", feature = "zerocopy", feature = "io_uring")
+))]
+compile_error!(
+    "`cfg(loom)` is not compatible with the \"shm\", \"bytemuck\", \"zerocopy\", or \"io_uring\" features"
+);
+
 // Re-export this so that our users can use the same version we do.
 #[cfg(feature = "lock_api")]
 pub use lock_api;
@@ -10,43 +23,74 @@ pub use lock_api;
 #[cfg(not(feature = "lock_api"))]
 pub mod lock_api;
 
-#[cfg(feature = "lock_api")]
+// Re-export this so that our users can construct deadlines for our
+// `wait_until` functions.
+pub use rustix::time::Timespec;
+
 pub use condvar::WaitTimeoutResult;
-pub use once::OnceState;
+pub use barrier::BarrierWaitResult;
+pub use once::{ExclusiveState, OnceState};
+pub use queue_mutex::QueueMutex;
+pub use futex_backend::{FutexBackend, SyscallBackend};
+#[cfg(feature = "eventfd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "eventfd")))]
+pub use event_fd::EventFd;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use async_lock::{
+    AsyncMutex, AsyncMutexGuard, AsyncMutexLockFuture, AsyncRwLock, AsyncRwLockReadFuture,
+    AsyncRwLockReadGuard, AsyncRwLockWriteFuture, AsyncRwLockWriteGuard,
+};
 
 // Non-shared API.
 
 pub type Once = generic::Once<false>;
-#[cfg(feature = "lock_api")]
+pub type Barrier = generic::Barrier<false>;
+pub type Event = generic::Event<false>;
+pub type Latch = generic::Latch<false>;
+pub type WaitGroup = generic::WaitGroup<false>;
+pub type Parker = generic::Parker<false>;
+pub type Unparker<'a> = generic::Unparker<'a, false>;
+pub type SeqLock<T> = generic::SeqLock<T, false>;
+pub type SeqLockGuard<'a, T> = generic::SeqLockGuard<'a, T, false>;
+pub type Semaphore = generic::Semaphore<false>;
+pub type SemaphorePermit<'a> = generic::SemaphorePermit<'a, false>;
 pub type Condvar = generic::Condvar<false>;
 pub type RawCondvar = generic::RawCondvar<false>;
 pub type RawMutex = generic::RawMutex<false>;
 pub type RawRwLock = generic::RawRwLock<false>;
+pub type TicketMutex = generic::TicketMutex<false>;
 pub type OnceLock<T> = generic::OnceLock<T, false>;
-#[cfg(feature = "lock_api")]
+pub type LazyLock<T, F = fn() -> T> = generic::LazyLock<T, F, false>;
+pub type ReprCMutex<T> = generic::ReprCMutex<T, false>;
+pub type ReprCMutexGuard<'a, T> = generic::ReprCMutexGuard<'a, T, false>;
+pub type ReprCRwLock<T> = generic::ReprCRwLock<T, false>;
+pub type ReprCRwLockReadGuard<'a, T> = generic::ReprCRwLockReadGuard<'a, T, false>;
+pub type ReprCRwLockWriteGuard<'a, T> = generic::ReprCRwLockWriteGuard<'a, T, false>;
 pub type Mutex<T> = generic::Mutex<T, false>;
-#[cfg(feature = "lock_api")]
 pub type RwLock<T> = generic::RwLock<T, false>;
-#[cfg(feature = "lock_api")]
 pub type MutexGuard<'a, T> = generic::MutexGuard<'a, T, false>;
-#[cfg(feature = "lock_api")]
 pub type MappedMutexGuard<'a, T> = generic::MappedMutexGuard<'a, T, false>;
-#[cfg(feature = "lock_api")]
 pub type RwLockReadGuard<'a, T> = generic::RwLockReadGuard<'a, T, false>;
-#[cfg(feature = "lock_api")]
 pub type RwLockWriteGuard<'a, T> = generic::RwLockWriteGuard<'a, T, false>;
-#[cfg(feature = "lock_api")]
 pub type MappedRwLockReadGuard<'a, T> = generic::MappedRwLockReadGuard<'a, T, false>;
-#[cfg(feature = "lock_api")]
 pub type MappedRwLockWriteGuard<'a, T> = generic::MappedRwLockWriteGuard<'a, T, false>;
-#[cfg(feature = "lock_api")]
 #[cfg(feature = "atomic_usize")]
 #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
 pub type ReentrantMutex<G, T> = generic::ReentrantMutex<G, T, false>;
-#[cfg(feature = "lock_api")]
 #[cfg(feature = "atomic_usize")]
 #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
 pub type ReentrantMutexGuard<'a, G, T> = generic::ReentrantMutexGuard<'a, G, T, false>;
+/// A [`ReentrantMutex`] which uses [`RawThreadId`] for its thread IDs.
+#[cfg(feature = "atomic_usize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
+pub type TidReentrantMutex<T> = ReentrantMutex<RawThreadId, T>;
+#[cfg(feature = "atomic_usize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
+pub type TidReentrantMutexGuard<'a, T> = ReentrantMutexGuard<'a, RawThreadId, T>;
+#[cfg(feature = "atomic_usize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
+pub type RawThreadId = generic::RawThreadId<false>;
 
 /// Shared-memory API.
 ///
@@ -66,93 +110,185 @@ pub mod shm {
     pub use super::lock_api;
 
     pub type Once = generic::Once<true>;
-    #[cfg(feature = "lock_api")]
+    pub type Barrier = generic::Barrier<true>;
+    pub type Event = generic::Event<true>;
+    pub type Latch = generic::Latch<true>;
+    pub type WaitGroup = generic::WaitGroup<true>;
+    pub type Parker = generic::Parker<true>;
+    pub type Unparker<'a> = generic::Unparker<'a, true>;
+    pub type SeqLock<T> = generic::SeqLock<T, true>;
+    pub type SeqLockGuard<'a, T> = generic::SeqLockGuard<'a, T, true>;
+    pub type Semaphore = generic::Semaphore<true>;
+    pub type SemaphorePermit<'a> = generic::SemaphorePermit<'a, true>;
     pub type Condvar = generic::Condvar<true>;
     pub type RawCondvar = generic::RawCondvar<true>;
     pub type RawMutex = generic::RawMutex<true>;
     pub type RawRwLock = generic::RawRwLock<true>;
+    pub type TicketMutex = generic::TicketMutex<true>;
     pub type OnceLock<T> = generic::OnceLock<T, true>;
-    #[cfg(feature = "lock_api")]
+    /// See [`generic::LazyLock`] for the rules about sharing its initializer
+    /// between processes.
+    pub type LazyLock<T, F = fn() -> T> = generic::LazyLock<T, F, true>;
+    pub type ReprCMutex<T> = generic::ReprCMutex<T, true>;
+    pub type ReprCMutexGuard<'a, T> = generic::ReprCMutexGuard<'a, T, true>;
+    pub type ReprCRwLock<T> = generic::ReprCRwLock<T, true>;
+    pub type ReprCRwLockReadGuard<'a, T> = generic::ReprCRwLockReadGuard<'a, T, true>;
+    pub type ReprCRwLockWriteGuard<'a, T> = generic::ReprCRwLockWriteGuard<'a, T, true>;
+
+    pub use crate::region::{Placeable, Region};
+    pub use crate::robust_once::RobustOnce;
+    pub use crate::versioned::{LayoutError, Versionable, Versioned, LAYOUT_VERSION};
     pub type Mutex<T> = generic::Mutex<T, true>;
-    #[cfg(feature = "lock_api")]
     pub type RwLock<T> = generic::RwLock<T, true>;
-    #[cfg(feature = "lock_api")]
     pub type MutexGuard<'a, T> = generic::MutexGuard<'a, T, true>;
-    #[cfg(feature = "lock_api")]
     pub type MappedMutexGuard<'a, T> = generic::MappedMutexGuard<'a, T, true>;
-    #[cfg(feature = "lock_api")]
     pub type RwLockReadGuard<'a, T> = generic::RwLockReadGuard<'a, T, true>;
-    #[cfg(feature = "lock_api")]
     pub type RwLockWriteGuard<'a, T> = generic::RwLockWriteGuard<'a, T, true>;
-    #[cfg(feature = "lock_api")]
     pub type MappedRwLockReadGuard<'a, T> = generic::MappedRwLockReadGuard<'a, T, true>;
-    #[cfg(feature = "lock_api")]
     pub type MappedRwLockWriteGuard<'a, T> = generic::MappedRwLockWriteGuard<'a, T, true>;
-    #[cfg(feature = "lock_api")]
     #[cfg(feature = "atomic_usize")]
     #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
     pub type ReentrantMutex<G, T> = generic::ReentrantMutex<G, T, true>;
-    #[cfg(feature = "lock_api")]
     #[cfg(feature = "atomic_usize")]
     #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
     pub type ReentrantMutexGuard<'a, G, T> = generic::ReentrantMutexGuard<'a, G, T, true>;
+    /// A [`ReentrantMutex`] which uses [`RawThreadId`] for its thread IDs.
+    #[cfg(feature = "atomic_usize")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
+    pub type TidReentrantMutex<T> = ReentrantMutex<RawThreadId, T>;
+    #[cfg(feature = "atomic_usize")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
+    pub type TidReentrantMutexGuard<'a, T> = ReentrantMutexGuard<'a, RawThreadId, T>;
+    /// A [`lock_api::GetThreadId`] for shared-memory reentrant mutexes.
+    ///
+    /// This uses the plain Linux thread ID, rather than a `(pid, tid)`
+    /// pair. A thread ID is already unique across all the processes in a
+    /// PID namespace, and process IDs come from the same namespace, so a
+    /// pair would be no more unique across namespaces. A pair also wouldn't
+    /// fit in a 32-bit `usize`. As with [`RobustOnce`], all the processes
+    /// sharing a mutex must be in the same PID namespace.
+    ///
+    /// [`lock_api::GetThreadId`]: https://docs.rs/lock_api/*/lock_api/trait.GetThreadId.html
+    #[cfg(feature = "atomic_usize")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
+    pub type RawThreadId = generic::RawThreadId<true>;
 }
 
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod channel;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod oneshot;
+#[cfg(feature = "io_uring")]
+#[cfg_attr(docsrs, doc(cfg(feature = "io_uring")))]
+pub mod io_uring;
+
 /// Types and traits with a `const SHM: bool>` generic paramters.
 ///
 /// These are the generic types that are parameterized on whether they support
 /// shared memory or not. They are aliased as non-parameterized types in the
 /// top-level crate and in the `shm` module for better ergonomics.
+///
+/// They're also parameterized on a [`FutexBackend`], which defaults to
+/// [`SyscallBackend`].
+///
+/// [`FutexBackend`]: crate::FutexBackend
 pub mod generic {
-    #[cfg(feature = "lock_api")]
+    use crate::SyscallBackend;
+
+    pub use crate::barrier::Barrier;
+    pub use crate::event::Event;
+    pub use crate::latch::Latch;
     pub use crate::condvar::Condvar;
     pub use crate::futex_condvar::Condvar as RawCondvar;
+    pub use crate::lazy_lock::LazyLock;
     pub use crate::once::Once;
     pub use crate::once_lock::OnceLock;
+    pub use crate::parker::{Parker, Unparker};
     pub use crate::raw_mutex::RawMutex;
     pub use crate::raw_rwlock::RawRwLock;
+    pub use crate::repr_c_mutex::{ReprCMutex, ReprCMutexGuard};
+    pub use crate::repr_c_rwlock::{ReprCRwLock, ReprCRwLockReadGuard, ReprCRwLockWriteGuard};
+    pub use crate::semaphore::{Semaphore, SemaphorePermit};
+    pub use crate::seq_lock::{SeqLock, SeqLockGuard};
+    pub use crate::ticket_mutex::TicketMutex;
+    #[cfg(feature = "atomic_usize")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
+    pub use crate::thread_id::RawThreadId;
+    #[cfg(not(feature = "lock_api"))]
+    #[cfg(feature = "atomic_usize")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
+    pub use crate::reentrant_mutex::{ReentrantMutex, ReentrantMutexGuard};
 
-    #[cfg(feature = "lock_api")]
-    pub type Mutex<T, const SHM: bool> = lock_api::Mutex<RawMutex<SHM>, T>;
-    #[cfg(feature = "lock_api")]
-    pub type RwLock<T, const SHM: bool> = lock_api::RwLock<RawRwLock<SHM>, T>;
-    #[cfg(feature = "lock_api")]
-    pub type MutexGuard<'a, T, const SHM: bool> = lock_api::MutexGuard<'a, RawMutex<SHM>, T>;
-    #[cfg(feature = "lock_api")]
-    pub type MappedMutexGuard<'a, T, const SHM: bool> =
-        lock_api::MappedMutexGuard<'a, RawMutex<SHM>, T>;
-    #[cfg(feature = "lock_api")]
-    pub type RwLockReadGuard<'a, T, const SHM: bool> =
-        lock_api::RwLockReadGuard<'a, RawRwLock<SHM>, T>;
-    #[cfg(feature = "lock_api")]
-    pub type RwLockWriteGuard<'a, T, const SHM: bool> =
-        lock_api::RwLockWriteGuard<'a, RawRwLock<SHM>, T>;
-    #[cfg(feature = "lock_api")]
-    pub type MappedRwLockReadGuard<'a, T, const SHM: bool> =
-        lock_api::MappedRwLockReadGuard<'a, RawRwLock<SHM>, T>;
-    #[cfg(feature = "lock_api")]
-    pub type MappedRwLockWriteGuard<'a, T, const SHM: bool> =
-        lock_api::MappedRwLockWriteGuard<'a, RawRwLock<SHM>, T>;
+    /// A [`Latch`] is also known as a wait group.
+    pub type WaitGroup<const SHM: bool, B = SyscallBackend> = Latch<SHM, B>;
+    pub type Mutex<T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::Mutex<RawMutex<SHM, B>, T>;
+    pub type RwLock<T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::RwLock<RawRwLock<SHM, B>, T>;
+    pub type MutexGuard<'a, T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::MutexGuard<'a, RawMutex<SHM, B>, T>;
+    pub type MappedMutexGuard<'a, T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::MappedMutexGuard<'a, RawMutex<SHM, B>, T>;
+    pub type RwLockReadGuard<'a, T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::RwLockReadGuard<'a, RawRwLock<SHM, B>, T>;
+    pub type RwLockWriteGuard<'a, T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::RwLockWriteGuard<'a, RawRwLock<SHM, B>, T>;
+    pub type MappedRwLockReadGuard<'a, T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::MappedRwLockReadGuard<'a, RawRwLock<SHM, B>, T>;
+    pub type MappedRwLockWriteGuard<'a, T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::MappedRwLockWriteGuard<'a, RawRwLock<SHM, B>, T>;
     #[cfg(feature = "lock_api")]
     #[cfg(feature = "atomic_usize")]
     #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
-    pub type ReentrantMutex<G, T, const SHM: bool> = lock_api::ReentrantMutex<RawMutex<SHM>, G, T>;
+    pub type ReentrantMutex<G, T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::ReentrantMutex<RawMutex<SHM, B>, G, T>;
     #[cfg(feature = "lock_api")]
     #[cfg(feature = "atomic_usize")]
     #[cfg_attr(docsrs, doc(cfg(feature = "atomic_usize")))]
-    pub type ReentrantMutexGuard<'a, G, T, const SHM: bool> =
-        lock_api::ReentrantMutexGuard<'a, RawMutex<SHM>, G, T>;
+    pub type ReentrantMutexGuard<'a, G, T, const SHM: bool, B = SyscallBackend> =
+        crate::lock_api::ReentrantMutexGuard<'a, RawMutex<SHM, B>, G, T>;
 }
 
 // std's implementation code.
-#[cfg(feature = "lock_api")]
+#[cfg(feature = "alloc")]
+mod async_lock;
+mod atomic;
+mod barrier;
+mod event;
+#[cfg(feature = "eventfd")]
+mod event_fd;
 mod condvar;
+mod futex_backend;
 mod futex_condvar;
 mod futex_mutex;
 mod futex_once;
 mod futex_rwlock;
+mod latch;
+mod lazy_lock;
 mod once;
 mod once_lock;
+mod parker;
+mod queue_mutex;
 mod raw_mutex;
 mod raw_rwlock;
+#[cfg(not(feature = "lock_api"))]
+#[cfg(feature = "atomic_usize")]
+mod reentrant_mutex;
+#[cfg(feature = "shm")]
+mod region;
+mod repr_c_mutex;
+mod repr_c_rwlock;
+#[cfg(feature = "shm")]
+mod robust_once;
+mod semaphore;
+mod seq_lock;
+#[cfg(feature = "atomic_usize")]
+mod thread_id;
+mod ticket_mutex;
+#[cfg(feature = "shm")]
+mod versioned;
 mod wait_wake;
+#[cfg(feature = "bytemuck")]
+mod zeroable;
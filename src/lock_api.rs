@@ -1,10 +1,26 @@
-//! Polyfills for `lock_api` traits for when we don't have the real `lock_api`
-//! crate.
+//! Polyfills for `lock_api` traits and types for when we don't have the real
+//! `lock_api` crate.
+
+// The safety requirements are those of the corresponding `lock_api` items,
+// which the polyfills' docs link to.
+#![allow(clippy::missing_safety_doc)]
+
+use core::marker::PhantomData;
+
+mod mutex;
+mod rwlock;
+
+pub use mutex::{MappedMutexGuard, Mutex, MutexGuard};
+pub use rwlock::{
+    MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
 
 /// Polyfill for [`lock_api::GuardNoSend`].
 ///
 /// [`lock_api::GuardNoSend`]: https://docs.rs/lock_api/*/lock_api/struct.GuardNoSend.html
-pub struct GuardNoSend(());
+pub struct GuardNoSend(PhantomData<*mut ()>);
+
+unsafe impl Sync for GuardNoSend {}
 
 /// Polyfill for [`lock_api::RawMutex`].
 ///
@@ -30,6 +46,19 @@ pub unsafe trait RawMutex {
     }
 }
 
+/// Polyfill for [`lock_api::RawMutexFair`].
+///
+/// [`lock_api::RawMutexFair`]: https://docs.rs/lock_api/*/lock_api/trait.RawMutexFair.html
+pub unsafe trait RawMutexFair: RawMutex {
+    unsafe fn unlock_fair(&self);
+
+    #[inline]
+    unsafe fn bump(&self) {
+        self.unlock_fair();
+        self.lock();
+    }
+}
+
 /// Polyfill for [`lock_api::RawRwLock`].
 ///
 /// [`lock_api::RawRwLock`]: https://docs.rs/lock_api/*/lock_api/trait.RawRwLock.html
@@ -66,3 +95,13 @@ pub unsafe trait RawRwLock {
         !acquired_lock
     }
 }
+
+/// Polyfill for [`lock_api::GetThreadId`].
+///
+/// [`lock_api::GetThreadId`]: https://docs.rs/lock_api/*/lock_api/trait.GetThreadId.html
+#[cfg(feature = "atomic_usize")]
+pub unsafe trait GetThreadId {
+    const INIT: Self;
+
+    fn nonzero_thread_id(&self) -> core::num::NonZeroUsize;
+}
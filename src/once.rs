@@ -14,7 +14,11 @@ mod tests;
 
 use core::fmt;
 use core::panic::{RefUnwindSafe, UnwindSafe};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
 use super::futex_once as sys;
+use super::wait_wake::deadline;
 
 /// A synchronization primitive which can be used to run a one-time global
 /// initialization. Useful for one-time initialization for FFI or related
@@ -33,15 +37,16 @@ use super::futex_once as sys;
 /// ```
 //#[stable(feature = "rust1", since = "1.0.0")]
 #[repr(transparent)]
-pub struct Once<const SHM: bool> {
-    inner: sys::Once<SHM>,
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Once<const SHM: bool, B = SyscallBackend> {
+    inner: sys::Once<SHM, B>,
 }
 
 //#[stable(feature = "sync_once_unwind_safe", since = "1.59.0")]
-impl<const SHM: bool> UnwindSafe for Once<SHM> {}
+impl<const SHM: bool, B: FutexBackend> UnwindSafe for Once<SHM, B> {}
 
 //#[stable(feature = "sync_once_unwind_safe", since = "1.59.0")]
-impl<const SHM: bool> RefUnwindSafe for Once<SHM> {}
+impl<const SHM: bool, B: FutexBackend> RefUnwindSafe for Once<SHM, B> {}
 
 /// State yielded to [`Once::call_once_force()`]’s closure parameter. The state
 /// can be used to query the poison status of the [`Once`].
@@ -50,13 +55,19 @@ pub struct OnceState {
     pub(crate) inner: sys::OnceState,
 }
 
-/*
-pub(crate) enum ExclusiveState {
+/// The state of a [`Once`], as observed through an exclusive reference.
+///
+/// Since [`Once::state`] and [`Once::set_state`] take a mutable reference, no
+/// initialization can be running, so there are only three possible states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExclusiveState {
+    /// No initialization has run yet.
     Incomplete,
+    /// A previous initialization panicked, or was explicitly poisoned.
     Poisoned,
+    /// Initialization has completed.
     Complete,
 }
-*/
 
 /*
 /// Initialization value for static [`Once`] values.
@@ -77,7 +88,7 @@ pub(crate) enum ExclusiveState {
 pub const ONCE_INIT: Once = Once::new();
 */
 
-impl<const SHM: bool> Once<SHM> {
+impl<const SHM: bool, B: FutexBackend> Once<SHM, B> {
     /// Creates a new `Once` value.
     #[inline]
     //#[stable(feature = "once_new", since = "1.2.0")]
@@ -190,17 +201,15 @@ impl<const SHM: bool> Once<SHM> {
     /// });
     /// assert!(handle.join().is_err());
     ///
-    /// /*
     /// // poisoning propagates
     /// let handle = thread::spawn(|| {
     ///     INIT.call_once(|| {});
     /// });
     /// assert!(handle.join().is_err());
-    /// */
     ///
     /// // call_once_force will still run and reset the poisoned state
     /// INIT.call_once_force(|state| {
-    ///     //assert!(state.is_poisoned());
+    ///     assert!(state.is_poisoned());
     /// });
     ///
     /// // once any success happens, we stop propagating the poison
@@ -268,28 +277,189 @@ impl<const SHM: bool> Once<SHM> {
         self.inner.is_completed()
     }
 
-    /*
+    /// Blocks the current thread until initialization has completed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustix_futex_sync::Once;
+    /// use std::thread;
+    ///
+    /// static READY: Once = Once::new();
+    ///
+    /// let thread = thread::spawn(|| {
+    ///     READY.wait();
+    ///     println!("everything is ready");
+    /// });
+    ///
+    /// READY.call_once(|| println!("performing setup"));
+    /// # thread.join().unwrap();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If this [`Once`] has been poisoned because an initialization closure has
+    /// panicked, this method will also panic. Use [`wait_force`](Self::wait_force)
+    /// if this behavior is not desired.
+    //#[stable(feature = "once_wait", since = "1.86.0")]
+    #[track_caller]
+    pub fn wait(&self) {
+        if !self.inner.is_completed() {
+            self.inner.wait(false, None);
+        }
+    }
+
+    /// Blocks the current thread until initialization has completed, ignoring
+    /// poisoning.
+    //#[stable(feature = "once_wait", since = "1.86.0")]
+    pub fn wait_force(&self) {
+        if !self.inner.is_completed() {
+            self.inner.wait(true, None);
+        }
+    }
+
+    /// Blocks the current thread until initialization has completed, or until
+    /// `timeout` has elapsed.
+    ///
+    /// Returns `true` if initialization has completed, and `false` if the
+    /// timeout elapsed first.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustix_futex_sync::Once;
+    /// use std::time::Duration;
+    ///
+    /// static READY: Once = Once::new();
+    ///
+    /// assert!(!READY.wait_timeout(Duration::from_millis(10)));
+    ///
+    /// READY.call_once(|| {});
+    /// assert!(READY.wait_timeout(Duration::from_millis(10)));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If this [`Once`] has been poisoned because an initialization closure has
+    /// panicked, this method will also panic.
+    #[track_caller]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        if self.inner.is_completed() {
+            return true;
+        }
+
+        // Overflows are rounded up to an infinite timeout.
+        self.inner.wait(false, deadline(timeout).as_ref())
+    }
+
+    /// Blocks the current thread until initialization has completed, or until
+    /// the time `deadline` on the `CLOCK_MONOTONIC` clock has passed.
+    ///
+    /// Returns `true` if initialization has completed, and `false` if the
+    /// deadline passed first.
+    ///
+    /// # Panics
+    ///
+    /// If this [`Once`] has been poisoned because an initialization closure has
+    /// panicked, this method will also panic.
+    #[track_caller]
+    pub fn wait_until(&self, deadline: &Timespec) -> bool {
+        if self.inner.is_completed() {
+            return true;
+        }
+
+        self.inner.wait(false, Some(deadline))
+    }
+
+    /// Returns the futex word and value to wait on until initialization has
+    /// completed, or `None` if it has.
+    #[cfg(feature = "io_uring")]
+    #[track_caller]
+    pub(crate) fn prepare_wait(
+        &self,
+        ignore_poisoning: bool,
+    ) -> Option<(&core::sync::atomic::AtomicU32, u32)> {
+        if self.inner.is_completed() {
+            return None;
+        }
+
+        self.inner.prepare_wait(ignore_poisoning)
+    }
+
+    /// Like [`wait_until`](Self::wait_until), but ignores poisoning.
+    pub(crate) fn wait_force_until(&self, deadline: Option<&Timespec>) -> bool {
+        if self.inner.is_completed() {
+            return true;
+        }
+
+        self.inner.wait(true, deadline)
+    }
+
     /// Returns the current state of the `Once` instance.
     ///
     /// Since this takes a mutable reference, no initialization can currently
     /// be running, so the state must be either "incomplete", "poisoned" or
     /// "complete".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::{ExclusiveState, Once};
+    ///
+    /// let mut once = Once::new();
+    /// assert_eq!(once.state(), ExclusiveState::Incomplete);
+    ///
+    /// once.call_once(|| {});
+    /// assert_eq!(once.state(), ExclusiveState::Complete);
+    /// ```
     #[inline]
-    pub(crate) fn state(&mut self) -> ExclusiveState {
+    pub fn state(&mut self) -> ExclusiveState {
         self.inner.state()
     }
-    */
+
+    /// Sets the current state of the `Once` instance.
+    ///
+    /// Since this takes a mutable reference, no initialization can currently
+    /// be running, so this can be used to reset a completed or poisoned
+    /// `Once` so that it can be initialized again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rustix_futex_sync::{ExclusiveState, Once};
+    ///
+    /// let mut once = Once::new();
+    /// once.call_once(|| {});
+    /// assert!(once.is_completed());
+    ///
+    /// once.set_state(ExclusiveState::Incomplete);
+    /// assert!(!once.is_completed());
+    ///
+    /// let mut called = false;
+    /// once.call_once(|| called = true);
+    /// assert!(called);
+    /// ```
+    #[inline]
+    pub fn set_state(&mut self, new_state: ExclusiveState) {
+        self.inner.set_state(new_state);
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> Default for Once<SHM, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 //#[stable(feature = "std_debug", since = "1.16.0")]
-impl<const SHM: bool> fmt::Debug for Once<SHM> {
+impl<const SHM: bool, B: FutexBackend> fmt::Debug for Once<SHM, B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Once").finish_non_exhaustive()
     }
 }
 
 impl OnceState {
-/*
     /// Returns `true` if the associated [`Once`] was poisoned prior to the
     /// invocation of the closure passed to [`Once::call_once_force()`].
     ///
@@ -324,25 +494,33 @@ impl OnceState {
     /// INIT.call_once_force(|state| {
     ///     assert!(!state.is_poisoned());
     /// });
+    /// ```
     //#[stable(feature = "once_poison", since = "1.51.0")]
     #[inline]
     pub fn is_poisoned(&self) -> bool {
         self.inner.is_poisoned()
     }
 
-    /// Poison the associated [`Once`] without explicitly panicking.
-    // NOTE: This is currently only exposed for `OnceLock`.
+    /// Returns `true` if the closure is being invoked because the process
+    /// that was running a previous initialization of a [`shm::RobustOnce`]
+    /// died.
+    ///
+    /// When this is `true`, [`is_poisoned`] is also `true`, and any state
+    /// the dead initializer was setting up may be only partially written.
+    /// This is always `false` for a [`Once`].
+    ///
+    /// [`shm::RobustOnce`]: crate::shm::RobustOnce
+    /// [`is_poisoned`]: Self::is_poisoned
     #[inline]
-    pub(crate) fn poison(&self) {
-        self.inner.poison();
+    pub fn is_recovered(&self) -> bool {
+        self.inner.is_recovered()
     }
-*/
 
-    /// Mark the associated [`Once`] as incomplete.
+    /// Poison the associated [`Once`] without explicitly panicking.
     // NOTE: This is currently only exposed for `OnceLock`.
     #[inline]
-    pub(crate) fn set_incomplete(&self) {
-        self.inner.set_incomplete();
+    pub(crate) fn poison(&self) {
+        self.inner.poison();
     }
 }
 
@@ -350,6 +528,8 @@ impl OnceState {
 impl fmt::Debug for OnceState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("OnceState")
-            /* .field("poisoned", &self.is_poisoned()) */.finish()
+            .field("poisoned", &self.is_poisoned())
+            .field("recovered", &self.is_recovered())
+            .finish()
     }
 }
@@ -5,9 +5,13 @@
 use core::cell::UnsafeCell;
 use core::fmt;
 use core::marker::PhantomData;
-use core::mem::MaybeUninit;
+use core::mem::{align_of, size_of, MaybeUninit};
 use core::panic::{RefUnwindSafe, UnwindSafe};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
 use crate::generic::Once;
+use crate::wait_wake::deadline;
 
 /// A synchronization primitive which can nominally be written to only once.
 ///
@@ -18,8 +22,8 @@ use crate::generic::Once;
 /// doesn't allow additional inputs to its function after you call [`LazyLock::new(|| ...)`].
 ///
 /// [`OnceCell`]: core::cell::OnceCell
-/// [`LazyLock<T, F>`]: https://doc.rust-lang.org/std/sync/struct.LazyLock.html
-/// [`LazyLock::new(|| ...)`]: https://doc.rust-lang.org/std/sync/struct.LazyLock.html#method.new
+/// [`LazyLock<T, F>`]: crate::generic::LazyLock
+/// [`LazyLock::new(|| ...)`]: crate::generic::LazyLock::new
 ///
 /// # Examples
 ///
@@ -97,9 +101,19 @@ use crate::generic::Once;
 /// }
 ///
 /// ```
+///
+/// # Layout
+///
+/// `OnceLock` is guaranteed to be a `repr(C)` struct containing a [`Once`] at
+/// offset 0, followed by the value at offset [`VALUE_OFFSET`]. An all-zeros
+/// `OnceLock` is a valid empty `OnceLock`, for any `T`.
+///
+/// [`VALUE_OFFSET`]: Self::VALUE_OFFSET
 //#[stable(feature = "once_cell", since = "1.70.0")]
-pub struct OnceLock<T, const SHM: bool> {
-    once: Once<SHM>,
+#[repr(C)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct OnceLock<T, const SHM: bool, B: FutexBackend = SyscallBackend> {
+    once: Once<SHM, B>,
     // Whether or not the value is initialized is tracked by `once.is_completed()`.
     value: UnsafeCell<MaybeUninit<T>>,
     /// `PhantomData` to make sure dropck understands we're dropping T in our Drop impl.
@@ -122,7 +136,13 @@ pub struct OnceLock<T, const SHM: bool> {
     _marker: PhantomData<T>,
 }
 
-impl<T, const SHM: bool> OnceLock<T, SHM> {
+impl<T, const SHM: bool, B: FutexBackend> OnceLock<T, SHM, B> {
+    /// The offset, in bytes, of the value within a `OnceLock`.
+    ///
+    /// This is the size of a `Once`, rounded up to the alignment of `T`.
+    pub const VALUE_OFFSET: usize =
+        (size_of::<Once<SHM, B>>() + align_of::<T>() - 1) & !(align_of::<T>() - 1);
+
     /// Creates a new empty cell.
     #[inline]
     #[must_use]
@@ -165,6 +185,80 @@ impl<T, const SHM: bool> OnceLock<T, SHM> {
         }
     }
 
+    /// Blocks the current thread until the cell is initialized.
+    ///
+    /// # Example
+    ///
+    /// Waiting for a computation on another thread to finish:
+    /// ```rust
+    /// use std::thread;
+    /// use rustix_futex_sync::OnceLock;
+    ///
+    /// let value = OnceLock::new();
+    ///
+    /// thread::scope(|s| {
+    ///     s.spawn(|| value.set(1 + 1));
+    ///
+    ///     let result = value.wait();
+    ///     assert_eq!(result, &2);
+    /// })
+    /// ```
+    #[inline]
+    //#[stable(feature = "once_wait", since = "1.86.0")]
+    pub fn wait(&self) -> &T {
+        self.once.wait_force();
+
+        // SAFETY: `wait_force` only returns once the cell is initialized.
+        unsafe { self.get_unchecked() }
+    }
+
+    /// Blocks the current thread until the cell is initialized, or until
+    /// `timeout` has elapsed.
+    ///
+    /// Returns `None` if the timeout elapsed before the cell was initialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustix_futex_sync::OnceLock;
+    /// use std::time::Duration;
+    ///
+    /// let value = OnceLock::new();
+    /// assert_eq!(value.wait_timeout(Duration::from_millis(10)), None);
+    ///
+    /// value.set(92).unwrap();
+    /// assert_eq!(value.wait_timeout(Duration::from_millis(10)), Some(&92));
+    /// ```
+    #[inline]
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<&T> {
+        if self.is_initialized() {
+            // SAFETY: Checked `is_initialized`.
+            return Some(unsafe { self.get_unchecked() });
+        }
+
+        // Overflows are rounded up to an infinite timeout.
+        self.wait_optional_deadline(deadline(timeout).as_ref())
+    }
+
+    /// Blocks the current thread until the cell is initialized, or until the
+    /// time `deadline` on the `CLOCK_MONOTONIC` clock has passed.
+    ///
+    /// Returns `None` if the deadline passed before the cell was initialized.
+    #[inline]
+    pub fn wait_until(&self, deadline: &Timespec) -> Option<&T> {
+        self.wait_optional_deadline(Some(deadline))
+    }
+
+    fn wait_optional_deadline(&self, deadline: Option<&Timespec>) -> Option<&T> {
+        if self.once.wait_force_until(deadline) {
+            // SAFETY: `wait_force_until` returned `true`, so the cell is
+            // initialized.
+            Some(unsafe { self.get_unchecked() })
+        } else {
+            None
+        }
+    }
+
     /// Sets the contents of this cell to `value`.
     ///
     /// May block if another thread is currently attempting to initialize the cell. The cell is
@@ -460,7 +554,7 @@ impl<T, const SHM: bool> OnceLock<T, SHM> {
             // SAFETY: `self.value` is initialized and contains a valid `T`.
             // `self.once` is reset, so `is_initialized()` will be false again
             // which prevents the value from being read twice.
-            unsafe { Some((&mut *self.value.get()).assume_init_read()) }
+            unsafe { Some((*self.value.get()).assume_init_read()) }
         } else {
             None
         }
@@ -489,15 +583,9 @@ impl<T, const SHM: bool> OnceLock<T, SHM> {
                 Err(e) => {
                     res = Err(e);
 
-                    // The call failed, so reset the once state back to
-                    // incomplete.
-                    p.set_incomplete();
-
-                    /*
                     // Treat the underlying `Once` as poisoned since we
-                    // failed to initialize our value. Calls
+                    // failed to initialize our value.
                     p.poison();
-                    */
                 }
             }
         });
@@ -529,17 +617,20 @@ impl<T, const SHM: bool> OnceLock<T, SHM> {
 // then destroyed by A. That is, destructor observes
 // a sent value.
 //#[stable(feature = "once_cell", since = "1.70.0")]
-unsafe impl<T: Sync + Send, const SHM: bool> Sync for OnceLock<T, SHM> {}
+unsafe impl<T: Sync + Send, const SHM: bool, B: FutexBackend> Sync for OnceLock<T, SHM, B> {}
 //#[stable(feature = "once_cell", since = "1.70.0")]
-unsafe impl<T: Send, const SHM: bool> Send for OnceLock<T, SHM> {}
+unsafe impl<T: Send, const SHM: bool, B: FutexBackend> Send for OnceLock<T, SHM, B> {}
 
 //#[stable(feature = "once_cell", since = "1.70.0")]
-impl<T: RefUnwindSafe + UnwindSafe, const SHM: bool> RefUnwindSafe for OnceLock<T, SHM> {}
+impl<T: RefUnwindSafe + UnwindSafe, const SHM: bool, B: FutexBackend> RefUnwindSafe
+    for OnceLock<T, SHM, B>
+{
+}
 //#[stable(feature = "once_cell", since = "1.70.0")]
-impl<T: UnwindSafe, const SHM: bool> UnwindSafe for OnceLock<T, SHM> {}
+impl<T: UnwindSafe, const SHM: bool, B: FutexBackend> UnwindSafe for OnceLock<T, SHM, B> {}
 
 //#[stable(feature = "once_cell", since = "1.70.0")]
-impl<T, const SHM: bool> Default for OnceLock<T, SHM> {
+impl<T, const SHM: bool, B: FutexBackend> Default for OnceLock<T, SHM, B> {
     /// Creates a new empty cell.
     ///
     /// # Example
@@ -558,7 +649,7 @@ impl<T, const SHM: bool> Default for OnceLock<T, SHM> {
 }
 
 //#[stable(feature = "once_cell", since = "1.70.0")]
-impl<T: fmt::Debug, const SHM: bool> fmt::Debug for OnceLock<T, SHM> {
+impl<T: fmt::Debug, const SHM: bool, B: FutexBackend> fmt::Debug for OnceLock<T, SHM, B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut d = f.debug_tuple("OnceLock");
         match self.get() {
@@ -570,7 +661,7 @@ impl<T: fmt::Debug, const SHM: bool> fmt::Debug for OnceLock<T, SHM> {
 }
 
 //#[stable(feature = "once_cell", since = "1.70.0")]
-impl<T: Clone, const SHM: bool> Clone for OnceLock<T, SHM> {
+impl<T: Clone, const SHM: bool, B: FutexBackend> Clone for OnceLock<T, SHM, B> {
     #[inline]
     fn clone(&self) -> Self {
         let cell = Self::new();
@@ -585,7 +676,7 @@ impl<T: Clone, const SHM: bool> Clone for OnceLock<T, SHM> {
 }
 
 //#[stable(feature = "once_cell", since = "1.70.0")]
-impl<T, const SHM: bool> From<T> for OnceLock<T, SHM> {
+impl<T, const SHM: bool, B: FutexBackend> From<T> for OnceLock<T, SHM, B> {
     /// Create a new cell with its contents set to `value`.
     ///
     /// # Example
@@ -612,7 +703,7 @@ impl<T, const SHM: bool> From<T> for OnceLock<T, SHM> {
 }
 
 //#[stable(feature = "once_cell", since = "1.70.0")]
-impl<T: PartialEq, const SHM: bool> PartialEq for OnceLock<T, SHM> {
+impl<T: PartialEq, const SHM: bool, B: FutexBackend> PartialEq for OnceLock<T, SHM, B> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
         self.get() == other.get()
@@ -620,10 +711,12 @@ impl<T: PartialEq, const SHM: bool> PartialEq for OnceLock<T, SHM> {
 }
 
 //#[stable(feature = "once_cell", since = "1.70.0")]
-impl<T: Eq, const SHM: bool> Eq for OnceLock<T, SHM> {}
+impl<T: Eq, const SHM: bool, B: FutexBackend> Eq for OnceLock<T, SHM, B> {}
 
 //#[stable(feature = "once_cell", since = "1.70.0")]
-/*unsafe*/ impl</*#[may_dangle]*/ T, const SHM: bool> Drop for OnceLock<T, SHM> {
+/*unsafe*/ impl</*#[may_dangle]*/ T, const SHM: bool, B: FutexBackend> Drop
+    for OnceLock<T, SHM, B>
+{
     #[inline]
     fn drop(&mut self) {
         if self.is_initialized() {
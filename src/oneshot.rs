@@ -0,0 +1,243 @@
+//! Oneshot channels, for sending a single value between threads.
+//!
+//! The channel's state is a single futex word, stored in the same allocation
+//! as the value, so creating a channel allocates once.
+//!
+//! # Examples
+//!
+//! ```
+//! use rustix_futex_sync::oneshot;
+//! use std::thread;
+//!
+//! let (tx, rx) = oneshot::channel();
+//! thread::spawn(move || {
+//!     tx.send(42).unwrap();
+//! });
+//! assert_eq!(rx.recv(), Ok(42));
+//! ```
+
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ptr;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering::{Acquire, Relaxed, Release},
+};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::SyscallBackend;
+use crate::wait_wake::{deadline, futex_wait_timespec, futex_wake};
+
+pub use crate::channel::{RecvError, RecvTimeoutError, SendError, TryRecvError};
+
+/// No value has been sent, and the receiver isn't waiting.
+const EMPTY: u32 = 0;
+/// No value has been sent, and the receiver is waiting for one.
+const WAITING: u32 = 1;
+/// A value has been sent and not yet received.
+const SENT: u32 = 2;
+/// The sender was dropped without sending, the receiver was dropped, or the
+/// value has been received.
+const DISCONNECTED: u32 = 3;
+
+/// Creates a oneshot channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        state: AtomicU32::new(EMPTY),
+        value: UnsafeCell::new(MaybeUninit::uninit()),
+    });
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver { inner },
+    )
+}
+
+struct Inner<T> {
+    state: AtomicU32,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: The value is only accessed by the sender before it's sent, and by
+// the receiver after, as coordinated by `state`.
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == SENT {
+            // SAFETY: The value was sent and never received.
+            unsafe { self.value.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+/// The sending half of a oneshot channel.
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value, waking the receiver if it's waiting.
+    ///
+    /// If the receiver has been dropped, this returns the value in an error.
+    pub fn send(self, t: T) -> Result<(), SendError<T>> {
+        // Don't run our `Drop`, which would disconnect the channel.
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again, so this moves the `Arc` out.
+        let inner = unsafe { ptr::read(&this.inner) };
+
+        if inner.state.load(Relaxed) == DISCONNECTED {
+            return Err(SendError(t));
+        }
+
+        // SAFETY: The value hasn't been sent, so the receiver doesn't access
+        // it.
+        unsafe { (*inner.value.get()).write(t) };
+
+        let mut state = inner.state.load(Relaxed);
+        loop {
+            if state == DISCONNECTED {
+                // The receiver was dropped while we were writing the value.
+                // SAFETY: We just wrote the value, and nobody else will
+                // access it.
+                return Err(SendError(unsafe { (*inner.value.get()).assume_init_read() }));
+            }
+            match inner.state.compare_exchange_weak(state, SENT, Release, Relaxed) {
+                Ok(_) => break,
+                Err(s) => state = s,
+            }
+        }
+
+        if state == WAITING {
+            futex_wake::<false, SyscallBackend>(&inner.state);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.inner.state.load(Relaxed) == DISCONNECTED
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        // We're being dropped without sending, so disconnect the channel.
+        if self.inner.state.swap(DISCONNECTED, Relaxed) == WAITING {
+            futex_wake::<false, SyscallBackend>(&self.inner.state);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a oneshot channel.
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the value, blocking until it's sent.
+    ///
+    /// If the sender is dropped without sending a value, or the value has
+    /// already been received, this returns an error.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.recv_optional_deadline(None).map_err(|err| match err {
+            RecvTimeoutError::Disconnected => RecvError,
+            RecvTimeoutError::Timeout => unreachable!(),
+        })
+    }
+
+    /// Attempts to receive the value without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self
+            .inner
+            .state
+            .compare_exchange(SENT, DISCONNECTED, Acquire, Relaxed)
+        {
+            // SAFETY: The value was sent, and the state change ensures that
+            // only we take it.
+            Ok(_) => Ok(unsafe { (*self.inner.value.get()).assume_init_read() }),
+            Err(DISCONNECTED) => Err(TryRecvError::Disconnected),
+            Err(_) => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Like [`recv`], but gives up if the value isn't sent within `timeout`.
+    ///
+    /// [`recv`]: Self::recv
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match self.try_recv() {
+            Ok(t) => return Ok(t),
+            Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+            Err(TryRecvError::Empty) => {}
+        }
+        // Overflows are rounded up to an infinite timeout.
+        self.recv_optional_deadline(deadline(timeout).as_ref())
+    }
+
+    /// Like [`recv_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`recv_timeout`]: Self::recv_timeout
+    pub fn recv_until(&self, deadline: &Timespec) -> Result<T, RecvTimeoutError> {
+        self.recv_optional_deadline(Some(deadline))
+    }
+
+    fn recv_optional_deadline(&self, deadline: Option<&Timespec>) -> Result<T, RecvTimeoutError> {
+        let mut timed_out = false;
+        loop {
+            match self.try_recv() {
+                Ok(t) => return Ok(t),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) if timed_out => return Err(RecvTimeoutError::Timeout),
+                Err(TryRecvError::Empty) => {}
+            }
+
+            // Let the sender know we're waiting.
+            if self
+                .inner
+                .state
+                .compare_exchange(EMPTY, WAITING, Relaxed, Relaxed)
+                .is_err_and(|s| s != WAITING)
+            {
+                continue;
+            }
+
+            timed_out = !futex_wait_timespec::<false, SyscallBackend>(
+                &self.inner.state,
+                WAITING,
+                deadline,
+            );
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        // If a value was sent, leave it for `Inner` to drop. Otherwise,
+        // disconnect the channel. The state may be `WAITING` if an earlier
+        // wait timed out.
+        self.inner
+            .state
+            .fetch_update(Relaxed, Relaxed, |state| match state {
+                EMPTY | WAITING => Some(DISCONNECTED),
+                _ => None,
+            })
+            .ok();
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
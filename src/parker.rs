@@ -0,0 +1,217 @@
+//! The following is derived from Rust's
+//! library/std/src/sys/sync/thread_parking/futex.rs at revision
+//! 22a5267c83a3e17f2b763279eb24bb632c45dc6b.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering::{Acquire, Release},
+};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::wait_wake::{deadline, futex_wait_timespec, futex_wake};
+
+const PARKED: u32 = u32::MAX;
+const EMPTY: u32 = 0;
+const NOTIFIED: u32 = 1;
+
+/// A thread parker, like the one behind `std::thread::park` and
+/// `Thread::unpark`.
+///
+/// A `Parker` holds a token which is initially absent. [`park`] blocks until
+/// the token is available and then consumes it, and [`Unparker::unpark`]
+/// makes the token available, waking the parked thread if there is one.
+///
+/// As with std's parker, only one thread, the one which owns the `Parker`,
+/// may park on it, which is why the parking functions are `unsafe`. Any
+/// thread may unpark it, through an [`Unparker`] obtained from [`unparker`].
+///
+/// `Parker` is guaranteed to be a `repr(transparent)` wrapper around a single
+/// `AtomicU32`, and an all-zeros `Parker` is a valid parker without the
+/// token.
+///
+/// [`park`]: Self::park
+/// [`unparker`]: Self::unparker
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::Parker;
+/// use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+/// use std::thread;
+///
+/// let parker = Parker::new();
+/// let unparker = parker.unparker();
+/// let flag = AtomicBool::new(false);
+///
+/// thread::scope(|s| {
+///     s.spawn(|| {
+///         flag.store(true, SeqCst);
+///         unparker.unpark();
+///     });
+///
+///     // Parking may return spuriously, so check the condition in a loop.
+///     while !flag.load(SeqCst) {
+///         // SAFETY: Only this thread parks on `parker`.
+///         unsafe { parker.park() };
+///     }
+/// });
+/// ```
+#[repr(transparent)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Parker<const SHM: bool, B = SyscallBackend> {
+    state: AtomicU32,
+    backend: PhantomData<B>,
+}
+
+/// A handle for unparking a [`Parker`].
+///
+/// [`Parker`]: crate::generic::Parker
+pub struct Unparker<'a, const SHM: bool, B = SyscallBackend> {
+    state: &'a AtomicU32,
+    backend: PhantomData<B>,
+}
+
+impl<'a, const SHM: bool, B> Clone for Unparker<'a, SHM, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, const SHM: bool, B> Copy for Unparker<'a, SHM, B> {}
+
+impl<const SHM: bool, B: FutexBackend> Parker<SHM, B> {
+    /// Creates a new `Parker` without the token.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(EMPTY),
+            backend: PhantomData,
+        }
+    }
+
+    /// Returns an [`Unparker`] for this `Parker`.
+    #[inline]
+    pub fn unparker(&self) -> Unparker<'_, SHM, B> {
+        Unparker {
+            state: &self.state,
+            backend: PhantomData,
+        }
+    }
+
+    /// Blocks the current thread until the token is available, and then
+    /// consumes it.
+    ///
+    /// This may also return spuriously, without consuming the token.
+    ///
+    /// # Safety
+    ///
+    /// This must only be called by the thread which owns this `Parker`, and
+    /// never by more than one thread at a time.
+    pub unsafe fn park(&self) {
+        // Change NOTIFIED=>EMPTY or EMPTY=>PARKED, and directly return in the
+        // first case.
+        if self.state.fetch_sub(1, Acquire) == NOTIFIED {
+            return;
+        }
+        loop {
+            // Wait for something to happen, assuming it's still set to PARKED.
+            futex_wait_timespec::<SHM, B>(&self.state, PARKED, None);
+            // Change NOTIFIED=>EMPTY and return in that case.
+            if self.state.compare_exchange(NOTIFIED, EMPTY, Acquire, Acquire).is_ok() {
+                return;
+            } else {
+                // Spurious wake up. We loop to try again.
+            }
+        }
+    }
+
+    /// Like [`park`], but gives up after `timeout` has elapsed.
+    ///
+    /// [`park`]: Self::park
+    ///
+    /// # Safety
+    ///
+    /// This must only be called by the thread which owns this `Parker`, and
+    /// never by more than one thread at a time.
+    pub unsafe fn park_timeout(&self, timeout: Duration) {
+        if self.state.fetch_sub(1, Acquire) == NOTIFIED {
+            return;
+        }
+        // Overflows are rounded up to an infinite timeout.
+        self.park_optional_deadline(deadline(timeout).as_ref())
+    }
+
+    /// Like [`park_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`park_timeout`]: Self::park_timeout
+    ///
+    /// # Safety
+    ///
+    /// This must only be called by the thread which owns this `Parker`, and
+    /// never by more than one thread at a time.
+    pub unsafe fn park_deadline(&self, deadline: &Timespec) {
+        if self.state.fetch_sub(1, Acquire) == NOTIFIED {
+            return;
+        }
+        self.park_optional_deadline(Some(deadline))
+    }
+
+    fn park_optional_deadline(&self, deadline: Option<&Timespec>) {
+        // Wait for something to happen, assuming it's still set to PARKED.
+        futex_wait_timespec::<SHM, B>(&self.state, PARKED, deadline);
+        // This is not just a store, because we need to establish a
+        // release-acquire ordering with unpark().
+        if self.state.swap(EMPTY, Acquire) == NOTIFIED {
+            // Woke up because of unpark().
+        } else {
+            // Timeout or spurious wake up.
+            // We return either way, because we can't easily tell if it was the
+            // timeout or not.
+        }
+    }
+}
+
+impl<'a, const SHM: bool, B: FutexBackend> Unparker<'a, SHM, B> {
+    /// Makes the token available, waking the thread parked on the
+    /// [`Parker`], if any.
+    ///
+    /// If the token is already available, this has no effect.
+    ///
+    /// [`Parker`]: crate::generic::Parker
+    #[inline]
+    pub fn unpark(&self) {
+        // Change PARKED=>NOTIFIED, EMPTY=>NOTIFIED, or NOTIFIED=>NOTIFIED, and
+        // wake the thread in the first case.
+        //
+        // Note that even NOTIFIED=>NOTIFIED results in a write. This is on
+        // purpose, to make sure every unpark() has a release-acquire ordering
+        // with park().
+        if self.state.swap(NOTIFIED, Release) == PARKED {
+            futex_wake::<SHM, B>(self.state);
+        }
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> Default for Parker<SHM, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> fmt::Debug for Parker<SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Parker").finish_non_exhaustive()
+    }
+}
+
+impl<'a, const SHM: bool, B: FutexBackend> fmt::Debug for Unparker<'a, SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Unparker").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,253 @@
+//! An MCS-style queue lock.
+
+use core::hint::spin_loop;
+use core::ptr::{null_mut, NonNull};
+use core::sync::atomic::{
+    AtomicPtr, AtomicU32,
+    Ordering::{AcqRel, Acquire, Relaxed, Release},
+};
+use rustix::thread::sched_yield;
+use crate::lock_api;
+use crate::futex_backend::SyscallBackend;
+use crate::wait_wake::{futex_wait_timespec, futex_wake};
+
+/// A mutual exclusion primitive in which each waiter spins and sleeps on its
+/// own futex word, implementing [`lock_api::RawMutex`].
+///
+/// With [`RawMutex`], all waiters wait on the mutex's own futex word, so
+/// under heavy contention they all contend for the same cache line and the
+/// same kernel futex hash bucket. `QueueMutex` is an [MCS lock]: each waiter
+/// enqueues a node on its own stack, and spins and then sleeps on a futex
+/// word in that node. Unlocking hands the lock directly to the first waiter
+/// in the queue and wakes only that waiter, so the lock is granted in FIFO
+/// order.
+///
+/// The uncontended lock and unlock are each a single compare-and-swap.
+///
+/// Since the nodes live on the waiters' stacks, this is only available for
+/// the non-shm case.
+///
+/// All of this `QueueMutex`'s methods are in its implementation of
+/// [`lock_api::RawMutex`].
+///
+/// [`RawMutex`]: crate::generic::RawMutex
+/// [`lock_api::RawMutex`]: https://docs.rs/lock_api/*/lock_api/trait.RawMutex.html
+/// [MCS lock]: https://doi.org/10.1145/103727.103729
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::lock_api::RawMutex as _;
+/// use rustix_futex_sync::QueueMutex;
+///
+/// let lock = QueueMutex::INIT;
+/// lock.lock();
+/// assert!(!lock.try_lock());
+/// unsafe { lock.unlock() };
+/// assert!(lock.try_lock());
+/// # unsafe { lock.unlock() };
+/// ```
+pub struct QueueMutex {
+    /// The last waiter in the queue, or `locked()` if the mutex is locked
+    /// and the queue is empty, or null if the mutex is unlocked.
+    tail: AtomicPtr<Node>,
+    /// The first waiter in the queue, once the owner knows it.
+    ///
+    /// The owner's node is removed from the queue when it acquires the lock,
+    /// since it's on the owner's stack, so the waiter behind it is recorded
+    /// here instead.
+    head: AtomicPtr<Node>,
+}
+
+/// A waiter's node, on its stack.
+struct Node {
+    /// The next waiter in the queue, once it has linked itself in.
+    next: AtomicPtr<Node>,
+    /// One of the states below. This is the futex word the waiter sleeps on.
+    state: AtomicU32,
+}
+
+/// The waiter is spinning.
+const WAITING: u32 = 0;
+/// The waiter is sleeping, or about to.
+const SLEEPING: u32 = 1;
+/// The lock has been handed to the waiter, but the unlocking thread is still
+/// waking it, so the node must stay alive.
+const WAKING: u32 = 2;
+/// The lock has been handed to the waiter, and the unlocking thread is done
+/// with the node.
+const GRANTED: u32 = 3;
+
+/// The `tail` value for a locked mutex with no waiters. This is never the
+/// address of a real node.
+#[inline]
+fn locked() -> *mut Node {
+    NonNull::dangling().as_ptr()
+}
+
+impl Node {
+    const fn new() -> Self {
+        Self {
+            next: AtomicPtr::new(null_mut()),
+            state: AtomicU32::new(WAITING),
+        }
+    }
+
+    /// Wait until the lock is handed to us.
+    fn wait(&self) {
+        // Spin first to speed things up if the lock is handed over quickly.
+        let mut spin = 100;
+        while self.state.load(Acquire) == WAITING && spin != 0 {
+            spin_loop();
+            spin -= 1;
+        }
+
+        if self
+            .state
+            .compare_exchange(WAITING, SLEEPING, Relaxed, Acquire)
+            .is_err()
+        {
+            // It's `GRANTED`, since we haven't set `SLEEPING`.
+            return;
+        }
+
+        loop {
+            match self.state.load(Acquire) {
+                GRANTED => return,
+                WAKING => {
+                    // The unlocking thread is about to finish with our node.
+                    // Yield, since waking us may have preempted it.
+                    sched_yield();
+                }
+                state => {
+                    futex_wait_timespec::<false, SyscallBackend>(&self.state, state, None);
+                }
+            }
+        }
+    }
+
+    /// Hand the lock to the waiter owning `node`.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be the node of a waiter in `wait`.
+    unsafe fn grant(node: *const Node) {
+        let state = &(*node).state;
+        if state
+            .compare_exchange(WAITING, GRANTED, Release, Relaxed)
+            .is_err()
+        {
+            // It's sleeping. It won't return until we set `GRANTED`, so its
+            // node stays alive while we wake it.
+            state.store(WAKING, Release);
+            futex_wake::<false, SyscallBackend>(state);
+            state.store(GRANTED, Release);
+        }
+    }
+}
+
+/// Wait for a waiter to finish linking itself into the queue. This is the
+/// short window between it swapping itself into `tail` and storing its
+/// pointer, so we spin, and yield in case it was preempted.
+fn wait_for_link(link: &AtomicPtr<Node>) -> *mut Node {
+    let mut spin = 100;
+    loop {
+        let node = link.load(Acquire);
+        if !node.is_null() {
+            return node;
+        }
+        if spin != 0 {
+            spin_loop();
+            spin -= 1;
+        } else {
+            sched_yield();
+        }
+    }
+}
+
+impl QueueMutex {
+    #[cold]
+    fn lock_contended(&self) {
+        let node = Node::new();
+        let node_ptr = &node as *const Node as *mut Node;
+
+        let prev = self.tail.swap(node_ptr, AcqRel);
+        if !prev.is_null() {
+            // Link ourselves in behind the previous waiter, or, if the queue
+            // was empty, as the first waiter.
+            if prev == locked() {
+                self.head.store(node_ptr, Release);
+            } else {
+                // SAFETY: The previous waiter keeps its node alive until we've
+                // linked ourselves in.
+                unsafe { (*prev).next.store(node_ptr, Release) };
+            }
+            node.wait();
+        }
+
+        // We own the lock. Remove our node from the queue, since it's on our
+        // stack, recording the waiter behind us, if any, in `head`.
+        if self
+            .tail
+            .compare_exchange(node_ptr, locked(), Relaxed, Relaxed)
+            .is_err()
+        {
+            let next = wait_for_link(&node.next);
+            self.head.store(next, Relaxed);
+        }
+    }
+}
+
+unsafe impl lock_api::RawMutex for QueueMutex {
+    type GuardMarker = lock_api::GuardNoSend;
+
+    const INIT: Self = Self {
+        tail: AtomicPtr::new(null_mut()),
+        head: AtomicPtr::new(null_mut()),
+    };
+
+    #[inline]
+    fn lock(&self) {
+        if self
+            .tail
+            .compare_exchange(null_mut(), locked(), Acquire, Relaxed)
+            .is_err()
+        {
+            self.lock_contended();
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.tail
+            .compare_exchange(null_mut(), locked(), Acquire, Relaxed)
+            .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        let mut head = self.head.load(Acquire);
+        if head.is_null() {
+            if self
+                .tail
+                .compare_exchange(locked(), null_mut(), Release, Relaxed)
+                .is_ok()
+            {
+                return;
+            }
+
+            // A waiter has joined the queue, and is about to record itself
+            // in `head`.
+            head = wait_for_link(&self.head);
+        }
+
+        // Hand the lock directly to the first waiter.
+        self.head.store(null_mut(), Relaxed);
+        Node::grant(head);
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        !self.tail.load(Relaxed).is_null()
+    }
+}
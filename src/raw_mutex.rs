@@ -1,3 +1,4 @@
+use crate::futex_backend::{FutexBackend, SyscallBackend};
 use crate::lock_api;
 
 /// An implementation of [`lock_api::RawMutex`].
@@ -10,9 +11,18 @@ use crate::lock_api;
 /// use rustix_futex_sync::lock_api::RawMutex as _;
 /// ```
 #[repr(transparent)]
-pub struct RawMutex<const SHM: bool>(crate::futex_mutex::Mutex<SHM>);
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct RawMutex<const SHM: bool, B = SyscallBackend>(crate::futex_mutex::Mutex<SHM, B>);
 
-unsafe impl<const SHM: bool> lock_api::RawMutex for RawMutex<SHM> {
+impl<const SHM: bool, B: FutexBackend> RawMutex<SHM, B> {
+    #[cfg(feature = "io_uring")]
+    #[inline]
+    pub(crate) fn futex_mutex(&self) -> &crate::futex_mutex::Mutex<SHM, B> {
+        &self.0
+    }
+}
+
+unsafe impl<const SHM: bool, B: FutexBackend> lock_api::RawMutex for RawMutex<SHM, B> {
     type GuardMarker = lock_api::GuardNoSend;
 
     const INIT: Self = Self(crate::futex_mutex::Mutex::new());
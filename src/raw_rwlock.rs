@@ -1,3 +1,4 @@
+use crate::futex_backend::{FutexBackend, SyscallBackend};
 use crate::lock_api;
 
 /// An implementation of [`lock_api::RawRwLock`].
@@ -10,9 +11,10 @@ use crate::lock_api;
 /// use rustix_futex_sync::lock_api::RawRwLock as _;
 /// ```
 #[repr(C)]
-pub struct RawRwLock<const SHM: bool>(crate::futex_rwlock::RwLock<SHM>);
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct RawRwLock<const SHM: bool, B = SyscallBackend>(crate::futex_rwlock::RwLock<SHM, B>);
 
-unsafe impl<const SHM: bool> lock_api::RawRwLock for RawRwLock<SHM> {
+unsafe impl<const SHM: bool, B: FutexBackend> lock_api::RawRwLock for RawRwLock<SHM, B> {
     type GuardMarker = lock_api::GuardNoSend;
 
     const INIT: Self = Self(crate::futex_rwlock::RwLock::new());
@@ -0,0 +1,291 @@
+//! A reentrant mutex for when we don't have the real `lock_api` crate.
+
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::generic::RawMutex;
+use crate::lock_api::{GetThreadId, RawMutex as _};
+
+/// A mutex which can be recursively locked by a single thread.
+///
+/// This is a polyfill for [`lock_api::ReentrantMutex`], for when the
+/// `lock_api` feature is disabled. The thread IDs come from `G`, such as
+/// [`RawThreadId`].
+///
+/// Since the lock can be held by multiple guards at once, the guards only
+/// give out shared references to the data. Use a [`Cell`] or [`RefCell`]
+/// for mutability.
+///
+/// [`lock_api::ReentrantMutex`]: https://docs.rs/lock_api/*/lock_api/struct.ReentrantMutex.html
+/// [`RawThreadId`]: crate::generic::RawThreadId
+/// [`RefCell`]: core::cell::RefCell
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::TidReentrantMutex;
+/// use std::cell::Cell;
+///
+/// let mutex: TidReentrantMutex<Cell<i32>> = TidReentrantMutex::new(Cell::new(0));
+/// let a = mutex.lock();
+/// let b = mutex.lock();
+/// a.set(1);
+/// assert_eq!(b.get(), 1);
+/// ```
+pub struct ReentrantMutex<G, T: ?Sized, const SHM: bool, B = SyscallBackend> {
+    raw: RawMutex<SHM, B>,
+    owner: AtomicUsize,
+    lock_count: Cell<usize>,
+    get_thread_id: G,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send, G: GetThreadId + Send, const SHM: bool, B: FutexBackend> Send
+    for ReentrantMutex<G, T, SHM, B>
+{
+}
+unsafe impl<T: ?Sized + Send, G: GetThreadId + Sync, const SHM: bool, B: FutexBackend> Sync
+    for ReentrantMutex<G, T, SHM, B>
+{
+}
+
+impl<T, G: GetThreadId, const SHM: bool, B: FutexBackend> ReentrantMutex<G, T, SHM, B> {
+    /// Creates a new reentrant mutex in an unlocked state ready for use.
+    #[inline]
+    pub const fn new(val: T) -> Self {
+        Self {
+            raw: RawMutex::INIT,
+            owner: AtomicUsize::new(0),
+            lock_count: Cell::new(0),
+            get_thread_id: G::INIT,
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, G: GetThreadId, const SHM: bool, B: FutexBackend> ReentrantMutex<G, T, SHM, B> {
+    #[inline]
+    fn lock_internal(&self, try_lock: impl FnOnce() -> bool) -> bool {
+        let id = self.get_thread_id.nonzero_thread_id().get();
+        if self.owner.load(Relaxed) == id {
+            self.lock_count.set(
+                self.lock_count
+                    .get()
+                    .checked_add(1)
+                    .expect("ReentrantMutex lock count overflow"),
+            );
+        } else {
+            if !try_lock() {
+                return false;
+            }
+            self.owner.store(id, Relaxed);
+            debug_assert_eq!(self.lock_count.get(), 0);
+            self.lock_count.set(1);
+        }
+        true
+    }
+
+    /// Acquires the reentrant mutex, blocking the current thread until it is
+    /// able to do so.
+    ///
+    /// If the mutex is held by another thread then this function will block
+    /// the local thread until it is available to acquire the mutex. If the
+    /// mutex is already held by the current thread then this function will
+    /// increment the lock reference count and return immediately.
+    #[inline]
+    pub fn lock(&self) -> ReentrantMutexGuard<'_, G, T, SHM, B> {
+        self.lock_internal(|| {
+            self.raw.lock();
+            true
+        });
+        // SAFETY: The lock is held, as required.
+        unsafe { ReentrantMutexGuard::new(self) }
+    }
+
+    /// Attempts to acquire this lock.
+    ///
+    /// If the lock could not be acquired at this time, then `None` is
+    /// returned. Otherwise, an RAII guard is returned. The lock will be
+    /// unlocked when the guard is dropped.
+    #[inline]
+    pub fn try_lock(&self) -> Option<ReentrantMutexGuard<'_, G, T, SHM, B>> {
+        if self.lock_internal(|| self.raw.try_lock()) {
+            // SAFETY: The lock is held, as required.
+            Some(unsafe { ReentrantMutexGuard::new(self) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `ReentrantMutex` mutably, no actual
+    /// locking needs to take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Checks whether the mutex is currently locked.
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.raw.is_locked()
+    }
+
+    /// Checks whether the mutex is currently held by the current thread.
+    #[inline]
+    pub fn is_owned_by_current_thread(&self) -> bool {
+        let id = self.get_thread_id.nonzero_thread_id().get();
+        self.owner.load(Relaxed) == id
+    }
+
+    /// Forcibly unlocks the mutex.
+    ///
+    /// # Safety
+    ///
+    /// This method must only be called if the current thread logically owns
+    /// a `ReentrantMutexGuard` but that guard has been discarded using
+    /// `mem::forget`.
+    #[inline]
+    pub unsafe fn force_unlock(&self) {
+        self.unlock();
+    }
+
+    /// Returns a raw pointer to the underlying data.
+    #[inline]
+    pub fn data_ptr(&self) -> *mut T {
+        self.data.get()
+    }
+
+    /// # Safety
+    ///
+    /// The mutex must be locked by the current thread.
+    #[inline]
+    unsafe fn unlock(&self) {
+        let lock_count = self.lock_count.get() - 1;
+        self.lock_count.set(lock_count);
+        if lock_count == 0 {
+            self.owner.store(0, Relaxed);
+            self.raw.unlock();
+        }
+    }
+}
+
+impl<T: Default, G: GetThreadId, const SHM: bool, B: FutexBackend> Default
+    for ReentrantMutex<G, T, SHM, B>
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T, G: GetThreadId, const SHM: bool, B: FutexBackend> From<T> for ReentrantMutex<G, T, SHM, B> {
+    #[inline]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, G: GetThreadId, const SHM: bool, B: FutexBackend> fmt::Debug
+    for ReentrantMutex<G, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ReentrantMutex");
+        match self.try_lock() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// An RAII implementation of a "scoped lock" of a [`ReentrantMutex`].
+///
+/// When this structure is dropped (falls out of scope), the lock will be
+/// unlocked, once the lock's other guards in this thread are dropped too.
+#[must_use = "if unused the ReentrantMutex will immediately unlock"]
+pub struct ReentrantMutexGuard<
+    'a,
+    G: GetThreadId,
+    T: ?Sized,
+    const SHM: bool,
+    B: FutexBackend = SyscallBackend,
+> {
+    mutex: &'a ReentrantMutex<G, T, SHM, B>,
+    // The lock must be released by the thread that acquired it.
+    marker: PhantomData<(&'a T, *mut ())>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync, G: GetThreadId + Sync, const SHM: bool, B: FutexBackend> Sync
+    for ReentrantMutexGuard<'a, G, T, SHM, B>
+{
+}
+
+impl<'a, T: ?Sized, G: GetThreadId, const SHM: bool, B: FutexBackend>
+    ReentrantMutexGuard<'a, G, T, SHM, B>
+{
+    /// # Safety
+    ///
+    /// The mutex must be locked by the current thread.
+    #[inline]
+    unsafe fn new(mutex: &'a ReentrantMutex<G, T, SHM, B>) -> Self {
+        Self {
+            mutex,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the original `ReentrantMutex` object.
+    #[inline]
+    pub fn remutex(s: &Self) -> &'a ReentrantMutex<G, T, SHM, B> {
+        s.mutex
+    }
+}
+
+impl<'a, T: ?Sized, G: GetThreadId, const SHM: bool, B: FutexBackend> Deref
+    for ReentrantMutexGuard<'a, G, T, SHM, B>
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: The lock is held.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized, G: GetThreadId, const SHM: bool, B: FutexBackend> Drop
+    for ReentrantMutexGuard<'a, G, T, SHM, B>
+{
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: The lock is held.
+        unsafe { self.mutex.unlock() }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug, G: GetThreadId, const SHM: bool, B: FutexBackend> fmt::Debug
+    for ReentrantMutexGuard<'a, G, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Display, G: GetThreadId, const SHM: bool, B: FutexBackend> fmt::Display
+    for ReentrantMutexGuard<'a, G, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
@@ -0,0 +1,338 @@
+//! A helper for placing shared-memory synchronization primitives in a
+//! mapping shared between processes.
+
+use core::ffi::{c_void, CStr};
+use core::fmt;
+use core::mem::{align_of, size_of};
+use core::ptr::{null_mut, NonNull};
+use core::sync::atomic::{AtomicI32, AtomicI64, AtomicU32, AtomicU64};
+use rustix::fd::{AsFd, BorrowedFd, OwnedFd};
+use rustix::fs::{fstat, ftruncate, memfd_create, MemfdFlags};
+use rustix::io;
+use rustix::mm::{mmap, munmap, MapFlags, ProtFlags};
+use crate::generic::{
+    Event, Latch, Once, OnceLock, Parker, RawCondvar, RawMutex, RawRwLock, ReprCMutex,
+    ReprCRwLock, Semaphore, SeqLock, TicketMutex,
+};
+
+/// The number of bytes at the start of a [`Region`] reserved for its header.
+///
+/// The header contains the [`Once`] used to coordinate initialization. This
+/// is a multiple of the cache-line size on common architectures, so the data
+/// area that follows it starts on its own cache line.
+const HEADER_SIZE: usize = 64;
+
+/// Types which may be placed in a [`Region`].
+///
+/// # Safety
+///
+/// Implementations must be valid when all of their bytes are zero, must not
+/// contain pointers or any other values which are only meaningful within a
+/// single process, and must only synchronize using futexes without the
+/// `FUTEX_PRIVATE_FLAG`, which is what the types in the [`shm`] module do.
+///
+/// This trait doesn't make it safe to place a type at overlapping offsets
+/// with a different type; see [`Region::place`].
+///
+/// [`shm`]: crate::shm
+pub unsafe trait Placeable: Sync {}
+
+unsafe impl Placeable for AtomicU32 {}
+unsafe impl Placeable for AtomicI32 {}
+unsafe impl Placeable for AtomicU64 {}
+unsafe impl Placeable for AtomicI64 {}
+unsafe impl Placeable for u8 {}
+unsafe impl Placeable for u16 {}
+unsafe impl Placeable for u32 {}
+unsafe impl Placeable for u64 {}
+unsafe impl Placeable for i8 {}
+unsafe impl Placeable for i16 {}
+unsafe impl Placeable for i32 {}
+unsafe impl Placeable for i64 {}
+unsafe impl<T: Placeable, const N: usize> Placeable for [T; N] {}
+
+unsafe impl Placeable for RawMutex<true> {}
+unsafe impl Placeable for RawRwLock<true> {}
+unsafe impl Placeable for TicketMutex<true> {}
+unsafe impl Placeable for RawCondvar<true> {}
+unsafe impl Placeable for crate::generic::Condvar<true> {}
+unsafe impl Placeable for Once<true> {}
+unsafe impl Placeable for Semaphore<true> {}
+unsafe impl Placeable for Event<true> {}
+unsafe impl Placeable for Latch<true> {}
+unsafe impl Placeable for Parker<true> {}
+unsafe impl Placeable for crate::shm::RobustOnce {}
+unsafe impl<T: Placeable + Send> Placeable for OnceLock<T, true> {}
+unsafe impl<T: Placeable + Send> Placeable for ReprCMutex<T, true> {}
+unsafe impl<T: Placeable + Send> Placeable for ReprCRwLock<T, true> {}
+unsafe impl<T: Placeable + Copy + Send> Placeable for SeqLock<T, true> {}
+
+/// A memory mapping which can be shared between processes, for holding the
+/// types in the [`shm`] module.
+///
+/// A `Region` is backed by either an anonymous memfd, which can be shared
+/// with other processes by passing its file descriptor or by `fork`, or a
+/// named shared-memory object in `/dev/shm`, which other processes can open
+/// by name.
+///
+/// The start of a `Region` holds a small header containing a [`shm::Once`],
+/// which processes can use to coordinate initialization with
+/// [`Region::init`]. The rest of the `Region` is the data area, in which
+/// [`Placeable`] types can be accessed with [`Region::place`]. The data area
+/// starts out zero-filled, which is a valid initial state for all
+/// `Placeable` types.
+///
+/// [`shm`]: crate::shm
+/// [`shm::Once`]: crate::shm::Once
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::shm::{Region, ReprCMutex};
+/// use std::ffi::CStr;
+///
+/// let name = CStr::from_bytes_with_nul(b"example\0").unwrap();
+/// let region = Region::create_anonymous(name, 4096)?;
+///
+/// // SAFETY: Offset 0 only ever holds a `ReprCMutex<u64>`.
+/// let mutex = unsafe { region.place::<ReprCMutex<u64>>(0) };
+///
+/// region.init(|| {
+///     *mutex.lock() = 42;
+/// });
+///
+/// assert_eq!(*mutex.lock(), 42);
+/// # Ok::<(), rustix::io::Errno>(())
+/// ```
+pub struct Region {
+    fd: OwnedFd,
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// SAFETY: The mapping is only accessed through `Placeable` types, which are
+// `Sync`.
+unsafe impl Send for Region {}
+unsafe impl Sync for Region {}
+
+impl Region {
+    /// Creates a new `Region` backed by an anonymous memfd, with a data area
+    /// of `data_len` bytes.
+    ///
+    /// `name` is only used for debugging, and appears in `/proc/self/fd`.
+    /// The memfd can be shared with other processes through [`AsFd`], and
+    /// opened with [`Region::from_fd`].
+    pub fn create_anonymous(name: &CStr, data_len: usize) -> io::Result<Self> {
+        let fd = memfd_create(name, MemfdFlags::CLOEXEC)?;
+        ftruncate(&fd, total_len(data_len)? as u64)?;
+        Self::map(fd)
+    }
+
+    /// Creates a new named shared-memory object with a data area of
+    /// `data_len` bytes, and maps it.
+    ///
+    /// `name` should begin with a slash and contain no other slashes. This
+    /// fails with `Errno::EXIST` if an object with the same name already
+    /// exists.
+    pub fn create(name: &CStr, data_len: usize) -> io::Result<Self> {
+        let fd = rustix::shm::open(
+            name,
+            rustix::shm::OFlags::CREATE | rustix::shm::OFlags::EXCL | rustix::shm::OFlags::RDWR,
+            rustix::shm::Mode::RUSR | rustix::shm::Mode::WUSR,
+        )?;
+        ftruncate(&fd, total_len(data_len)? as u64)?;
+        Self::map(fd)
+    }
+
+    /// Opens an existing named shared-memory object, and maps it.
+    ///
+    /// If another process has just created the object with
+    /// [`Region::create`] and not yet set its size, this fails with
+    /// `Errno::INVAL`. Use [`Region::create_or_open`] to avoid this.
+    pub fn open(name: &CStr) -> io::Result<Self> {
+        let fd = rustix::shm::open(
+            name,
+            rustix::shm::OFlags::RDWR,
+            rustix::shm::Mode::empty(),
+        )?;
+        Self::map(fd)
+    }
+
+    /// Opens a named shared-memory object, creating it with a data area of
+    /// `data_len` bytes if it doesn't already exist, and maps it.
+    ///
+    /// This is suitable for having several processes race to set up a
+    /// `Region`, with [`Region::init`] deciding which of them initializes
+    /// it.
+    pub fn create_or_open(name: &CStr, data_len: usize) -> io::Result<Self> {
+        let len = total_len(data_len)?;
+        let fd = rustix::shm::open(
+            name,
+            rustix::shm::OFlags::CREATE | rustix::shm::OFlags::RDWR,
+            rustix::shm::Mode::RUSR | rustix::shm::Mode::WUSR,
+        )?;
+
+        // Another process may have created the object and not yet set its
+        // size. Extending it with `ftruncate` is idempotent, and fills it
+        // with zeros.
+        if (fstat(&fd)?.st_size as u64) < len as u64 {
+            ftruncate(&fd, len as u64)?;
+        }
+
+        Self::map(fd)
+    }
+
+    /// Removes a named shared-memory object.
+    ///
+    /// Processes which have already opened it can continue to use it.
+    pub fn unlink(name: &CStr) -> io::Result<()> {
+        rustix::shm::unlink(name)
+    }
+
+    /// Maps a `Region` from a file descriptor, such as one received from a
+    /// process that created it with [`Region::create_anonymous`].
+    pub fn from_fd(fd: OwnedFd) -> io::Result<Self> {
+        Self::map(fd)
+    }
+
+    fn map(fd: OwnedFd) -> io::Result<Self> {
+        let len = usize::try_from(fstat(&fd)?.st_size).map_err(|_| io::Errno::FBIG)?;
+        if len < HEADER_SIZE {
+            return Err(io::Errno::INVAL);
+        }
+
+        // SAFETY: We're creating a new mapping, not replacing any existing
+        // memory.
+        let ptr = unsafe {
+            mmap(
+                null_mut(),
+                len,
+                ProtFlags::READ | ProtFlags::WRITE,
+                MapFlags::SHARED,
+                &fd,
+                0,
+            )?
+        };
+
+        Ok(Self {
+            fd,
+            ptr: NonNull::new(ptr.cast::<u8>()).unwrap(),
+            len,
+        })
+    }
+
+    /// Returns the length of the data area, in bytes.
+    #[inline]
+    pub fn data_len(&self) -> usize {
+        self.len - HEADER_SIZE
+    }
+
+    /// Returns a reference to the `T` at `offset` bytes into the data area.
+    ///
+    /// # Safety
+    ///
+    /// While the returned reference is alive, the memory of the `T` must
+    /// only be accessed, from this process or any other process sharing the
+    /// memory, through other places of a `T` at the same offset, or through
+    /// atomics of the same type and address as atomics within the `T`, such
+    /// as the header of a [`Versioned`].
+    ///
+    /// In particular, placing different types at overlapping offsets, for
+    /// example a `u32` within a [`ReprCMutex`], or two locks which share a
+    /// futex word, is undefined behavior, and can let the primitives corrupt
+    /// each other. `Versioned` can detect processes which disagree about the
+    /// kind, size, or alignment of the primitive at an offset, before either
+    /// of them uses it.
+    ///
+    /// [`Versioned`]: crate::shm::Versioned
+    /// [`ReprCMutex`]: crate::shm::ReprCMutex
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not a multiple of the alignment of `T`, if the
+    /// alignment of `T` is greater than the alignment of the data area, which
+    /// is 64 bytes, or if a `T` at `offset` would extend past the end of the
+    /// data area.
+    #[inline]
+    pub unsafe fn place<T: Placeable>(&self, offset: usize) -> &T {
+        assert!(align_of::<T>() <= HEADER_SIZE, "overaligned type in Region");
+        assert!(offset % align_of::<T>() == 0, "misaligned offset in Region");
+        assert!(
+            offset
+                .checked_add(size_of::<T>())
+                .is_some_and(|end| end <= self.data_len()),
+            "offset out of bounds in Region"
+        );
+        // SAFETY: We checked that the `T` is in bounds and aligned (the data
+        // area is aligned to `HEADER_SIZE`, and we checked that `T`'s
+        // alignment is no greater). The memory is initialized, `Placeable`
+        // types are valid for any state another process may leave them in,
+        // and the caller guarantees the memory isn't used as another type.
+        unsafe { &*self.ptr.as_ptr().add(HEADER_SIZE + offset).cast::<T>() }
+    }
+
+    /// Runs `f` if this `Region` has not yet been initialized by any process
+    /// sharing it.
+    ///
+    /// This uses the [`shm::Once`] in the `Region`'s header, so it has the
+    /// same behavior as [`Once::call_once`]: if another process is currently
+    /// initializing the `Region`, this waits for it to finish, and if an
+    /// initialization panics, the `Region` is poisoned.
+    ///
+    /// [`shm::Once`]: crate::shm::Once
+    /// [`Once::call_once`]: crate::generic::Once::call_once
+    #[inline]
+    #[track_caller]
+    pub fn init<F: FnOnce()>(&self, f: F) {
+        self.header().call_once(f)
+    }
+
+    /// Returns `true` if some process has completed [`Region::init`].
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        self.header().is_completed()
+    }
+
+    /// Blocks until some process has completed [`Region::init`].
+    #[inline]
+    #[track_caller]
+    pub fn wait_initialized(&self) {
+        self.header().wait()
+    }
+
+    fn header(&self) -> &Once<true> {
+        // SAFETY: `map` checked that the mapping is at least `HEADER_SIZE`
+        // bytes long, and the mapping is page-aligned.
+        unsafe { &*self.ptr.as_ptr().cast::<Once<true>>() }
+    }
+}
+
+fn total_len(data_len: usize) -> io::Result<usize> {
+    data_len.checked_add(HEADER_SIZE).ok_or(io::Errno::FBIG)
+}
+
+impl AsFd for Region {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        // SAFETY: We created this mapping, and all references into it borrow
+        // from `self`.
+        unsafe {
+            munmap(self.ptr.as_ptr().cast::<c_void>(), self.len).ok();
+        }
+    }
+}
+
+impl fmt::Debug for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Region")
+            .field("fd", &self.fd)
+            .field("data_len", &self.data_len())
+            .finish_non_exhaustive()
+    }
+}
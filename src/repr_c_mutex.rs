@@ -0,0 +1,246 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::ops::{Deref, DerefMut};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::generic::RawMutex;
+use crate::lock_api::RawMutex as _;
+
+/// A mutual exclusion primitive with a guaranteed layout.
+///
+/// This provides the same locking behavior as [`Mutex`], however where
+/// `Mutex` is a [`lock_api::Mutex`] and has an unspecified layout,
+/// `ReprCMutex` is guaranteed to be a `repr(C)` struct containing a
+/// [`RawMutex`] at offset 0, followed by the `T` at offset
+/// [`DATA_OFFSET`]. This makes it suitable for placing at a fixed offset in
+/// memory shared between separately compiled programs.
+///
+/// An all-zeros `ReprCMutex` is a valid unlocked mutex whenever an all-zeros
+/// `T` is a valid `T`.
+///
+/// [`Mutex`]: crate::generic::Mutex
+/// [`lock_api::Mutex`]: https://docs.rs/lock_api/*/lock_api/struct.Mutex.html
+/// [`DATA_OFFSET`]: Self::DATA_OFFSET
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::ReprCMutex;
+///
+/// let mutex = ReprCMutex::new(0_u64);
+/// *mutex.lock() += 1;
+/// assert_eq!(*mutex.lock(), 1);
+///
+/// assert_eq!(ReprCMutex::<u64>::DATA_OFFSET, 8);
+/// ```
+#[repr(C)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct ReprCMutex<T: ?Sized, const SHM: bool, B = SyscallBackend> {
+    raw: RawMutex<SHM, B>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send, const SHM: bool, B: FutexBackend> Send for ReprCMutex<T, SHM, B> {}
+unsafe impl<T: ?Sized + Send, const SHM: bool, B: FutexBackend> Sync for ReprCMutex<T, SHM, B> {}
+
+impl<T, const SHM: bool, B: FutexBackend> ReprCMutex<T, SHM, B> {
+    /// The offset, in bytes, of the `T` within a `ReprCMutex`.
+    ///
+    /// This is the size of a `RawMutex`, rounded up to the alignment of `T`.
+    pub const DATA_OFFSET: usize =
+        (size_of::<RawMutex<SHM, B>>() + align_of::<T>() - 1) & !(align_of::<T>() - 1);
+
+    /// Creates a new mutex in an unlocked state ready for use.
+    #[inline]
+    pub const fn new(val: T) -> Self {
+        Self {
+            raw: RawMutex::INIT,
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, const SHM: bool, B: FutexBackend> ReprCMutex<T, SHM, B> {
+    /// Acquires the mutex, blocking the current thread until it is able to
+    /// do so.
+    ///
+    /// Attempts to lock a mutex in the thread which already holds the lock
+    /// will result in a deadlock.
+    #[inline]
+    pub fn lock(&self) -> ReprCMutexGuard<'_, T, SHM, B> {
+        self.raw.lock();
+        // SAFETY: The lock is held, as required.
+        unsafe { ReprCMutexGuard::new(self) }
+    }
+
+    /// Attempts to acquire this lock.
+    ///
+    /// If the lock could not be acquired at this time, then `None` is
+    /// returned. Otherwise, an RAII guard is returned. The lock will be
+    /// unlocked when the guard is dropped.
+    #[inline]
+    pub fn try_lock(&self) -> Option<ReprCMutexGuard<'_, T, SHM, B>> {
+        if self.raw.try_lock() {
+            // SAFETY: The lock is held, as required.
+            Some(unsafe { ReprCMutexGuard::new(self) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `ReprCMutex` mutably, no actual locking
+    /// needs to take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Checks whether the mutex is currently locked.
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.raw.is_locked()
+    }
+
+    /// Forcibly unlocks the mutex.
+    ///
+    /// # Safety
+    ///
+    /// This method must only be called if the current thread logically owns
+    /// a `ReprCMutexGuard` but that guard has been discarded using
+    /// `mem::forget`.
+    #[inline]
+    pub unsafe fn force_unlock(&self) {
+        self.raw.unlock();
+    }
+
+    /// Returns the underlying raw mutex object.
+    ///
+    /// # Safety
+    ///
+    /// This method is unsafe because it allows unlocking a mutex while
+    /// still holding a reference to a `ReprCMutexGuard`.
+    #[inline]
+    pub unsafe fn raw(&self) -> &RawMutex<SHM, B> {
+        &self.raw
+    }
+
+    /// Returns a raw pointer to the underlying data.
+    #[inline]
+    pub fn data_ptr(&self) -> *mut T {
+        self.data.get()
+    }
+}
+
+impl<T: Default, const SHM: bool, B: FutexBackend> Default for ReprCMutex<T, SHM, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T, const SHM: bool, B: FutexBackend> From<T> for ReprCMutex<T, SHM, B> {
+    #[inline]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, const SHM: bool, B: FutexBackend> fmt::Debug
+    for ReprCMutex<T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ReprCMutex");
+        match self.try_lock() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// An RAII implementation of a "scoped lock" of a [`ReprCMutex`].
+///
+/// When this structure is dropped (falls out of scope), the lock will be
+/// unlocked.
+#[must_use = "if unused the ReprCMutex will immediately unlock"]
+pub struct ReprCMutexGuard<'a, T: ?Sized, const SHM: bool, B: FutexBackend = SyscallBackend> {
+    mutex: &'a ReprCMutex<T, SHM, B>,
+    // Like `lock_api::GuardNoSend`, the lock must be released by the thread
+    // that acquired it.
+    marker: PhantomData<(&'a mut T, *mut ())>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync, const SHM: bool, B: FutexBackend> Sync
+    for ReprCMutexGuard<'a, T, SHM, B>
+{
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> ReprCMutexGuard<'a, T, SHM, B> {
+    /// # Safety
+    ///
+    /// The mutex must be locked by the current thread.
+    #[inline]
+    unsafe fn new(mutex: &'a ReprCMutex<T, SHM, B>) -> Self {
+        Self {
+            mutex,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the original `ReprCMutex` object.
+    #[inline]
+    pub fn mutex(s: &Self) -> &'a ReprCMutex<T, SHM, B> {
+        s.mutex
+    }
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> Deref for ReprCMutexGuard<'a, T, SHM, B> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: The lock is held.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> DerefMut for ReprCMutexGuard<'a, T, SHM, B> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The lock is held.
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> Drop for ReprCMutexGuard<'a, T, SHM, B> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: The lock is held.
+        unsafe { self.mutex.raw.unlock() }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug, const SHM: bool, B: FutexBackend> fmt::Debug
+    for ReprCMutexGuard<'a, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Display, const SHM: bool, B: FutexBackend> fmt::Display
+    for ReprCMutexGuard<'a, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
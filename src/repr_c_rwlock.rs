@@ -0,0 +1,335 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::ops::{Deref, DerefMut};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::generic::RawRwLock;
+use crate::lock_api::RawRwLock as _;
+
+/// A reader-writer lock with a guaranteed layout.
+///
+/// This provides the same locking behavior as [`RwLock`], however where
+/// `RwLock` is a [`lock_api::RwLock`] and has an unspecified layout,
+/// `ReprCRwLock` is guaranteed to be a `repr(C)` struct containing a
+/// [`RawRwLock`] at offset 0, followed by the `T` at offset
+/// [`DATA_OFFSET`]. This makes it suitable for placing at a fixed offset in
+/// memory shared between separately compiled programs.
+///
+/// An all-zeros `ReprCRwLock` is a valid unlocked lock whenever an all-zeros
+/// `T` is a valid `T`.
+///
+/// [`RwLock`]: crate::generic::RwLock
+/// [`lock_api::RwLock`]: https://docs.rs/lock_api/*/lock_api/struct.RwLock.html
+/// [`DATA_OFFSET`]: Self::DATA_OFFSET
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::ReprCRwLock;
+///
+/// let lock = ReprCRwLock::new(5_u32);
+/// {
+///     let r1 = lock.read();
+///     let r2 = lock.read();
+///     assert_eq!(*r1 + *r2, 10);
+/// }
+/// *lock.write() += 1;
+/// assert_eq!(*lock.read(), 6);
+///
+/// assert_eq!(ReprCRwLock::<u32>::DATA_OFFSET, 8);
+/// ```
+#[repr(C)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct ReprCRwLock<T: ?Sized, const SHM: bool, B = SyscallBackend> {
+    raw: RawRwLock<SHM, B>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send, const SHM: bool, B: FutexBackend> Send for ReprCRwLock<T, SHM, B> {}
+unsafe impl<T: ?Sized + Send + Sync, const SHM: bool, B: FutexBackend> Sync
+    for ReprCRwLock<T, SHM, B>
+{
+}
+
+impl<T, const SHM: bool, B: FutexBackend> ReprCRwLock<T, SHM, B> {
+    /// The offset, in bytes, of the `T` within a `ReprCRwLock`.
+    ///
+    /// This is the size of a `RawRwLock`, rounded up to the alignment of `T`.
+    pub const DATA_OFFSET: usize =
+        (size_of::<RawRwLock<SHM, B>>() + align_of::<T>() - 1) & !(align_of::<T>() - 1);
+
+    /// Creates a new instance of a `ReprCRwLock` which is unlocked.
+    #[inline]
+    pub const fn new(val: T) -> Self {
+        Self {
+            raw: RawRwLock::INIT,
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Consumes this `ReprCRwLock`, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized, const SHM: bool, B: FutexBackend> ReprCRwLock<T, SHM, B> {
+    /// Locks this `ReprCRwLock` with shared read access, blocking the current
+    /// thread until it can be acquired.
+    #[inline]
+    pub fn read(&self) -> ReprCRwLockReadGuard<'_, T, SHM, B> {
+        self.raw.lock_shared();
+        // SAFETY: A shared lock is held, as required.
+        unsafe { ReprCRwLockReadGuard::new(self) }
+    }
+
+    /// Attempts to acquire this `ReprCRwLock` with shared read access.
+    ///
+    /// If the access could not be granted at this time, then `None` is
+    /// returned.
+    #[inline]
+    pub fn try_read(&self) -> Option<ReprCRwLockReadGuard<'_, T, SHM, B>> {
+        if self.raw.try_lock_shared() {
+            // SAFETY: A shared lock is held, as required.
+            Some(unsafe { ReprCRwLockReadGuard::new(self) })
+        } else {
+            None
+        }
+    }
+
+    /// Locks this `ReprCRwLock` with exclusive write access, blocking the
+    /// current thread until it can be acquired.
+    #[inline]
+    pub fn write(&self) -> ReprCRwLockWriteGuard<'_, T, SHM, B> {
+        self.raw.lock_exclusive();
+        // SAFETY: An exclusive lock is held, as required.
+        unsafe { ReprCRwLockWriteGuard::new(self) }
+    }
+
+    /// Attempts to lock this `ReprCRwLock` with exclusive write access.
+    ///
+    /// If the lock could not be acquired at this time, then `None` is
+    /// returned.
+    #[inline]
+    pub fn try_write(&self) -> Option<ReprCRwLockWriteGuard<'_, T, SHM, B>> {
+        if self.raw.try_lock_exclusive() {
+            // SAFETY: An exclusive lock is held, as required.
+            Some(unsafe { ReprCRwLockWriteGuard::new(self) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `ReprCRwLock` mutably, no actual locking
+    /// needs to take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Checks whether this `ReprCRwLock` is currently locked in any way.
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.raw.is_locked()
+    }
+
+    /// Returns the underlying raw reader-writer lock object.
+    ///
+    /// # Safety
+    ///
+    /// This method is unsafe because it allows unlocking the lock while
+    /// still holding a reference to a lock guard.
+    #[inline]
+    pub unsafe fn raw(&self) -> &RawRwLock<SHM, B> {
+        &self.raw
+    }
+
+    /// Returns a raw pointer to the underlying data.
+    #[inline]
+    pub fn data_ptr(&self) -> *mut T {
+        self.data.get()
+    }
+}
+
+impl<T: Default, const SHM: bool, B: FutexBackend> Default for ReprCRwLock<T, SHM, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T, const SHM: bool, B: FutexBackend> From<T> for ReprCRwLock<T, SHM, B> {
+    #[inline]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, const SHM: bool, B: FutexBackend> fmt::Debug
+    for ReprCRwLock<T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ReprCRwLock");
+        match self.try_read() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// RAII structure used to release the shared read access of a
+/// [`ReprCRwLock`] when dropped.
+#[must_use = "if unused the ReprCRwLock will immediately unlock"]
+pub struct ReprCRwLockReadGuard<'a, T: ?Sized, const SHM: bool, B: FutexBackend = SyscallBackend> {
+    rwlock: &'a ReprCRwLock<T, SHM, B>,
+    // Like `lock_api::GuardNoSend`, the lock must be released by the thread
+    // that acquired it.
+    marker: PhantomData<(&'a T, *mut ())>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync, const SHM: bool, B: FutexBackend> Sync
+    for ReprCRwLockReadGuard<'a, T, SHM, B>
+{
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> ReprCRwLockReadGuard<'a, T, SHM, B> {
+    /// # Safety
+    ///
+    /// The lock must be read-locked by the current thread.
+    #[inline]
+    unsafe fn new(rwlock: &'a ReprCRwLock<T, SHM, B>) -> Self {
+        Self {
+            rwlock,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the original `ReprCRwLock` object.
+    #[inline]
+    pub fn rwlock(s: &Self) -> &'a ReprCRwLock<T, SHM, B> {
+        s.rwlock
+    }
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> Deref
+    for ReprCRwLockReadGuard<'a, T, SHM, B>
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: A shared lock is held.
+        unsafe { &*self.rwlock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> Drop for ReprCRwLockReadGuard<'a, T, SHM, B> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: A shared lock is held.
+        unsafe { self.rwlock.raw.unlock_shared() }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug, const SHM: bool, B: FutexBackend> fmt::Debug
+    for ReprCRwLockReadGuard<'a, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Display, const SHM: bool, B: FutexBackend> fmt::Display
+    for ReprCRwLockReadGuard<'a, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// RAII structure used to release the exclusive write access of a
+/// [`ReprCRwLock`] when dropped.
+#[must_use = "if unused the ReprCRwLock will immediately unlock"]
+pub struct ReprCRwLockWriteGuard<'a, T: ?Sized, const SHM: bool, B: FutexBackend = SyscallBackend> {
+    rwlock: &'a ReprCRwLock<T, SHM, B>,
+    // Like `lock_api::GuardNoSend`, the lock must be released by the thread
+    // that acquired it.
+    marker: PhantomData<(&'a mut T, *mut ())>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync, const SHM: bool, B: FutexBackend> Sync
+    for ReprCRwLockWriteGuard<'a, T, SHM, B>
+{
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> ReprCRwLockWriteGuard<'a, T, SHM, B> {
+    /// # Safety
+    ///
+    /// The lock must be write-locked by the current thread.
+    #[inline]
+    unsafe fn new(rwlock: &'a ReprCRwLock<T, SHM, B>) -> Self {
+        Self {
+            rwlock,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the original `ReprCRwLock` object.
+    #[inline]
+    pub fn rwlock(s: &Self) -> &'a ReprCRwLock<T, SHM, B> {
+        s.rwlock
+    }
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> Deref
+    for ReprCRwLockWriteGuard<'a, T, SHM, B>
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: An exclusive lock is held.
+        unsafe { &*self.rwlock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> DerefMut
+    for ReprCRwLockWriteGuard<'a, T, SHM, B>
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: An exclusive lock is held.
+        unsafe { &mut *self.rwlock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized, const SHM: bool, B: FutexBackend> Drop
+    for ReprCRwLockWriteGuard<'a, T, SHM, B>
+{
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: An exclusive lock is held.
+        unsafe { self.rwlock.raw.unlock_exclusive() }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug, const SHM: bool, B: FutexBackend> fmt::Debug
+    for ReprCRwLockWriteGuard<'a, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Display, const SHM: bool, B: FutexBackend> fmt::Display
+    for ReprCRwLockWriteGuard<'a, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
@@ -0,0 +1,409 @@
+//! A `Once` for shared memory which recovers when the process running its
+//! initialization dies.
+
+use core::fmt;
+use core::panic::{RefUnwindSafe, UnwindSafe};
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use core::time::Duration;
+use rustix::event::{poll, PollFd, PollFlags};
+use rustix::io::Errno;
+use rustix::process::{getpid, pidfd_open, test_kill_process, Pid, PidfdFlags};
+use rustix::time::{ClockId, Timespec};
+use crate::futex_backend::SyscallBackend;
+use crate::futex_once as sys;
+use crate::wait_wake::{deadline, futex_wait_timespec, futex_wake_all};
+use crate::OnceState;
+
+// The low bits hold the same states as `Once`, with the same meanings.
+const INCOMPLETE: u32 = 0;
+const POISONED: u32 = 1;
+const RUNNING: u32 = 2;
+const COMPLETE: u32 = 3;
+const STATE_MASK: u32 = 0b11;
+
+/// Set if there are waiting threads. May only be set if the state is not
+/// COMPLETE.
+const QUEUED: u32 = 0b100;
+
+/// Set, along with POISONED, when the process running the initialization
+/// died, so that the next initialization knows it's recovering.
+const DIED: u32 = 0b1000;
+
+/// While RUNNING, the bits above this hold the PID of the process running
+/// the initialization. Linux PIDs are at most 2^22, so they fit.
+const OWNER_SHIFT: u32 = 4;
+
+/// How long to wait for a RUNNING initialization before checking whether
+/// its process is still alive.
+const LIVENESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A [`Once`] for memory shared between processes, which recovers when a
+/// process dies while running its initialization.
+///
+/// With a plain [`shm::Once`], if a process is killed while running the
+/// closure passed to [`call_once`], the `Once` stays in its running state
+/// forever, and every process that calls it afterwards hangs. A
+/// `RobustOnce` records the ID of the process running the initialization,
+/// and threads waiting for it periodically check whether that process is
+/// still alive. If it isn't, the `RobustOnce` becomes poisoned, so that
+/// [`call_once`] panics, and [`call_once_force`] takes over the
+/// initialization, with [`OnceState::is_recovered`] returning `true`.
+///
+/// # Limitations
+///
+/// All the processes sharing a `RobustOnce` must be in the same PID
+/// namespace.
+///
+/// Only the death of the whole process is detected. A thread which exits
+/// without unwinding while running the initialization, such as with
+/// `pthread_exit`, leaves the `RobustOnce` running forever, as with a
+/// [`shm::Once`].
+///
+/// A process is considered dead once it has exited, even if it hasn't been
+/// reaped yet. On kernels older than Linux 5.3, which lack `pidfd_open`,
+/// processes which have exited but haven't been reaped aren't detected.
+///
+/// Processes are identified by their numeric process IDs, so on any kernel,
+/// if the process is reaped and its ID reused by another process before a
+/// waiter notices the death, waiters keep waiting for the new process, and
+/// recover only once it exits too.
+///
+/// [`Once`]: crate::Once
+/// [`shm::Once`]: crate::shm::Once
+/// [`call_once`]: Self::call_once
+/// [`call_once_force`]: Self::call_once_force
+///
+/// # Layout
+///
+/// `RobustOnce` is guaranteed to be a `repr(transparent)` wrapper around an
+/// `AtomicU32`, and an all-zeros `RobustOnce` is a new, incomplete one.
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::shm::{Region, RobustOnce};
+/// use std::ffi::CStr;
+///
+/// let name = CStr::from_bytes_with_nul(b"example\0").unwrap();
+/// let region = Region::create_anonymous(name, 4096)?;
+/// // SAFETY: Offset 0 only ever holds a `RobustOnce`.
+/// let once = unsafe { region.place::<RobustOnce>(0) };
+///
+/// once.call_once_force(|state| {
+///     if state.is_recovered() {
+///         // clean up after the process that died while initializing
+///     }
+///     // run initialization here
+/// });
+/// assert!(once.is_completed());
+/// # Ok::<(), rustix::io::Errno>(())
+/// ```
+#[repr(transparent)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct RobustOnce {
+    state: AtomicU32,
+}
+
+impl UnwindSafe for RobustOnce {}
+
+impl RefUnwindSafe for RobustOnce {}
+
+/// Sets the state when an initialization finishes, including by unwinding,
+/// and wakes any waiters.
+struct CompletionGuard<'a> {
+    state: &'a AtomicU32,
+    set_state_on_drop_to: u32,
+}
+
+impl<'a> Drop for CompletionGuard<'a> {
+    fn drop(&mut self) {
+        if self.state.swap(self.set_state_on_drop_to, Release) & QUEUED != 0 {
+            futex_wake_all::<true, SyscallBackend>(self.state);
+        }
+    }
+}
+
+impl RobustOnce {
+    /// Creates a new `RobustOnce` value.
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(INCOMPLETE),
+        }
+    }
+
+    /// Performs an initialization routine once and only once, like
+    /// [`Once::call_once`].
+    ///
+    /// This method will block the calling thread if another initialization
+    /// routine is currently running, in this process or another one.
+    ///
+    /// [`Once::call_once`]: crate::Once::call_once
+    ///
+    /// # Panics
+    ///
+    /// If a previous initialization panicked, or its process died while
+    /// running it, this `RobustOnce` is poisoned, and this panics.
+    #[inline]
+    #[track_caller]
+    pub fn call_once<F>(&self, f: F)
+    where
+        F: FnOnce(),
+    {
+        // Fast path check
+        if self.is_completed() {
+            return;
+        }
+
+        let mut f = Some(f);
+        self.call(false, &mut |_| f.take().unwrap()());
+    }
+
+    /// Performs the same function as [`call_once`] except ignores
+    /// poisoning, including poisoning from a process dying while running a
+    /// previous initialization.
+    ///
+    /// The closure `f` is yielded a [`OnceState`], whose
+    /// [`is_recovered`] method returns `true` if it's taking over from a
+    /// process which died.
+    ///
+    /// [`call_once`]: Self::call_once
+    /// [`is_recovered`]: OnceState::is_recovered
+    #[inline]
+    pub fn call_once_force<F>(&self, f: F)
+    where
+        F: FnOnce(&OnceState),
+    {
+        // Fast path check
+        if self.is_completed() {
+            return;
+        }
+
+        let mut f = Some(f);
+        self.call(true, &mut |p| f.take().unwrap()(p));
+    }
+
+    /// Returns `true` if some [`call_once`] call has completed successfully.
+    ///
+    /// [`call_once`]: Self::call_once
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        // Use acquire ordering to make all initialization changes visible to
+        // the current thread.
+        self.state.load(Acquire) == COMPLETE
+    }
+
+    /// Blocks the current thread until initialization has completed.
+    ///
+    /// # Panics
+    ///
+    /// If this `RobustOnce` has been poisoned, including by a process dying
+    /// while running the initialization, this method will also panic. Use
+    /// [`wait_force`](Self::wait_force) if this behavior is not desired.
+    #[track_caller]
+    pub fn wait(&self) {
+        if !self.is_completed() {
+            self.wait_until_inner(false, None);
+        }
+    }
+
+    /// Blocks the current thread until initialization has completed, ignoring
+    /// poisoning.
+    pub fn wait_force(&self) {
+        if !self.is_completed() {
+            self.wait_until_inner(true, None);
+        }
+    }
+
+    /// Blocks the current thread until initialization has completed, or until
+    /// `timeout` has elapsed.
+    ///
+    /// Returns `true` if initialization has completed, and `false` if the
+    /// timeout elapsed first.
+    ///
+    /// # Panics
+    ///
+    /// If this `RobustOnce` has been poisoned, this method will also panic.
+    #[track_caller]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        if self.is_completed() {
+            return true;
+        }
+
+        // Overflows are rounded up to an infinite timeout.
+        self.wait_until_inner(false, deadline(timeout).as_ref())
+    }
+
+    #[cold]
+    #[track_caller]
+    fn wait_until_inner(&self, ignore_poisoning: bool, deadline: Option<&Timespec>) -> bool {
+        let mut state = self.state.load(Acquire);
+        loop {
+            match state & STATE_MASK {
+                COMPLETE => return true,
+                POISONED if !ignore_poisoning => {
+                    // Panic to propagate the poison.
+                    panic!("Once instance has previously been poisoned");
+                }
+                _ => match self.wait_for_change(state, deadline) {
+                    Some(new) => state = new,
+                    None => return false,
+                },
+            }
+        }
+    }
+
+    #[cold]
+    #[track_caller]
+    fn call(&self, ignore_poisoning: bool, f: &mut impl FnMut(&OnceState)) {
+        let mut state = self.state.load(Acquire);
+        loop {
+            match state & STATE_MASK {
+                COMPLETE => return,
+                POISONED if !ignore_poisoning => {
+                    // Panic to propagate the poison.
+                    panic!("Once instance has previously been poisoned");
+                }
+                INCOMPLETE | POISONED => {
+                    // Try to register the current process as the one running.
+                    let owner = getpid().as_raw_nonzero().get() as u32;
+                    let next = RUNNING | (state & QUEUED) | owner << OWNER_SHIFT;
+                    if let Err(new) = self.state.compare_exchange_weak(state, next, Acquire, Acquire)
+                    {
+                        state = new;
+                        continue;
+                    }
+                    let mut waiter_queue = CompletionGuard {
+                        state: &self.state,
+                        set_state_on_drop_to: POISONED,
+                    };
+                    // Run the function, letting it know if we're poisoned, and
+                    // whether we're recovering from a dead process.
+                    let f_state = OnceState {
+                        inner: sys::OnceState::new(
+                            state & STATE_MASK == POISONED,
+                            state & DIED != 0,
+                        ),
+                    };
+                    f(&f_state);
+                    waiter_queue.set_state_on_drop_to = f_state.inner.set_state_to();
+                    return;
+                }
+                _ => {
+                    // Without a deadline, this doesn't time out.
+                    state = self.wait_for_change(state, None).unwrap();
+                }
+            }
+        }
+    }
+
+    /// Waits for the state to change from `state`, which isn't COMPLETE, and
+    /// returns the new state, or `None` if `deadline` passed first.
+    ///
+    /// If the state is RUNNING, this wakes up periodically to check whether
+    /// the process running the initialization has died, and if so, poisons
+    /// the `RobustOnce`.
+    fn wait_for_change(&self, mut state: u32, deadline: Option<&Timespec>) -> Option<u32> {
+        // Set the QUEUED bit if it is not already set.
+        if state & QUEUED == 0 {
+            match self
+                .state
+                .compare_exchange_weak(state, state | QUEUED, Relaxed, Acquire)
+            {
+                Ok(_) => state |= QUEUED,
+                Err(new) => return Some(new),
+            }
+        }
+
+        let running = state & STATE_MASK == RUNNING;
+        let check = if running {
+            crate::wait_wake::deadline(LIVENESS_INTERVAL)
+        } else {
+            None
+        };
+        let wake_at = match (check.as_ref(), deadline) {
+            (Some(check), Some(deadline)) => Some(earlier(check, deadline)),
+            (check, deadline) => check.or(deadline),
+        };
+
+        let woken = futex_wait_timespec::<true, SyscallBackend>(&self.state, state, wake_at);
+        let new = self.state.load(Acquire);
+        if woken || new != state {
+            return Some(new);
+        }
+
+        if running && exited(state >> OWNER_SHIFT) {
+            // Poison the `RobustOnce`, so that the next initialization knows
+            // it's recovering, and wake everyone to see it.
+            return match self
+                .state
+                .compare_exchange(state, POISONED | DIED, Acquire, Acquire)
+            {
+                Ok(_) => {
+                    futex_wake_all::<true, SyscallBackend>(&self.state);
+                    Some(POISONED | DIED)
+                }
+                Err(new) => Some(new),
+            };
+        }
+
+        match deadline {
+            Some(deadline) if passed(deadline) => None,
+            _ => Some(new),
+        }
+    }
+}
+
+/// Returns whichever of `a` and `b` is earlier.
+fn earlier<'a>(a: &'a Timespec, b: &'a Timespec) -> &'a Timespec {
+    if (a.tv_sec, a.tv_nsec) <= (b.tv_sec, b.tv_nsec) {
+        a
+    } else {
+        b
+    }
+}
+
+/// Returns `true` if the time `deadline` on the monotonic clock has passed.
+fn passed(deadline: &Timespec) -> bool {
+    let now = rustix::time::clock_gettime(ClockId::Monotonic);
+    (now.tv_sec, now.tv_nsec) >= (deadline.tv_sec, deadline.tv_nsec)
+}
+
+/// Returns `true` if the process `pid` has exited.
+fn exited(pid: u32) -> bool {
+    let Some(pid) = Pid::from_raw(pid as i32) else {
+        return false;
+    };
+    match pidfd_open(pid, PidfdFlags::empty()) {
+        // A pidfd becomes readable when its process exits, even if it
+        // hasn't been reaped.
+        Ok(pidfd) => {
+            let mut fds = [PollFd::new(&pidfd, PollFlags::IN)];
+            let now = Timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            matches!(poll(&mut fds, Some(&now)), Ok(n) if n != 0)
+        }
+        Err(Errno::SRCH) => true,
+        // Before Linux 5.3, fall back to `kill`, which only fails once the
+        // process has been reaped.
+        Err(Errno::NOSYS) => test_kill_process(pid) == Err(Errno::SRCH),
+        Err(_) => false,
+    }
+}
+
+impl Default for RobustOnce {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RobustOnce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RobustOnce").finish_non_exhaustive()
+    }
+}
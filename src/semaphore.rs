@@ -0,0 +1,398 @@
+//! A futex-based counting semaphore.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::forget;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering::{Acquire, Relaxed, Release},
+};
+use core::time::Duration;
+use rustix::time::Timespec;
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::wait_wake::{deadline, futex_wait_timespec, futex_wake, futex_wake_all, futex_wake_n};
+
+// The state holds the number of available permits in its low bits, and two
+// flags indicating whether there may be threads waiting for permits.
+//
+// Threads waiting for a single permit set `WAITERS`. Since any woken thread
+// can then make progress, `release(n)` only needs to wake `n` of them. Threads
+// waiting for more than one permit also set `MANY_WAITERS`, in which case a
+// woken thread may not be able to make progress while a different waiter
+// could, so `release` wakes all waiters.
+//
+// `release` clears both flags, so a thread that acquires permits after being
+// woken conservatively sets `WAITERS` again, since there may be other threads
+// still waiting that weren't woken. That isn't enough on its own: between the
+// wake and the woken thread running, further `release`s see no flags and wake
+// nobody, so their permits would sit unused while other threads sleep. So a
+// woken thread that leaves permits available after acquiring, or that gives
+// up on a timeout, passes the wake on to another waiter.
+
+/// Some threads may be waiting for permits.
+const WAITERS: u32 = 1 << 31;
+/// Some threads may be waiting for more than one permit.
+const MANY_WAITERS: u32 = 1 << 30;
+/// The bits holding the number of available permits.
+const PERMITS_MASK: u32 = MANY_WAITERS - 1;
+
+/// A counting semaphore.
+///
+/// A `Semaphore` holds a number of permits. [`acquire`] takes a permit,
+/// blocking until one is available, and returns a [`SemaphorePermit`] which
+/// gives it back when dropped. Permits can also be added with [`release`].
+///
+/// `Semaphore` is guaranteed to be a `repr(transparent)` wrapper around a
+/// single `AtomicU32`, and an all-zeros `Semaphore` is a valid semaphore with
+/// no available permits.
+///
+/// [`acquire`]: Self::acquire
+/// [`release`]: Self::release
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::Semaphore;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// // Allow at most two threads in the critical section at a time.
+/// let sem = Arc::new(Semaphore::new(2));
+/// let handles = (0..8)
+///     .map(|_| {
+///         let sem = Arc::clone(&sem);
+///         thread::spawn(move || {
+///             let _permit = sem.acquire();
+///             // ...
+///         })
+///     })
+///     .collect::<Vec<_>>();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(sem.available_permits(), 2);
+/// ```
+#[repr(transparent)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Semaphore<const SHM: bool, B = SyscallBackend> {
+    state: AtomicU32,
+    backend: PhantomData<B>,
+}
+
+impl<const SHM: bool, B: FutexBackend> Semaphore<SHM, B> {
+    /// The maximum number of permits a `Semaphore` can hold.
+    pub const MAX_PERMITS: u32 = PERMITS_MASK;
+
+    /// Creates a new semaphore with `permits` available permits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `permits` is greater than [`MAX_PERMITS`].
+    ///
+    /// [`MAX_PERMITS`]: Self::MAX_PERMITS
+    #[inline]
+    pub const fn new(permits: u32) -> Self {
+        assert!(permits <= PERMITS_MASK, "too many permits for Semaphore");
+        Self {
+            state: AtomicU32::new(permits),
+            backend: PhantomData,
+        }
+    }
+
+    /// Returns the number of permits currently available.
+    #[inline]
+    pub fn available_permits(&self) -> u32 {
+        self.state.load(Relaxed) & PERMITS_MASK
+    }
+
+    /// Acquires a permit, blocking the current thread until one is
+    /// available.
+    #[inline]
+    pub fn acquire(&self) -> SemaphorePermit<'_, SHM, B> {
+        self.acquire_many(1)
+    }
+
+    /// Acquires `n` permits at once, blocking the current thread until they
+    /// are all available.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than [`MAX_PERMITS`], since that many
+    /// permits can never become available.
+    ///
+    /// [`MAX_PERMITS`]: Self::MAX_PERMITS
+    #[inline]
+    pub fn acquire_many(&self, n: u32) -> SemaphorePermit<'_, SHM, B> {
+        match self.acquire_many_optional_deadline(n, None) {
+            Some(permit) => permit,
+            None => unreachable!(),
+        }
+    }
+
+    /// Attempts to acquire a permit without blocking.
+    #[inline]
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_, SHM, B>> {
+        self.try_acquire_many(1)
+    }
+
+    /// Attempts to acquire `n` permits at once without blocking.
+    ///
+    /// Either all `n` permits are acquired, or none are.
+    #[inline]
+    pub fn try_acquire_many(&self, n: u32) -> Option<SemaphorePermit<'_, SHM, B>> {
+        if self.try_take(n) {
+            Some(SemaphorePermit { sem: self, permits: n })
+        } else {
+            None
+        }
+    }
+
+    /// Like [`acquire`], but gives up and returns `None` if a permit doesn't
+    /// become available within `timeout`.
+    ///
+    /// [`acquire`]: Self::acquire
+    #[inline]
+    pub fn acquire_timeout(&self, timeout: Duration) -> Option<SemaphorePermit<'_, SHM, B>> {
+        self.acquire_many_timeout(1, timeout)
+    }
+
+    /// Like [`acquire_many`], but gives up and returns `None` if the permits
+    /// don't become available within `timeout`.
+    ///
+    /// [`acquire_many`]: Self::acquire_many
+    pub fn acquire_many_timeout(
+        &self,
+        n: u32,
+        timeout: Duration,
+    ) -> Option<SemaphorePermit<'_, SHM, B>> {
+        if let Some(permit) = self.try_acquire_many(n) {
+            return Some(permit);
+        }
+        // Overflows are rounded up to an infinite timeout.
+        self.acquire_many_optional_deadline(n, deadline(timeout).as_ref())
+    }
+
+    /// Like [`acquire_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`acquire_timeout`]: Self::acquire_timeout
+    #[inline]
+    pub fn acquire_until(&self, deadline: &Timespec) -> Option<SemaphorePermit<'_, SHM, B>> {
+        self.acquire_many_until(1, deadline)
+    }
+
+    /// Like [`acquire_many_timeout`], but takes an absolute time on the
+    /// `CLOCK_MONOTONIC` clock to give up at.
+    ///
+    /// [`acquire_many_timeout`]: Self::acquire_many_timeout
+    #[inline]
+    pub fn acquire_many_until(
+        &self,
+        n: u32,
+        deadline: &Timespec,
+    ) -> Option<SemaphorePermit<'_, SHM, B>> {
+        self.acquire_many_optional_deadline(n, Some(deadline))
+    }
+
+    /// Adds `n` permits to the semaphore, waking up waiting threads which
+    /// can use them.
+    ///
+    /// This can be used to give back permits whose [`SemaphorePermit`] was
+    /// [forgotten], or to add permits that were never acquired.
+    ///
+    /// [forgotten]: SemaphorePermit::forget
+    ///
+    /// # Panics
+    ///
+    /// Panics if this would make the number of available permits greater
+    /// than [`MAX_PERMITS`].
+    ///
+    /// [`MAX_PERMITS`]: Self::MAX_PERMITS
+    pub fn release(&self, n: u32) {
+        if n == 0 {
+            return;
+        }
+
+        let mut state = self.state.load(Relaxed);
+        loop {
+            assert!(
+                n <= PERMITS_MASK - (state & PERMITS_MASK),
+                "too many permits for Semaphore"
+            );
+            // Clear the waiter flags; we'll wake the waiters below.
+            let new = (state & PERMITS_MASK) + n;
+            match self.state.compare_exchange_weak(state, new, Release, Relaxed) {
+                Ok(_) => break,
+                Err(s) => state = s,
+            }
+        }
+
+        if state & MANY_WAITERS != 0 {
+            futex_wake_all::<SHM, B>(&self.state);
+        } else if state & WAITERS != 0 {
+            futex_wake_n::<SHM, B>(&self.state, n);
+        }
+    }
+
+    /// Takes `n` permits if they're available, without blocking.
+    #[inline]
+    fn try_take(&self, n: u32) -> bool {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            if state & PERMITS_MASK < n {
+                return false;
+            }
+            match self.state.compare_exchange_weak(state, state - n, Acquire, Relaxed) {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    /// Wakes another waiter if permits are available, for a thread which
+    /// may have consumed a wake from `release` that it didn't fully use.
+    #[cold]
+    fn pass_on_wake(&self) {
+        let state = self.state.load(Relaxed);
+        if state & PERMITS_MASK == 0 {
+            return;
+        }
+        if state & MANY_WAITERS != 0 {
+            futex_wake_all::<SHM, B>(&self.state);
+        } else {
+            futex_wake::<SHM, B>(&self.state);
+        }
+    }
+
+    fn acquire_many_optional_deadline(
+        &self,
+        n: u32,
+        deadline: Option<&Timespec>,
+    ) -> Option<SemaphorePermit<'_, SHM, B>> {
+        assert!(n <= PERMITS_MASK, "too many permits requested from Semaphore");
+
+        if self.try_take(n) {
+            return Some(SemaphorePermit { sem: self, permits: n });
+        }
+
+        self.acquire_contended(n, deadline)
+    }
+
+    #[cold]
+    fn acquire_contended(
+        &self,
+        n: u32,
+        deadline: Option<&Timespec>,
+    ) -> Option<SemaphorePermit<'_, SHM, B>> {
+        let wait_flags = if n > 1 { WAITERS | MANY_WAITERS } else { WAITERS };
+        let mut woken = false;
+        let mut state = self.state.load(Relaxed);
+        loop {
+            if state & PERMITS_MASK >= n {
+                // If we were woken, other threads may still be waiting, so
+                // keep the `WAITERS` flag set.
+                let new = (state - n) | if woken { WAITERS } else { 0 };
+                match self.state.compare_exchange_weak(state, new, Acquire, Relaxed) {
+                    Ok(_) => {
+                        if woken {
+                            self.pass_on_wake();
+                        }
+                        return Some(SemaphorePermit { sem: self, permits: n });
+                    }
+                    Err(s) => {
+                        state = s;
+                        continue;
+                    }
+                }
+            }
+
+            // Set our waiter flags, if they aren't set already.
+            let new = state | wait_flags;
+            if new != state {
+                if let Err(s) = self.state.compare_exchange_weak(state, new, Relaxed, Relaxed) {
+                    state = s;
+                    continue;
+                }
+            }
+
+            if !futex_wait_timespec::<SHM, B>(&self.state, new, deadline) {
+                // We timed out. Make one last attempt before giving up. A
+                // backend may have consumed a wake for us as we timed out,
+                // so pass it on either way.
+                let permit = self.try_acquire_many(n);
+                self.pass_on_wake();
+                return permit;
+            }
+            woken = true;
+            state = self.state.load(Relaxed);
+        }
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> Default for Semaphore<SHM, B> {
+    /// Creates a new semaphore with no available permits.
+    #[inline]
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<const SHM: bool, B: FutexBackend> fmt::Debug for Semaphore<SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("available_permits", &self.available_permits())
+            .finish()
+    }
+}
+
+/// An RAII guard holding permits acquired from a [`Semaphore`].
+///
+/// When this structure is dropped, its permits are released back to the
+/// semaphore.
+///
+/// [`Semaphore`]: crate::generic::Semaphore
+#[must_use = "if unused the permits will immediately be released"]
+pub struct SemaphorePermit<'a, const SHM: bool, B: FutexBackend = SyscallBackend> {
+    sem: &'a Semaphore<SHM, B>,
+    permits: u32,
+}
+
+impl<'a, const SHM: bool, B: FutexBackend> SemaphorePermit<'a, SHM, B> {
+    /// Returns the number of permits held by this guard.
+    #[inline]
+    pub fn num_permits(&self) -> u32 {
+        self.permits
+    }
+
+    /// Returns a reference to the `Semaphore` the permits were acquired
+    /// from.
+    #[inline]
+    pub fn semaphore(s: &Self) -> &'a Semaphore<SHM, B> {
+        s.sem
+    }
+
+    /// Consumes this guard without releasing its permits.
+    ///
+    /// The permits can later be given back with [`Semaphore::release`].
+    ///
+    /// [`Semaphore::release`]: crate::generic::Semaphore::release
+    #[inline]
+    pub fn forget(self) {
+        forget(self)
+    }
+}
+
+impl<'a, const SHM: bool, B: FutexBackend> Drop for SemaphorePermit<'a, SHM, B> {
+    #[inline]
+    fn drop(&mut self) {
+        self.sem.release(self.permits)
+    }
+}
+
+impl<'a, const SHM: bool, B: FutexBackend> fmt::Debug for SemaphorePermit<'a, SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SemaphorePermit")
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
@@ -0,0 +1,405 @@
+//! A sequence lock.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint::spin_loop;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{
+    fence, AtomicU16, AtomicU32, AtomicU8, AtomicUsize,
+    Ordering::{Acquire, Relaxed, Release},
+};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::generic::RawMutex;
+use crate::lock_api::RawMutex as _;
+use crate::wait_wake::{futex_wait_timespec, futex_wake_all};
+
+// The sequence number doubles as a futex word. Its low bit is set while a
+// write is in progress, and its second bit is set while a write is in
+// progress and there are readers blocked in `read_blocking` waiting for it
+// to finish. The rest of the bits count completed writes.
+
+/// A write is in progress.
+const WRITING: u32 = 1;
+/// Some readers may be waiting for the write in progress to finish.
+const WAITERS: u32 = 2;
+/// The amount the sequence number advances by for each write.
+const ONE_WRITE: u32 = 4;
+
+/// A sequence lock, for data which is read frequently and written rarely.
+///
+/// Readers don't write to shared memory, so they don't contend with each
+/// other at all. Instead, a reader reads the data optimistically and then
+/// checks whether a write happened in the meantime, retrying if so. Writers
+/// are serialized with a [`RawMutex`].
+///
+/// Since readers may see a partially written value before retrying, `T`
+/// must be `Copy`, and values are returned by copy rather than by
+/// reference. Readers and writers copy the value with relaxed atomic loads
+/// and stores of its bytes, so `T` shouldn't have padding bytes, which
+/// would be copied as uninitialized integers.
+///
+/// `SeqLock` is a `repr(C)` struct, and an all-zeros `SeqLock` is valid
+/// whenever an all-zeros `T` is a valid `T`.
+///
+/// [`RawMutex`]: crate::generic::RawMutex
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::SeqLock;
+///
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Config {
+///     width: u32,
+///     height: u32,
+/// }
+///
+/// let config = SeqLock::new(Config { width: 640, height: 480 });
+/// config.write(Config { width: 800, height: 600 });
+/// config.lock().height = 500;
+/// assert_eq!(config.read(), Config { width: 800, height: 500 });
+/// ```
+#[repr(C)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct SeqLock<T: Copy, const SHM: bool, B = SyscallBackend> {
+    seq: AtomicU32,
+    raw: RawMutex<SHM, B>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Copy + Send, const SHM: bool, B: FutexBackend> Send for SeqLock<T, SHM, B> {}
+unsafe impl<T: Copy + Send, const SHM: bool, B: FutexBackend> Sync for SeqLock<T, SHM, B> {}
+
+impl<T: Copy, const SHM: bool, B: FutexBackend> SeqLock<T, SHM, B> {
+    /// Creates a new `SeqLock` holding `val`.
+    #[inline]
+    pub const fn new(val: T) -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+            raw: RawMutex::INIT,
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    /// Consumes this `SeqLock`, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `SeqLock` mutably, no actual locking
+    /// needs to take place.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Reads the value, retrying if a write happens concurrently.
+    ///
+    /// If a write is in progress, this spins until it finishes. This never
+    /// writes to shared memory; see [`read_blocking`] for a version which
+    /// sleeps instead.
+    ///
+    /// [`read_blocking`]: Self::read_blocking
+    #[inline]
+    pub fn read(&self) -> T {
+        loop {
+            if let Some(val) = self.try_read() {
+                return val;
+            }
+            spin_loop();
+        }
+    }
+
+    /// Reads the value, retrying if a write happens concurrently, and
+    /// blocking if a write is in progress.
+    ///
+    /// This is useful when writers may hold the lock for a long time, such
+    /// as when they're modifying the value in place with [`lock`].
+    ///
+    /// [`lock`]: Self::lock
+    pub fn read_blocking(&self) -> T {
+        loop {
+            if let Some(val) = self.try_read() {
+                return val;
+            }
+            self.wait_for_write();
+        }
+    }
+
+    /// Makes a single attempt to read the value, returning `None` if a write
+    /// is in progress or happens concurrently.
+    #[inline]
+    pub fn try_read(&self) -> Option<T> {
+        let seq1 = self.seq.load(Acquire);
+        if seq1 & WRITING != 0 {
+            return None;
+        }
+
+        // SAFETY: This read may race with a writer, in which case the value
+        // may be torn. Writers only store to the data atomically, and we read
+        // it into a `MaybeUninit` so that a torn value is never interpreted
+        // as a `T`, and then discard it below.
+        let val = unsafe { atomic_load(self.data.get()) };
+
+        // Order the data read before the second sequence read.
+        fence(Acquire);
+
+        if self.seq.load(Relaxed) != seq1 {
+            return None;
+        }
+
+        // SAFETY: The sequence number didn't change, so no writer ran
+        // concurrently with our read, and the value is a valid `T`.
+        Some(unsafe { val.assume_init() })
+    }
+
+    /// Replaces the value with `val`.
+    ///
+    /// This blocks while another writer holds the lock.
+    #[inline]
+    pub fn write(&self, val: T) {
+        let seq = self.start_write();
+        drop(SeqLockGuard {
+            lock: self,
+            seq,
+            val,
+            marker: PhantomData,
+        });
+    }
+
+    /// Acquires the write lock, blocking the current thread until it is able
+    /// to do so, and returns a guard for modifying the value in place.
+    ///
+    /// The guard modifies a copy of the value, which is stored back when the
+    /// guard is dropped. Readers retry or wait until then.
+    #[inline]
+    pub fn lock(&self) -> SeqLockGuard<'_, T, SHM, B> {
+        let seq = self.start_write();
+
+        // SAFETY: We hold the lock, so no other thread writes the data.
+        let val = unsafe { *self.data.get() };
+
+        SeqLockGuard {
+            lock: self,
+            seq,
+            val,
+            marker: PhantomData,
+        }
+    }
+
+    /// Acquire the lock and set the `WRITING` bit, returning the sequence
+    /// number from before.
+    #[inline]
+    fn start_write(&self) -> u32 {
+        self.raw.lock();
+
+        // We hold the lock, so no other writer is running and both the
+        // `WRITING` and `WAITERS` bits are clear.
+        let seq = self.seq.load(Relaxed);
+        self.seq.store(seq | WRITING, Relaxed);
+
+        // Order the sequence write before the data writes.
+        fence(Release);
+
+        seq
+    }
+
+    /// Wait for the write in progress, if any, to finish.
+    #[cold]
+    fn wait_for_write(&self) {
+        let mut seq = self.seq.load(Relaxed);
+        loop {
+            if seq & WRITING == 0 {
+                return;
+            }
+
+            // Set the `WAITERS` bit, if it isn't set already.
+            if seq & WAITERS == 0 {
+                if let Err(s) = self.seq.compare_exchange_weak(seq, seq | WAITERS, Relaxed, Relaxed) {
+                    seq = s;
+                    continue;
+                }
+            }
+
+            futex_wait_timespec::<SHM, B>(&self.seq, seq | WAITERS, None);
+            seq = self.seq.load(Relaxed);
+        }
+    }
+}
+
+impl<T: Copy + Default, const SHM: bool, B: FutexBackend> Default for SeqLock<T, SHM, B> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: Copy, const SHM: bool, B: FutexBackend> From<T> for SeqLock<T, SHM, B> {
+    #[inline]
+    fn from(t: T) -> Self {
+        Self::new(t)
+    }
+}
+
+impl<T: Copy + fmt::Debug, const SHM: bool, B: FutexBackend> fmt::Debug for SeqLock<T, SHM, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("SeqLock");
+        match self.try_read() {
+            Some(val) => d.field("data", &val),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+/// An RAII guard for modifying the value of a [`SeqLock`] in place.
+///
+/// When this structure is dropped, the modified value is stored, the write is
+/// published to readers, and the lock is released.
+///
+/// [`SeqLock`]: crate::generic::SeqLock
+#[must_use = "if unused the SeqLock will immediately unlock"]
+pub struct SeqLockGuard<'a, T: Copy, const SHM: bool, B: FutexBackend = SyscallBackend> {
+    lock: &'a SeqLock<T, SHM, B>,
+    seq: u32,
+    /// The value being modified, which is stored back on drop.
+    val: T,
+    // Like `lock_api::GuardNoSend`, the lock must be released by the thread
+    // that acquired it.
+    marker: PhantomData<(&'a mut T, *mut ())>,
+}
+
+unsafe impl<'a, T: Copy + Sync, const SHM: bool, B: FutexBackend> Sync
+    for SeqLockGuard<'a, T, SHM, B>
+{
+}
+
+impl<'a, T: Copy, const SHM: bool, B: FutexBackend> Deref for SeqLockGuard<'a, T, SHM, B> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.val
+    }
+}
+
+impl<'a, T: Copy, const SHM: bool, B: FutexBackend> DerefMut for SeqLockGuard<'a, T, SHM, B> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.val
+    }
+}
+
+impl<'a, T: Copy, const SHM: bool, B: FutexBackend> Drop for SeqLockGuard<'a, T, SHM, B> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: We hold the lock, so there are no other writers, and
+        // readers only load the data atomically, and discard anything they
+        // read while `WRITING` is set.
+        unsafe { atomic_store(self.lock.data.get(), &self.val) }
+
+        // Publish the write, clearing the `WRITING` and `WAITERS` bits.
+        let prev = self
+            .lock
+            .seq
+            .swap(self.seq.wrapping_add(ONE_WRITE), Release);
+        if prev & WAITERS != 0 {
+            futex_wake_all::<SHM, B>(&self.lock.seq);
+        }
+
+        // SAFETY: We hold the lock.
+        unsafe { self.lock.raw.unlock() }
+    }
+}
+
+impl<'a, T: Copy + fmt::Debug, const SHM: bool, B: FutexBackend> fmt::Debug
+    for SeqLockGuard<'a, T, SHM, B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Copy a `T` out of `src` with relaxed atomic loads.
+///
+/// # Safety
+///
+/// `src` must be valid for reads and aligned, and may only be written
+/// concurrently by [`atomic_store`].
+#[inline]
+unsafe fn atomic_load<T>(src: *const T) -> MaybeUninit<T> {
+    unsafe fn load<T, C: Chunk>(src: *const T, dst: *mut T) {
+        for i in 0..size_of::<T>() / size_of::<C>() {
+            dst.cast::<C>().add(i).write(C::load(&*src.cast::<C::Atomic>().add(i)));
+        }
+    }
+
+    let mut val = MaybeUninit::<T>::uninit();
+    let dst = val.as_mut_ptr();
+    match align_of::<T>() {
+        a if a >= size_of::<usize>() => load::<T, usize>(src, dst),
+        a if a >= size_of::<u32>() => load::<T, u32>(src, dst),
+        a if a >= size_of::<u16>() => load::<T, u16>(src, dst),
+        _ => load::<T, u8>(src, dst),
+    }
+    val
+}
+
+/// Copy `val` into `dst` with relaxed atomic stores.
+///
+/// # Safety
+///
+/// `dst` must be valid for writes and aligned, and may only be accessed
+/// concurrently by [`atomic_load`].
+#[inline]
+unsafe fn atomic_store<T>(dst: *mut T, val: &T) {
+    unsafe fn store<T, C: Chunk>(src: *const T, dst: *mut T) {
+        for i in 0..size_of::<T>() / size_of::<C>() {
+            C::store(&*dst.cast::<C::Atomic>().add(i), src.cast::<C>().add(i).read());
+        }
+    }
+
+    let src: *const T = val;
+    match align_of::<T>() {
+        a if a >= size_of::<usize>() => store::<T, usize>(src, dst),
+        a if a >= size_of::<u32>() => store::<T, u32>(src, dst),
+        a if a >= size_of::<u16>() => store::<T, u16>(src, dst),
+        _ => store::<T, u8>(src, dst),
+    }
+}
+
+/// An integer type which values are copied in chunks of, with an atomic
+/// counterpart of the same size and alignment.
+trait Chunk: Copy {
+    type Atomic;
+    fn load(atomic: &Self::Atomic) -> Self;
+    fn store(atomic: &Self::Atomic, val: Self);
+}
+
+macro_rules! chunk {
+    ($($int:ty => $atomic:ty,)*) => {$(
+        impl Chunk for $int {
+            type Atomic = $atomic;
+            #[inline]
+            fn load(atomic: &$atomic) -> Self {
+                atomic.load(Relaxed)
+            }
+            #[inline]
+            fn store(atomic: &$atomic, val: Self) {
+                atomic.store(val, Relaxed)
+            }
+        }
+    )*};
+}
+
+chunk! {
+    u8 => AtomicU8,
+    u16 => AtomicU16,
+    u32 => AtomicU32,
+    usize => AtomicUsize,
+}
@@ -0,0 +1,55 @@
+//! Thread IDs for reentrant mutexes.
+
+use core::num::NonZeroUsize;
+use rustix::thread::gettid;
+use crate::lock_api;
+
+/// An implementation of [`lock_api::GetThreadId`], for use with
+/// [`ReentrantMutex`].
+///
+/// The thread ID is the thread's Linux thread ID, from [`gettid`], which is
+/// unique among the threads running in a PID namespace.
+///
+/// For the shm case, thread IDs from different PID namespaces may collide,
+/// so all the processes sharing a mutex which uses this must be in the same
+/// PID namespace, as with [`RobustOnce`].
+///
+/// Each call makes a system call, so this is somewhat slower than
+/// implementations using thread-local storage, which this crate can't use
+/// since it's `no_std`.
+///
+/// [`ReentrantMutex`]: crate::generic::ReentrantMutex
+/// [`lock_api::GetThreadId`]: https://docs.rs/lock_api/*/lock_api/trait.GetThreadId.html
+/// [`gettid`]: https://man7.org/linux/man-pages/man2/gettid.2.html
+/// [`RobustOnce`]: crate::shm::RobustOnce
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::lock_api::GetThreadId as _;
+/// use rustix_futex_sync::RawThreadId;
+///
+/// let id = RawThreadId::INIT.nonzero_thread_id();
+/// assert_eq!(id, RawThreadId::INIT.nonzero_thread_id());
+/// ```
+pub struct RawThreadId<const SHM: bool>(());
+
+// SAFETY: Linux thread IDs are unique among active threads in a PID
+// namespace, and are never 0. Processes sharing an shm mutex are required to
+// be in the same PID namespace.
+unsafe impl<const SHM: bool> lock_api::GetThreadId for RawThreadId<SHM> {
+    const INIT: Self = Self(());
+
+    #[inline]
+    fn nonzero_thread_id(&self) -> NonZeroUsize {
+        nonzero(gettid().as_raw_nonzero().get() as usize)
+    }
+}
+
+#[inline]
+fn nonzero(id: usize) -> NonZeroUsize {
+    match NonZeroUsize::new(id) {
+        Some(id) => id,
+        None => unreachable!("thread IDs are never 0"),
+    }
+}
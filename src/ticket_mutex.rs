@@ -0,0 +1,135 @@
+//! A ticket lock.
+
+use core::hint::spin_loop;
+use core::marker::PhantomData;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering::{Acquire, Relaxed, SeqCst},
+};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
+use crate::lock_api;
+use crate::wait_wake::{futex_wait_timespec, futex_wake_all};
+
+/// A mutual exclusion primitive which grants the lock in strict FIFO order,
+/// implementing [`lock_api::RawMutex`] and [`lock_api::RawMutexFair`].
+///
+/// Each thread which locks the mutex takes a ticket, and waits until the
+/// ticket is being served. Unlike [`RawMutex`], a thread can't barge in ahead
+/// of threads which are already waiting, at the cost of lower throughput
+/// under contention, since the lock can't be taken by whichever thread is
+/// running when it's released.
+///
+/// The thread next in line spins briefly before waiting. All waiters wait on
+/// the now-serving counter, and are all woken when it advances.
+///
+/// `TicketMutex` is a `repr(C)` struct containing the next ticket to hand out
+/// followed by the ticket now being served, as two `AtomicU32`s, and an
+/// all-zeros `TicketMutex` is unlocked.
+///
+/// All of this `TicketMutex`'s methods are in its implementations of
+/// [`lock_api::RawMutex`] and [`lock_api::RawMutexFair`]. Since every unlock
+/// is fair, `unlock_fair` is the same as `unlock`.
+///
+/// [`RawMutex`]: crate::generic::RawMutex
+/// [`lock_api::RawMutex`]: https://docs.rs/lock_api/*/lock_api/trait.RawMutex.html
+/// [`lock_api::RawMutexFair`]: https://docs.rs/lock_api/*/lock_api/trait.RawMutexFair.html
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::lock_api::RawMutex as _;
+/// use rustix_futex_sync::TicketMutex;
+///
+/// let lock = TicketMutex::INIT;
+/// lock.lock();
+/// assert!(!lock.try_lock());
+/// unsafe { lock.unlock() };
+/// assert!(lock.try_lock());
+/// # unsafe { lock.unlock() };
+/// ```
+#[repr(C)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct TicketMutex<const SHM: bool, B = SyscallBackend> {
+    next: AtomicU32,
+    serving: AtomicU32,
+    backend: PhantomData<B>,
+}
+
+impl<const SHM: bool, B: FutexBackend> TicketMutex<SHM, B> {
+    #[cold]
+    fn lock_contended(&self, ticket: u32, mut serving: u32) {
+        loop {
+            // Spin first if we're next in line, to speed things up if the
+            // lock is released quickly. Threads further back will have to
+            // wait anyway.
+            if ticket.wrapping_sub(serving) == 1 {
+                let mut spin = 100;
+                while serving != ticket && spin != 0 {
+                    spin_loop();
+                    serving = self.serving.load(Acquire);
+                    spin -= 1;
+                }
+            }
+            if serving == ticket {
+                return;
+            }
+
+            // Wait for the counter to advance, assuming it hasn't already.
+            futex_wait_timespec::<SHM, B>(&self.serving, serving, None);
+            serving = self.serving.load(Acquire);
+        }
+    }
+}
+
+unsafe impl<const SHM: bool, B: FutexBackend> lock_api::RawMutex for TicketMutex<SHM, B> {
+    type GuardMarker = lock_api::GuardNoSend;
+
+    const INIT: Self = Self {
+        next: AtomicU32::new(0),
+        serving: AtomicU32::new(0),
+        backend: PhantomData,
+    };
+
+    #[inline]
+    fn lock(&self) {
+        // This is `SeqCst`, paired with `unlock`, so that either `unlock`
+        // sees our ticket and wakes us, or we see the counter it advanced.
+        let ticket = self.next.fetch_add(1, SeqCst);
+        let serving = self.serving.load(SeqCst);
+        if serving != ticket {
+            self.lock_contended(ticket, serving);
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> bool {
+        // Take a ticket only if it would be served immediately.
+        let serving = self.serving.load(Acquire);
+        self.next
+            .compare_exchange(serving, serving.wrapping_add(1), Acquire, Relaxed)
+            .is_ok()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        let serving = self.serving.fetch_add(1, SeqCst).wrapping_add(1);
+
+        // If anyone has taken a later ticket, wake the waiters so that the
+        // next in line can notice that it's being served.
+        if self.next.load(SeqCst) != serving {
+            futex_wake_all::<SHM, B>(&self.serving);
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.next.load(Relaxed) != self.serving.load(Relaxed)
+    }
+}
+
+unsafe impl<const SHM: bool, B: FutexBackend> lock_api::RawMutexFair for TicketMutex<SHM, B> {
+    #[inline]
+    unsafe fn unlock_fair(&self) {
+        lock_api::RawMutex::unlock(self)
+    }
+}
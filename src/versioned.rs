@@ -0,0 +1,315 @@
+//! A header for detecting incompatible layouts of primitives in shared
+//! memory.
+
+use core::alloc::Layout;
+use core::fmt;
+use core::mem::{align_of, size_of};
+use core::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use crate::generic::{
+    Condvar, Event, Latch, Once, OnceLock, Parker, RawCondvar, RawMutex, RawRwLock, ReprCMutex,
+    ReprCRwLock, Semaphore, SeqLock, TicketMutex,
+};
+use crate::region::Placeable;
+use crate::robust_once::RobustOnce;
+
+/// The version of the state encodings of this crate's primitives.
+///
+/// This is incremented whenever a change to this crate changes how any
+/// primitive encodes its state, so that processes built against versions of
+/// this crate with different encodings don't share a primitive.
+pub const LAYOUT_VERSION: u16 = 1;
+
+/// The magic number at the start of a [`Versioned`] header: "rfsv".
+const MAGIC: u32 = u32::from_be_bytes(*b"rfsv");
+
+/// A primitive, preceded by a header describing its layout, for detecting
+/// when processes sharing it disagree about its layout.
+///
+/// Processes built against different versions of this crate may encode the
+/// state of a primitive differently, and if they share it, they silently
+/// corrupt each other's state. Similarly, processes which disagree about
+/// which primitive is at a given place in shared memory corrupt each other.
+/// Wrapping a primitive in a `Versioned` lets each process check, with
+/// [`Versioned::attach`], that the header written by the first process to
+/// attach matches its own build before using the primitive.
+///
+/// Along with the kind of primitive, the header records the size and
+/// alignment of the whole primitive, including any value it holds, so, for
+/// example, a `Versioned<OnceLock<u32>>` and a `Versioned<OnceLock<u64>>`
+/// don't attach to each other. Values of different types with the same size
+/// and alignment, such as `u32` and `f32`, can't be told apart.
+///
+/// # Layout
+///
+/// `Versioned` is guaranteed to be a `repr(C)` struct containing two `u64`
+/// header words at offsets 0 and 8, followed by the primitive at offset
+/// [`VALUE_OFFSET`]. Each header word is zero until a process attaches.
+///
+/// The first word holds, from the most significant bits to the least, a
+/// 32-bit magic number, the 16-bit [`LAYOUT_VERSION`], an 8-bit code for the
+/// kind of primitive, and an 8-bit flag which is 1 for the primitives in the
+/// `shm` module. The second word holds the size of the primitive in its high
+/// 32 bits, and its alignment in its low 32 bits. This layout is the same in
+/// all versions of this crate.
+///
+/// An all-zeros `Versioned` is valid whenever an all-zeros `T` is valid,
+/// which is the case for all the primitives which can be wrapped.
+///
+/// [`VALUE_OFFSET`]: Self::VALUE_OFFSET
+///
+/// # Examples
+///
+/// ```
+/// use rustix_futex_sync::shm::{Region, ReprCRwLock, Versioned};
+/// use std::ffi::CStr;
+///
+/// let name = CStr::from_bytes_with_nul(b"example\0").unwrap();
+/// let region = Region::create_anonymous(name, 4096)?;
+///
+/// // SAFETY: Offset 0 only ever holds a `Versioned<ReprCRwLock<u64>>`.
+/// let versioned = unsafe { region.place::<Versioned<ReprCRwLock<u64>>>(0) };
+/// let lock = versioned.attach().unwrap();
+/// *lock.write() = 42;
+/// assert_eq!(*lock.read(), 42);
+/// # Ok::<(), rustix::io::Errno>(())
+/// ```
+#[repr(C)]
+#[cfg_attr(feature = "zerocopy", derive(zerocopy::FromZeros))]
+pub struct Versioned<T: Versionable> {
+    header: AtomicU64,
+    layout: AtomicU64,
+    value: T,
+}
+
+impl<T: Versionable> Versioned<T> {
+    /// The offset, in bytes, of the primitive within a `Versioned`.
+    ///
+    /// This is the size of the header, rounded up to the alignment of `T`.
+    pub const VALUE_OFFSET: usize =
+        (2 * size_of::<AtomicU64>() + align_of::<T>() - 1) & !(align_of::<T>() - 1);
+
+    /// The header that this build of this crate writes for a `T`.
+    const HEADER: u64 = (MAGIC as u64) << 32
+        | (LAYOUT_VERSION as u64) << 16
+        | (T::KIND as u64) << 8
+        | T::SHM as u64;
+
+    /// The second header word that this build of this crate writes for a
+    /// `T`. This is never zero, since alignments are at least 1.
+    const LAYOUT: u64 = (size_of::<T>() as u64) << 32 | align_of::<T>() as u64;
+
+    /// Wraps `value`, with a header which is already attached.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            header: AtomicU64::new(Self::HEADER),
+            layout: AtomicU64::new(Self::LAYOUT),
+            value,
+        }
+    }
+
+    /// Checks that the header matches this build's layout of `T`, and if so,
+    /// returns the primitive.
+    ///
+    /// If no process has attached yet, this writes the header for this
+    /// build's layout, so that later attaches, from any process, are checked
+    /// against it.
+    pub fn attach(&self) -> Result<&T, LayoutError> {
+        // The header isn't used to synchronize access to anything else, so
+        // these can be `Relaxed`. Each word is checked on its own, so if two
+        // processes which disagree race to attach, at least one of them
+        // fails on one of the words.
+        match self.header.compare_exchange(0, Self::HEADER, Relaxed, Relaxed) {
+            Ok(_) => {}
+            Err(header) if header == Self::HEADER => {}
+            Err(header) => return Err(LayoutError::new::<T>(header)),
+        }
+        match self.layout.compare_exchange(0, Self::LAYOUT, Relaxed, Relaxed) {
+            Ok(_) => Ok(&self.value),
+            Err(layout) if layout == Self::LAYOUT => Ok(&self.value),
+            Err(layout) => Err(LayoutError::size::<T>(layout)),
+        }
+    }
+
+    /// Returns a mutable reference to the primitive, without checking the
+    /// header.
+    ///
+    /// Since this call borrows the `Versioned` mutably, no other process can
+    /// be using it.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Versionable + Default> Default for Versioned<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Versionable> fmt::Debug for Versioned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Versioned")
+            .field("header", &format_args!("{:#018x}", self.header.load(Relaxed)))
+            .field("layout", &format_args!("{:#018x}", self.layout.load(Relaxed)))
+            .finish_non_exhaustive()
+    }
+}
+
+// SAFETY: The header words are atomics, and are valid when zero.
+unsafe impl<T: Versionable + Placeable> Placeable for Versioned<T> {}
+
+/// The error returned by [`Versioned::attach`] when the header doesn't match
+/// this build's layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LayoutError {
+    /// The header doesn't start with the magic number, so the memory doesn't
+    /// hold a `Versioned`, or has been overwritten.
+    Magic,
+    /// The header was written by a version of this crate with a different
+    /// [`LAYOUT_VERSION`].
+    Version {
+        /// The `LAYOUT_VERSION` of this build.
+        ours: u16,
+        /// The `LAYOUT_VERSION` in the header.
+        theirs: u16,
+    },
+    /// The header was written for a different kind of primitive.
+    Kind {
+        /// The code for the kind of primitive in this build.
+        ours: u8,
+        /// The code for the kind of primitive in the header.
+        theirs: u8,
+    },
+    /// The header was written for the same kind of primitive, but one of
+    /// them is from the `shm` module and the other isn't, so they don't
+    /// wake each other.
+    Shm {
+        /// Whether this build's primitive is from the `shm` module.
+        ours: bool,
+        /// Whether the primitive in the header is from the `shm` module.
+        theirs: bool,
+    },
+    /// The header was written for the same kind of primitive, but with a
+    /// different size or alignment, such as when it holds a value of a
+    /// different type.
+    Size {
+        /// The size and alignment of the primitive in this build.
+        ours: Layout,
+        /// The size and alignment of the primitive in the header.
+        theirs: Layout,
+    },
+}
+
+impl LayoutError {
+    fn new<T: Versionable>(header: u64) -> Self {
+        if (header >> 32) as u32 != MAGIC {
+            Self::Magic
+        } else if (header >> 16) as u16 != LAYOUT_VERSION {
+            Self::Version {
+                ours: LAYOUT_VERSION,
+                theirs: (header >> 16) as u16,
+            }
+        } else if (header >> 8) as u8 != T::KIND {
+            Self::Kind {
+                ours: T::KIND,
+                theirs: (header >> 8) as u8,
+            }
+        } else {
+            Self::Shm {
+                ours: T::SHM,
+                theirs: header as u8 != 0,
+            }
+        }
+    }
+
+    fn size<T: Versionable>(layout: u64) -> Self {
+        match Layout::from_size_align((layout >> 32) as usize, layout as u32 as usize) {
+            Ok(theirs) => Self::Size {
+                ours: Layout::new::<T>(),
+                theirs,
+            },
+            // The memory has been overwritten with something else.
+            Err(_) => Self::Magic,
+        }
+    }
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Magic => "no layout header found".fmt(f),
+            Self::Version { ours, theirs } => {
+                write!(f, "layout version {} does not match {}", theirs, ours)
+            }
+            Self::Kind { ours, theirs } => {
+                write!(f, "primitive kind {} does not match {}", theirs, ours)
+            }
+            Self::Shm { ours, theirs } => {
+                write!(f, "shm flag {} does not match {}", theirs, ours)
+            }
+            Self::Size { ours, theirs } => write!(
+                f,
+                "size {} and alignment {} do not match {} and {}",
+                theirs.size(),
+                theirs.align(),
+                ours.size(),
+                ours.align()
+            ),
+        }
+    }
+}
+
+/// Primitives which can be wrapped in a [`Versioned`].
+///
+/// This is implemented for this crate's primitives which have a specified
+/// layout, and can't be implemented outside of this crate.
+pub trait Versionable: sealed::Sealed {}
+
+mod sealed {
+    pub trait Sealed {
+        /// The code for this kind of primitive. These codes are never
+        /// reused.
+        const KIND: u8;
+
+        /// Whether this primitive is from the `shm` module.
+        const SHM: bool;
+    }
+}
+
+macro_rules! versionable {
+    ($($kind:literal => $ty:ident$(<$param:ident $(: $bound:path)?>)?,)*) => {$(
+        impl<$($param $(: $bound)?,)? const SHM: bool> sealed::Sealed for $ty<$($param,)? SHM> {
+            const KIND: u8 = $kind;
+            const SHM: bool = SHM;
+        }
+        impl<$($param $(: $bound)?,)? const SHM: bool> Versionable for $ty<$($param,)? SHM> {}
+    )*};
+}
+
+versionable! {
+    1 => RawMutex,
+    2 => RawRwLock,
+    3 => TicketMutex,
+    4 => RawCondvar,
+    5 => Condvar,
+    6 => Once,
+    7 => OnceLock<V>,
+    8 => ReprCMutex<V>,
+    9 => ReprCRwLock<V>,
+    10 => SeqLock<V: Copy>,
+    11 => Semaphore,
+    12 => Event,
+    13 => Latch,
+    14 => Parker,
+}
+
+// `RobustOnce` is only in the `shm` module.
+impl sealed::Sealed for RobustOnce {
+    const KIND: u8 = 15;
+    const SHM: bool = true;
+}
+impl Versionable for RobustOnce {}
@@ -2,33 +2,46 @@
 //! library/std/src/sys/pal/unix/futex.rs at revision
 //! b58f647d5488dce73bba517907c44af2c2a618c4.
 
+#[cfg(not(miri))]
 use core::num::NonZeroU32;
 use core::sync::atomic::AtomicU32;
 use core::time::Duration;
+#[cfg(not(miri))]
 use rustix::thread::futex;
 use rustix::time::{ClockId, Timespec};
+use crate::futex_backend::{FutexBackend, SyscallBackend};
 
 /// Wait for a futex_wake operation to wake us.
 ///
 /// Returns directly if the futex doesn't hold the expected value.
 ///
 /// Returns false on timeout, and true in all other cases.
-pub fn futex_wait<const SHM: bool>(futex: &AtomicU32, expected: u32, timeout: Option<Duration>) -> bool {
+#[cfg_attr(loom, allow(dead_code))]
+pub fn futex_wait<const SHM: bool, B: FutexBackend>(
+    futex: &AtomicU32,
+    expected: u32,
+    timeout: Option<Duration>,
+) -> bool {
     // Calculate the timeout as an absolute timespec.
     //
     // Overflows are rounded up to an infinite timeout (None).
-    let timespec = timeout.and_then(|d| {
-        Some({
-            let now = rustix::time::clock_gettime(ClockId::Monotonic);
-            let plus = Duration::new(now.tv_sec as u64, now.tv_nsec as _).checked_add(d)?;
-            Timespec {
-                tv_sec: plus.as_secs() as i64,
-                tv_nsec: plus.subsec_nanos() as _,
-            }
-        })
-    });
+    let timespec = timeout.and_then(deadline);
+
+    futex_wait_timespec::<SHM, B>(futex, expected, timespec.as_ref())
+}
 
-    futex_wait_timespec::<SHM>(futex, expected, timespec.as_ref())
+/// Compute the absolute time on the [`ClockId::Monotonic`] clock that is
+/// `timeout` from now, for use with [`futex_wait_timespec`].
+///
+/// Returns `None` if the computation overflows, which callers should treat
+/// as an infinite timeout.
+pub fn deadline(timeout: Duration) -> Option<Timespec> {
+    let now = rustix::time::clock_gettime(ClockId::Monotonic);
+    let plus = Duration::new(now.tv_sec as u64, now.tv_nsec as _).checked_add(timeout)?;
+    Some(Timespec {
+        tv_sec: plus.as_secs() as i64,
+        tv_nsec: plus.subsec_nanos() as _,
+    })
 }
 
 /// Like [`futex_wait`], but takes a [`Timespec`] for an optional time on the
@@ -36,66 +49,182 @@ pub fn futex_wait<const SHM: bool>(futex: &AtomicU32, expected: u32, timeout: Op
 ///
 /// This allows callers that don't need the timeout to pass `None` and avoid
 /// statically depending on `clock_gettime`.
-pub fn futex_wait_timespec<const SHM: bool>(futex: &AtomicU32, expected: u32, timespec: Option<&Timespec>) -> bool {
-    use core::sync::atomic::Ordering::Relaxed;
+#[inline]
+pub fn futex_wait_timespec<const SHM: bool, B: FutexBackend>(
+    futex: &AtomicU32,
+    expected: u32,
+    timespec: Option<&Timespec>,
+) -> bool {
+    B::wait(futex, expected, timespec, SHM)
+}
 
-    let flags = if SHM {
-        futex::Flags::empty()
-    } else {
-        futex::Flags::PRIVATE
-    };
+/// Wake up one thread that's blocked on futex_wait on this futex.
+///
+/// Returns true if this actually woke up such a thread,
+/// or false if no thread was waiting on this futex.
+#[inline]
+pub fn futex_wake<const SHM: bool, B: FutexBackend>(futex: &AtomicU32) -> bool {
+    B::wake(futex, 1, SHM) != 0
+}
 
-    loop {
-        // No need to wait if the value already changed.
-        if futex.load(Relaxed) != expected {
-            return true;
-        }
+/// Wake up at most `n` threads that are waiting on futex_wait on this futex.
+#[inline]
+pub fn futex_wake_n<const SHM: bool, B: FutexBackend>(futex: &AtomicU32, n: u32) {
+    B::wake(futex, n, SHM);
+}
+
+/// Wake up all threads that are waiting on futex_wait on this futex.
+#[inline]
+pub fn futex_wake_all<const SHM: bool, B: FutexBackend>(futex: &AtomicU32) {
+    B::wake_all(futex, SHM);
+}
+
+#[cfg(not(miri))]
+impl FutexBackend for SyscallBackend {
+    fn wait(futex: &AtomicU32, expected: u32, timespec: Option<&Timespec>, shared: bool) -> bool {
+        use core::sync::atomic::Ordering::Relaxed;
+
+        let flags = flags(shared);
+
+        loop {
+            // No need to wait if the value already changed.
+            if futex.load(Relaxed) != expected {
+                return true;
+            }
 
-        let r =
-            // Use `FUTEX_WAIT_BITSET` rather than `FUTEX_WAIT` to be able to
-            // give an absolute time rather than a relative time.
-            futex::wait_bitset(
-                futex,
-                flags,
-                expected,
-                timespec,
- // A full bitmask, to make it behave like a regular `FUTEX_WAIT`.
-                NonZeroU32::MAX
-            )
-        ;
-
-        match r {
-            Err(rustix::io::Errno::TIMEDOUT) => return false,
-            Err(rustix::io::Errno::INTR) => continue,
-            _ => return true,
+            let r =
+                // Use `FUTEX_WAIT_BITSET` rather than `FUTEX_WAIT` to be able to
+                // give an absolute time rather than a relative time.
+                futex::wait_bitset(
+                    futex,
+                    flags,
+                    expected,
+                    timespec,
+                    // A full bitmask, to make it behave like a regular `FUTEX_WAIT`.
+                    NonZeroU32::MAX
+                )
+            ;
+
+            match r {
+                Err(rustix::io::Errno::TIMEDOUT) => return false,
+                Err(rustix::io::Errno::INTR) => continue,
+                _ => return true,
+            }
         }
     }
+
+    fn wake(futex: &AtomicU32, n: u32, shared: bool) -> u32 {
+        futex::wake(futex, flags(shared), n.min(i32::MAX as u32)).map_or(0, |n| n as u32)
+    }
 }
 
-/// Wake up one thread that's blocked on futex_wait on this futex.
-///
-/// Returns true if this actually woke up such a thread,
-/// or false if no thread was waiting on this futex.
-pub fn futex_wake<const SHM: bool>(futex: &AtomicU32) -> bool {
-    let flags = if SHM {
+#[cfg(not(miri))]
+fn flags(shared: bool) -> futex::Flags {
+    if shared {
         futex::Flags::empty()
     } else {
         futex::Flags::PRIVATE
-    };
+    }
+}
+
+/// Miri can't run the `futex` system calls, so under Miri, emulate them with
+/// `std`'s `Mutex` and `Condvar`, which it can. There are no other processes
+/// under Miri, so `shared` is ignored.
+#[cfg(miri)]
+impl FutexBackend for SyscallBackend {
+    fn wait(futex: &AtomicU32, expected: u32, timespec: Option<&Timespec>, _shared: bool) -> bool {
+        miri::wait(futex, expected, timespec)
+    }
 
-    match futex::wake(futex, flags, 1) {
-        Err(_) | Ok(0) => false,
-        _ => true,
+    fn wake(futex: &AtomicU32, n: u32, _shared: bool) -> u32 {
+        miri::wake(futex, n)
     }
 }
 
-/// Wake up all threads that are waiting on futex_wait on this futex.
-pub fn futex_wake_all<const SHM: bool>(futex: &AtomicU32) {
-    let flags = if SHM {
-        futex::Flags::empty()
-    } else {
-        futex::Flags::PRIVATE
-    };
+#[cfg(miri)]
+mod miri {
+    extern crate std;
+
+    use core::sync::atomic::{AtomicU32, Ordering::SeqCst};
+    use core::time::Duration;
+    use rustix::time::{ClockId, Timespec};
+    use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+    use std::vec::Vec;
+
+    /// The emulated kernel futex state: the next waiter ID, and the address
+    /// of each waiting thread's futex, with its waiter ID, in the order they
+    /// started waiting.
+    static WAITERS: Mutex<(u64, Vec<(usize, u64)>)> = Mutex::new((0, Vec::new()));
+
+    /// Notified whenever waiters are removed from `WAITERS`.
+    static WOKEN: Condvar = Condvar::new();
+
+    fn waiters() -> MutexGuard<'static, (u64, Vec<(usize, u64)>)> {
+        WAITERS.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(super) fn wait(futex: &AtomicU32, expected: u32, timespec: Option<&Timespec>) -> bool {
+        let addr = futex as *const AtomicU32 as usize;
+        let mut waiters = waiters();
 
-    futex::wake(futex, flags, i32::MAX as u32).ok();
+        // The kernel checks the value while holding the lock that wakers
+        // take, so a change followed by a wake can't be missed.
+        if futex.load(SeqCst) != expected {
+            return true;
+        }
+
+        let id = waiters.0;
+        waiters.0 += 1;
+        waiters.1.push((addr, id));
+
+        loop {
+            if !waiters.1.iter().any(|&(_, w)| w == id) {
+                return true;
+            }
+
+            waiters = match timespec {
+                None => WOKEN.wait(waiters).unwrap_or_else(PoisonError::into_inner),
+                Some(timespec) => match remaining(timespec) {
+                    Some(timeout) => {
+                        WOKEN
+                            .wait_timeout(waiters, timeout)
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .0
+                    }
+                    None => {
+                        waiters.1.retain(|&(_, w)| w != id);
+                        return false;
+                    }
+                },
+            };
+        }
+    }
+
+    pub(super) fn wake(futex: &AtomicU32, n: u32) -> u32 {
+        let addr = futex as *const AtomicU32 as usize;
+        let mut waiters = waiters();
+
+        let mut woken = 0;
+        waiters.1.retain(|&(a, _)| {
+            if a == addr && woken < n {
+                woken += 1;
+                false
+            } else {
+                true
+            }
+        });
+        if woken != 0 {
+            WOKEN.notify_all();
+        }
+        woken
+    }
+
+    /// Returns the time from now until `timespec` on the
+    /// [`ClockId::Monotonic`] clock, or `None` if it has passed.
+    fn remaining(timespec: &Timespec) -> Option<Duration> {
+        let now = rustix::time::clock_gettime(ClockId::Monotonic);
+        let now = Duration::new(now.tv_sec as u64, now.tv_nsec as _);
+        let deadline = Duration::new(timespec.tv_sec as u64, timespec.tv_nsec as _);
+        deadline.checked_sub(now).filter(|d| !d.is_zero())
+    }
 }
@@ -0,0 +1,59 @@
+//! Implementations of [`bytemuck::Zeroable`] for the types in this crate.
+//!
+//! All of this crate's synchronization primitives are guaranteed to be in
+//! their initial state when all of their bytes are zero, so they can be
+//! used in zero-filled memory, such as a freshly created shared-memory
+//! mapping, without being explicitly initialized.
+//!
+//! The corresponding `zerocopy::FromZeros` implementations are derived on
+//! the types themselves.
+
+use bytemuck::Zeroable;
+use crate::futex_backend::FutexBackend;
+use crate::generic::{
+    Event, Latch, Once, OnceLock, Parker, RawCondvar, RawMutex, RawRwLock, ReprCMutex,
+    ReprCRwLock, Semaphore, SeqLock, TicketMutex,
+};
+
+// SAFETY: All-zeros is the `INIT` state of these types.
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for RawMutex<SHM, B> {}
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for RawRwLock<SHM, B> {}
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for TicketMutex<SHM, B> {}
+
+// SAFETY: All-zeros is the state that `new` initializes these types to.
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for RawCondvar<SHM, B> {}
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for crate::generic::Condvar<SHM, B> {}
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for Once<SHM, B> {}
+
+// SAFETY: An all-zeros `Semaphore` has no permits and no waiters.
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for Semaphore<SHM, B> {}
+
+// SAFETY: An all-zeros `Event` is a manual-reset event which is not set.
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for Event<SHM, B> {}
+
+// SAFETY: An all-zeros `Latch` has a count of zero and no waiters.
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for Latch<SHM, B> {}
+
+// SAFETY: An all-zeros `Parker` is empty.
+unsafe impl<const SHM: bool, B: FutexBackend> Zeroable for Parker<SHM, B> {}
+
+// SAFETY: An all-zeros `OnceLock` has an incomplete `Once` and an
+// uninitialized value, so the value is never read.
+unsafe impl<T, const SHM: bool, B: FutexBackend> Zeroable for OnceLock<T, SHM, B> {}
+
+// SAFETY: These are `repr(C)` structs containing an unlocked lock (and for
+// `SeqLock`, a sequence number) and a `T`, which is valid when all-zeros
+// because `T: Zeroable`.
+unsafe impl<T: Zeroable, const SHM: bool, B: FutexBackend> Zeroable for ReprCMutex<T, SHM, B> {}
+unsafe impl<T: Zeroable, const SHM: bool, B: FutexBackend> Zeroable for ReprCRwLock<T, SHM, B> {}
+unsafe impl<T: Copy + Zeroable, const SHM: bool, B: FutexBackend> Zeroable for SeqLock<T, SHM, B> {}
+
+// SAFETY: All-zeros is the state that `new` initializes a `RobustOnce` to.
+#[cfg(feature = "shm")]
+unsafe impl Zeroable for crate::shm::RobustOnce {}
+
+// SAFETY: An all-zeros `Versioned` has a header which no process has
+// attached to yet, and a `T`, which is valid when all-zeros because
+// `T: Zeroable`.
+#[cfg(feature = "shm")]
+unsafe impl<T: crate::shm::Versionable + Zeroable> Zeroable for crate::shm::Versioned<T> {}
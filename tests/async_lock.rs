@@ -0,0 +1,197 @@
+#![cfg(feature = "alloc")]
+
+use rustix_futex_sync::{AsyncMutex, AsyncRwLock};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+struct ThreadWaker {
+    thread: Thread,
+    woken: AtomicBool,
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.woken.store(true, SeqCst);
+        self.thread.unpark();
+    }
+}
+
+fn waker() -> (Arc<ThreadWaker>, Waker) {
+    let inner = Arc::new(ThreadWaker {
+        thread: thread::current(),
+        woken: AtomicBool::new(false),
+    });
+    (inner.clone(), Waker::from(inner))
+}
+
+/// A minimal executor, which parks the thread until the future is woken.
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let (_, waker) = waker();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+            return out;
+        }
+        thread::park();
+    }
+}
+
+#[test]
+fn mutex_smoke() {
+    let m = AsyncMutex::new(0);
+    *m.lock() += 1;
+    *block_on(m.lock_async()) += 1;
+    *m.try_lock().unwrap() += 1;
+    assert_eq!(m.into_inner(), 3);
+}
+
+#[test]
+fn mutex_try_lock() {
+    let m = AsyncMutex::new(());
+    let g = m.lock();
+    assert!(m.try_lock().is_none());
+    drop(g);
+    assert!(m.try_lock().is_some());
+}
+
+#[test]
+fn mutex_async_waits_for_blocking() {
+    let m = AsyncMutex::new(0);
+    let g = m.lock();
+
+    let (woken, waker) = waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = pin!(m.lock_async());
+    assert!(fut.as_mut().poll(&mut cx).is_pending());
+    assert!(!woken.woken.load(SeqCst));
+
+    drop(g);
+    assert!(woken.woken.load(SeqCst));
+    match fut.as_mut().poll(&mut cx) {
+        Poll::Ready(mut g) => *g += 1,
+        Poll::Pending => panic!("lock_async didn't acquire the unlocked mutex"),
+    }
+    assert_eq!(*m.lock(), 1);
+}
+
+#[test]
+fn mutex_dropped_future_passes_on_wakeup() {
+    let m = AsyncMutex::new(());
+    let g = m.lock();
+
+    let (woken1, waker1) = waker();
+    let (woken2, waker2) = waker();
+    let mut fut1 = Box::pin(m.lock_async());
+    let mut fut2 = Box::pin(m.lock_async());
+    assert!(fut1.as_mut().poll(&mut Context::from_waker(&waker1)).is_pending());
+    assert!(fut2.as_mut().poll(&mut Context::from_waker(&waker2)).is_pending());
+
+    // Unlocking wakes one task. If it gives up, the other is woken instead.
+    drop(g);
+    assert!(woken1.woken.load(SeqCst) != woken2.woken.load(SeqCst));
+    if woken1.woken.load(SeqCst) {
+        drop(fut1);
+        assert!(woken2.woken.load(SeqCst));
+        assert!(fut2.as_mut().poll(&mut Context::from_waker(&waker2)).is_ready());
+    } else {
+        drop(fut2);
+        assert!(woken1.woken.load(SeqCst));
+        assert!(fut1.as_mut().poll(&mut Context::from_waker(&waker1)).is_ready());
+    }
+}
+
+#[test]
+fn mutex_mixed_contention() {
+    const N: usize = 8;
+    const M: usize = 1000;
+
+    let m = Arc::new(AsyncMutex::new(0));
+    let threads: Vec<_> = (0..N)
+        .map(|i| {
+            let m = m.clone();
+            thread::spawn(move || {
+                for _ in 0..M {
+                    if i % 2 == 0 {
+                        *m.lock() += 1;
+                    } else {
+                        *block_on(m.lock_async()) += 1;
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(*m.lock(), N * M);
+}
+
+#[test]
+fn rwlock_smoke() {
+    let l = AsyncRwLock::new(0);
+    {
+        let r1 = l.read();
+        let r2 = block_on(l.read_async());
+        let r3 = l.try_read().unwrap();
+        assert_eq!(*r1 + *r2 + *r3, 0);
+        assert!(l.try_write().is_none());
+    }
+    *l.write() += 1;
+    *block_on(l.write_async()) += 1;
+    *l.try_write().unwrap() += 1;
+    assert_eq!(l.into_inner(), 3);
+}
+
+#[test]
+fn rwlock_async_writer_waits_for_readers() {
+    let l = AsyncRwLock::new(0);
+    let r = l.read();
+
+    let (woken, waker) = waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = pin!(l.write_async());
+    assert!(fut.as_mut().poll(&mut cx).is_pending());
+
+    // New readers wait behind the waiting writer.
+    assert!(l.try_read().is_none());
+
+    drop(r);
+    assert!(woken.woken.load(SeqCst));
+    match fut.as_mut().poll(&mut cx) {
+        Poll::Ready(mut g) => *g += 1,
+        Poll::Pending => panic!("write_async didn't acquire the unlocked lock"),
+    }
+    assert_eq!(*l.read(), 1);
+}
+
+#[test]
+fn rwlock_mixed_contention() {
+    const N: usize = 8;
+    const M: usize = 1000;
+
+    let l = Arc::new(AsyncRwLock::new(0));
+    let threads: Vec<_> = (0..N)
+        .map(|i| {
+            let l = l.clone();
+            thread::spawn(move || {
+                for _ in 0..M {
+                    match i % 4 {
+                        0 => *l.write() += 1,
+                        1 => *block_on(l.write_async()) += 1,
+                        2 => drop(l.read()),
+                        _ => drop(block_on(l.read_async())),
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(*l.read(), N / 2 * M);
+}
@@ -0,0 +1,79 @@
+//! The following is derived from Rust's
+//! library/std/src/sync/barrier/tests.rs at revision
+//! 22a5267c83a3e17f2b763279eb24bb632c45dc6b.
+
+use rustix_futex_sync::Barrier;
+use std::sync::mpsc::{channel, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+#[cfg_attr(target_os = "emscripten", ignore)]
+fn test_barrier() {
+    const N: usize = 10;
+
+    let barrier = Arc::new(Barrier::new(N));
+    let (tx, rx) = channel();
+
+    for _ in 0..N - 1 {
+        let c = barrier.clone();
+        let tx = tx.clone();
+        thread::spawn(move || {
+            tx.send(c.wait().is_leader()).unwrap();
+        });
+    }
+
+    // At this point, all spawned threads should be blocked,
+    // so we shouldn't get anything from the port
+    assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
+
+    let mut leader_found = barrier.wait().is_leader();
+
+    // Now, the barrier is cleared and we should get data.
+    for _ in 0..N - 1 {
+        if rx.recv().unwrap() {
+            assert!(!leader_found);
+            leader_found = true;
+        }
+    }
+    assert!(leader_found);
+}
+
+#[test]
+fn reuse() {
+    const N: usize = 4;
+    const ROUNDS: usize = 100;
+
+    let barrier = Arc::new(Barrier::new(N));
+    let handles = (0..N)
+        .map(|_| {
+            let c = barrier.clone();
+            thread::spawn(move || (0..ROUNDS).filter(|_| c.wait().is_leader()).count())
+        })
+        .collect::<Vec<_>>();
+
+    let leaders: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(leaders, ROUNDS);
+}
+
+#[test]
+fn single_and_zero() {
+    assert!(Barrier::new(0).wait().is_leader());
+    assert!(Barrier::new(1).wait().is_leader());
+}
+
+#[test]
+#[cfg_attr(target_os = "emscripten", ignore)]
+fn wait_timeout() {
+    let barrier = Arc::new(Barrier::new(2));
+
+    // Nobody else arrives, so this times out and withdraws.
+    assert!(barrier.wait_timeout(Duration::from_millis(10)).is_none());
+
+    // The withdrawn arrival isn't counted, so it still takes two threads.
+    let c = barrier.clone();
+    let t = thread::spawn(move || c.wait_timeout(Duration::from_secs(60)).unwrap().is_leader());
+    let leader = barrier.wait().is_leader();
+    assert_ne!(leader, t.join().unwrap());
+}
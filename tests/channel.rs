@@ -0,0 +1,161 @@
+#![cfg(feature = "alloc")]
+
+use rustix_futex_sync::channel::{
+    bounded, RecvError, RecvTimeoutError, SendError, SendTimeoutError, TryRecvError, TrySendError,
+};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let (tx, rx) = bounded(1);
+    tx.send(1).unwrap();
+    assert_eq!(rx.recv().unwrap(), 1);
+}
+
+#[test]
+fn try_send_try_recv() {
+    let (tx, rx) = bounded(2);
+    assert_eq!(tx.capacity(), 2);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+    tx.try_send(1).unwrap();
+    tx.try_send(2).unwrap();
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+    assert_eq!(rx.len(), 2);
+
+    assert_eq!(rx.try_recv(), Ok(1));
+    assert_eq!(rx.try_recv(), Ok(2));
+    assert!(rx.is_empty());
+}
+
+#[test]
+fn timeouts() {
+    let (tx, rx) = bounded(1);
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    tx.send(1).unwrap();
+    assert_eq!(
+        tx.send_timeout(2, Duration::from_millis(10)),
+        Err(SendTimeoutError::Timeout(2))
+    );
+    assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Ok(1));
+}
+
+#[test]
+fn disconnect_senders() {
+    let (tx, rx) = bounded(2);
+    let tx2 = tx.clone();
+    tx.send(1).unwrap();
+    drop(tx);
+    tx2.send(2).unwrap();
+    drop(tx2);
+
+    // Values already sent can still be received.
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Ok(2));
+    assert_eq!(rx.recv(), Err(RecvError));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+}
+
+#[test]
+fn disconnect_receivers() {
+    let (tx, rx) = bounded(1);
+    drop(rx.clone());
+    drop(rx);
+    assert_eq!(tx.send(1), Err(SendError(1)));
+    assert_eq!(tx.try_send(2), Err(TrySendError::Disconnected(2)));
+}
+
+#[test]
+fn disconnect_wakes_blocked() {
+    let (tx, rx) = bounded::<i32>(1);
+    let t = thread::spawn(move || rx.recv());
+    thread::sleep(Duration::from_millis(10));
+    drop(tx);
+    assert_eq!(t.join().unwrap(), Err(RecvError));
+
+    let (tx, rx) = bounded(1);
+    tx.send(1).unwrap();
+    let t = thread::spawn(move || tx.send(2));
+    thread::sleep(Duration::from_millis(10));
+    drop(rx);
+    assert_eq!(t.join().unwrap(), Err(SendError(2)));
+}
+
+#[test]
+fn rendezvous() {
+    let (tx, rx) = bounded(0);
+    assert_eq!(tx.capacity(), 0);
+
+    // Nobody is receiving.
+    assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+    assert_eq!(
+        tx.send_timeout(1, Duration::from_millis(10)),
+        Err(SendTimeoutError::Timeout(1))
+    );
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+    let t = thread::spawn(move || {
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+    });
+    thread::sleep(Duration::from_millis(10));
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Ok(2));
+    t.join().unwrap();
+    assert_eq!(rx.recv(), Err(RecvError));
+}
+
+#[test]
+fn rendezvous_receiver_dropped() {
+    let (tx, rx) = bounded(0);
+    let t = thread::spawn(move || tx.send(1));
+    thread::sleep(Duration::from_millis(10));
+    drop(rx);
+    assert_eq!(t.join().unwrap(), Err(SendError(1)));
+}
+
+#[test]
+fn mpmc() {
+    const SENDERS: usize = 4;
+    const RECEIVERS: usize = 4;
+    const N: usize = 1000;
+
+    for cap in [0, 1, 16] {
+        let (tx, rx) = bounded(cap);
+
+        let senders = (0..SENDERS)
+            .map(|_| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..N {
+                        tx.send(i).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(tx);
+
+        let receivers = (0..RECEIVERS)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.iter().sum::<usize>())
+            })
+            .collect::<Vec<_>>();
+        drop(rx);
+
+        for s in senders {
+            s.join().unwrap();
+        }
+        let total: usize = receivers.into_iter().map(|r| r.join().unwrap()).sum();
+        assert_eq!(total, SENDERS * N * (N - 1) / 2);
+    }
+}
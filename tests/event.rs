@@ -0,0 +1,112 @@
+use rustix_futex_sync::Event;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn manual_reset() {
+    let event = Event::new();
+    assert!(!event.is_set());
+    assert!(!event.is_auto_reset());
+    assert!(!event.wait_timeout(Duration::from_millis(10)));
+
+    event.set();
+    assert!(event.is_set());
+    event.wait();
+    event.wait();
+    assert!(event.wait_timeout(Duration::from_millis(10)));
+    assert!(event.is_set());
+
+    event.reset();
+    assert!(!event.is_set());
+    assert!(!event.wait_timeout(Duration::from_millis(10)));
+}
+
+#[test]
+fn manual_reset_wakes_all() {
+    const N: usize = 8;
+
+    let event = Arc::new(Event::new());
+    let handles = (0..N)
+        .map(|_| {
+            let event = event.clone();
+            thread::spawn(move || event.wait())
+        })
+        .collect::<Vec<_>>();
+
+    thread::sleep(Duration::from_millis(10));
+    event.set();
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn auto_reset() {
+    let event = Event::new_auto_reset();
+    assert!(event.is_auto_reset());
+    assert!(!event.is_set());
+
+    event.set();
+    event.set();
+    assert!(event.is_set());
+    event.wait();
+    assert!(!event.is_set());
+    assert!(!event.wait_timeout(Duration::from_millis(10)));
+
+    event.set();
+    assert!(event.wait_timeout(Duration::from_millis(10)));
+    assert!(!event.is_set());
+}
+
+#[test]
+fn auto_reset_releases_one() {
+    const N: usize = 4;
+
+    let event = Arc::new(Event::new_auto_reset());
+    let released = Arc::new(AtomicUsize::new(0));
+    let handles = (0..N)
+        .map(|_| {
+            let event = event.clone();
+            let released = released.clone();
+            thread::spawn(move || {
+                event.wait();
+                released.fetch_add(1, SeqCst);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for i in 1..=N {
+        event.set();
+        while released.load(SeqCst) < i {
+            thread::yield_now();
+        }
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(released.load(SeqCst), i);
+    }
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert!(!event.is_set());
+}
+
+#[test]
+fn ping_pong() {
+    let ping = Arc::new(Event::new_auto_reset());
+    let pong = Arc::new(Event::new_auto_reset());
+
+    let (ping2, pong2) = (ping.clone(), pong.clone());
+    let t = thread::spawn(move || {
+        for _ in 0..1000 {
+            ping2.wait();
+            pong2.set();
+        }
+    });
+
+    for _ in 0..1000 {
+        ping.set();
+        pong.wait();
+    }
+    t.join().unwrap();
+}
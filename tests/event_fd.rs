@@ -0,0 +1,101 @@
+#![cfg(all(feature = "eventfd", not(miri)))]
+
+use rustix::event::{poll, PollFd, PollFlags};
+use rustix::time::Timespec;
+use rustix_futex_sync::EventFd;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn readable(event: &EventFd) -> bool {
+    let mut fds = [PollFd::new(event, PollFlags::IN)];
+    let zero = Timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    poll(&mut fds, Some(&zero)).unwrap() != 0
+}
+
+#[test]
+fn manual_reset() {
+    let event = EventFd::new().unwrap();
+    assert!(!event.is_auto_reset());
+    assert!(!event.is_set());
+    assert!(!readable(&event));
+    assert!(!event.wait_timeout(Duration::from_millis(10)));
+
+    event.set();
+    event.set();
+    assert!(event.is_set());
+    assert!(readable(&event));
+    event.wait();
+    event.wait();
+    assert!(event.wait_timeout(Duration::from_millis(10)));
+
+    event.reset();
+    assert!(!event.is_set());
+    assert!(!readable(&event));
+}
+
+#[test]
+fn auto_reset() {
+    let event = EventFd::new_auto_reset().unwrap();
+    assert!(event.is_auto_reset());
+
+    event.set();
+    event.set();
+    assert!(readable(&event));
+    event.wait();
+    assert!(!event.is_set());
+    assert!(!event.wait_timeout(Duration::from_millis(10)));
+}
+
+#[test]
+fn wakes_all() {
+    const N: usize = 4;
+
+    let event = Arc::new(EventFd::new().unwrap());
+    let handles = (0..N)
+        .map(|_| {
+            let event = event.clone();
+            thread::spawn(move || event.wait())
+        })
+        .collect::<Vec<_>>();
+
+    thread::sleep(Duration::from_millis(10));
+    event.set();
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn auto_reset_releases_one() {
+    const N: usize = 4;
+
+    let event = Arc::new(EventFd::new_auto_reset().unwrap());
+    let released = Arc::new(AtomicUsize::new(0));
+    let handles = (0..N)
+        .map(|_| {
+            let event = event.clone();
+            let released = released.clone();
+            thread::spawn(move || {
+                event.wait();
+                released.fetch_add(1, SeqCst);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for i in 1..=N {
+        event.set();
+        while released.load(SeqCst) < i {
+            thread::yield_now();
+        }
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(released.load(SeqCst), i);
+    }
+    for h in handles {
+        h.join().unwrap();
+    }
+}
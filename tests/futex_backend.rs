@@ -0,0 +1,136 @@
+use rustix_futex_sync::generic::{Condvar, Event, Mutex, Semaphore};
+use rustix_futex_sync::{FutexBackend, Timespec};
+use std::mem::size_of_val;
+use std::sync::atomic::{AtomicU32, Ordering::SeqCst};
+use std::sync::{Arc, Condvar as StdCondvar, Mutex as StdMutex};
+use std::thread;
+use std::time::Duration;
+
+/// A userspace simulation of futexes, in which waits with a deadline time
+/// out immediately.
+struct Sim;
+
+struct Waiter {
+    id: u64,
+    addr: usize,
+    shared: bool,
+}
+
+static WAITERS: StdMutex<(u64, Vec<Waiter>)> = StdMutex::new((0, Vec::new()));
+static WOKEN: StdCondvar = StdCondvar::new();
+
+impl FutexBackend for Sim {
+    fn wait(futex: &AtomicU32, expected: u32, deadline: Option<&Timespec>, shared: bool) -> bool {
+        let mut waiters = WAITERS.lock().unwrap();
+        if futex.load(SeqCst) != expected {
+            return true;
+        }
+        if deadline.is_some() {
+            return false;
+        }
+
+        let id = waiters.0;
+        waiters.0 += 1;
+        waiters.1.push(Waiter {
+            id,
+            addr: futex as *const AtomicU32 as usize,
+            shared,
+        });
+        while waiters.1.iter().any(|w| w.id == id) {
+            waiters = WOKEN.wait(waiters).unwrap();
+        }
+        true
+    }
+
+    fn wake(futex: &AtomicU32, n: u32, _shared: bool) -> u32 {
+        let addr = futex as *const AtomicU32 as usize;
+        let mut waiters = WAITERS.lock().unwrap();
+        let mut woken = 0;
+        waiters.1.retain(|w| {
+            if w.addr == addr && woken < n {
+                woken += 1;
+                false
+            } else {
+                true
+            }
+        });
+        WOKEN.notify_all();
+        woken
+    }
+}
+
+/// Returns the `shared` flag of a thread waiting on a futex within `object`,
+/// if there is one.
+fn waiter_in<T>(object: &T) -> Option<bool> {
+    let start = object as *const T as usize;
+    let end = start + size_of_val(object);
+    let waiters = WAITERS.lock().unwrap();
+    waiters
+        .1
+        .iter()
+        .find(|w| (start..end).contains(&w.addr))
+        .map(|w| w.shared)
+}
+
+fn wait_for_waiter<T>(object: &T) -> bool {
+    loop {
+        if let Some(shared) = waiter_in(object) {
+            return shared;
+        }
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn mutex() {
+    let m = Arc::new(Mutex::<u32, false, Sim>::new(0));
+    let threads = (0..4)
+        .map(|_| {
+            let m = Arc::clone(&m);
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    *m.lock() += 1;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(*m.lock(), 4000);
+}
+
+#[test]
+fn waits_in_backend() {
+    let sem = Arc::new(Semaphore::<false, Sim>::new(0));
+    let sem2 = Arc::clone(&sem);
+    let t = thread::spawn(move || {
+        sem2.acquire().forget();
+    });
+
+    assert!(!wait_for_waiter(&*sem));
+    sem.release(1);
+    t.join().unwrap();
+    assert_eq!(waiter_in(&*sem), None);
+}
+
+#[test]
+fn shared() {
+    let event = Arc::new(Event::<true, Sim>::new());
+    let event2 = Arc::clone(&event);
+    let t = thread::spawn(move || event2.wait());
+
+    assert!(wait_for_waiter(&*event));
+    event.set();
+    t.join().unwrap();
+}
+
+#[test]
+fn deadlines() {
+    let m = Mutex::<(), false, Sim>::new(());
+    let c = Condvar::<false, Sim>::new();
+
+    // The backend times out immediately, rather than after an hour.
+    let (_guard, result) = c.wait_timeout(m.lock(), Duration::from_secs(60 * 60));
+    assert!(result.timed_out());
+}
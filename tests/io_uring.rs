@@ -0,0 +1,174 @@
+#![cfg(all(feature = "io_uring", not(miri)))]
+
+use rustix::io_uring::{FutexWaitFlags, IoringOp};
+use rustix::thread::futex;
+use rustix::time::ClockId;
+use rustix_futex_sync::lock_api::RawMutex as _;
+use rustix_futex_sync::{io_uring, Once, RawCondvar, RawMutex};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Locks `mutex` by waiting with `futex_waitv`, as an io_uring runtime would
+/// with `IORING_OP_FUTEX_WAITV`.
+fn lock_with_waitv(mutex: &RawMutex) {
+    let mut next = io_uring::mutex_lock_after_wait(mutex);
+    while let Some(wait) = next {
+        match futex::waitv(&[wait.to_waitv()], futex::WaitvFlags::empty(), None, ClockId::Monotonic) {
+            Ok(_) | Err(rustix::io::Errno::AGAIN) => {}
+            Err(err) => panic!("futex_waitv failed: {:?}", err),
+        }
+        next = io_uring::mutex_lock_after_wait(mutex);
+    }
+}
+
+#[test]
+fn mutex_lock_uncontended() {
+    let mutex = RawMutex::INIT;
+    assert!(io_uring::mutex_lock(&mutex).is_none());
+    assert!(!mutex.try_lock());
+    unsafe { mutex.unlock() };
+}
+
+#[test]
+fn mutex_lock_sqe() {
+    let mutex = RawMutex::INIT;
+    mutex.lock();
+
+    let wait = io_uring::mutex_lock(&mutex).unwrap();
+    assert!(!wait.is_shared());
+    assert!(!wait.is_ready());
+    assert_eq!(wait.flags(), FutexWaitFlags::SIZE_U32 | FutexWaitFlags::PRIVATE);
+
+    let sqe = wait.to_sqe();
+    assert_eq!(sqe.opcode, IoringOp::FutexWait);
+    assert_eq!(sqe.fd as u32, wait.flags().bits());
+    unsafe {
+        assert_eq!(sqe.addr_or_splice_off_in.addr.ptr, wait.addr().cast());
+        assert_eq!(sqe.off_or_addr2.off, u64::from(wait.expected()));
+        assert_eq!(sqe.addr3_or_cmd.addr3.addr3, u64::from(u32::MAX));
+        assert_eq!(sqe.len.len, 0);
+    }
+
+    unsafe { mutex.unlock() };
+    assert!(wait.is_ready());
+    assert!(io_uring::mutex_lock(&mutex).is_none());
+    unsafe { mutex.unlock() };
+}
+
+#[test]
+fn mutex_lock_waitv() {
+    let mutex = RawMutex::INIT;
+    mutex.lock();
+
+    thread::scope(|s| {
+        s.spawn(|| lock_with_waitv(&mutex));
+
+        thread::sleep(Duration::from_millis(100));
+
+        // A regular unlock wakes the waiter.
+        unsafe { mutex.unlock() };
+    });
+
+    assert!(!mutex.try_lock());
+    unsafe { mutex.unlock() };
+}
+
+#[test]
+fn mutex_lock_two_waiters() {
+    static MUTEX: RawMutex = RawMutex::INIT;
+    MUTEX.lock();
+
+    // The waitv waiter starts waiting first, so the unlock wakes it.
+    let waitv = thread::spawn(|| {
+        lock_with_waitv(&MUTEX);
+        unsafe { MUTEX.unlock() };
+    });
+    thread::sleep(Duration::from_millis(100));
+    let (tx, rx) = mpsc::channel();
+    let blocked = thread::spawn(move || {
+        MUTEX.lock();
+        unsafe { MUTEX.unlock() };
+        tx.send(()).unwrap();
+    });
+    thread::sleep(Duration::from_millis(100));
+
+    // The waitv waiter's unlock must wake the blocked thread.
+    unsafe { MUTEX.unlock() };
+    rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    waitv.join().unwrap();
+    blocked.join().unwrap();
+}
+
+#[test]
+fn waitv_sqe() {
+    let a = RawMutex::INIT;
+    let b = RawMutex::INIT;
+    a.lock();
+    b.lock();
+
+    let waits = [
+        io_uring::mutex_lock(&a).unwrap().to_waitv(),
+        io_uring::mutex_lock(&b).unwrap().to_waitv(),
+    ];
+    let sqe = io_uring::waitv_sqe(&waits);
+    assert_eq!(sqe.opcode, IoringOp::FutexWaitv);
+    assert_eq!(sqe.fd, 0);
+    unsafe {
+        assert_eq!(sqe.addr_or_splice_off_in.addr.ptr, waits.as_ptr() as *mut _);
+        assert_eq!(sqe.len.len, 2);
+    }
+
+    unsafe {
+        a.unlock();
+        b.unlock();
+    }
+}
+
+#[test]
+fn condvar() {
+    let mutex = RawMutex::INIT;
+    let condvar = RawCondvar::new();
+
+    mutex.lock();
+    let wait = unsafe { io_uring::condvar_wait(&condvar, &mutex) };
+    // The mutex was unlocked.
+    assert!(mutex.try_lock());
+    unsafe { mutex.unlock() };
+    assert!(!wait.is_ready());
+
+    let wake = io_uring::condvar_notify_one(&condvar);
+    assert!(wait.is_ready());
+    assert_eq!(wake.count(), 1);
+    assert_eq!(wake.addr(), wait.addr());
+
+    let sqe = wake.to_sqe();
+    assert_eq!(sqe.opcode, IoringOp::FutexWake);
+    unsafe {
+        assert_eq!(sqe.off_or_addr2.off, 1);
+    }
+
+    assert_eq!(io_uring::condvar_notify_all(&condvar).count(), i32::MAX as u32);
+}
+
+#[test]
+fn once() {
+    let once = Once::new();
+    thread::scope(|s| {
+        s.spawn(|| {
+            once.call_once(|| thread::sleep(Duration::from_millis(100)));
+        });
+
+        loop {
+            let wait = match io_uring::once_wait(&once) {
+                None => break,
+                Some(wait) => wait,
+            };
+            let _ = futex::waitv(&[wait.to_waitv()], futex::WaitvFlags::empty(), None, ClockId::Monotonic);
+        }
+        assert!(once.is_completed());
+    });
+
+    assert!(io_uring::once_wait(&once).is_none());
+    assert!(io_uring::once_wait_force(&once).is_none());
+}
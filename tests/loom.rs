@@ -0,0 +1,140 @@
+#![cfg(loom)]
+
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --release --test loom`.
+
+use loom::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use loom::sync::Arc;
+use loom::thread;
+use rustix_futex_sync::{Condvar, Mutex, Once, RwLock};
+
+/// Run `f` under loom, bounding the preemptions to keep the state space
+/// manageable unless `LOOM_MAX_PREEMPTIONS` says otherwise.
+fn model<F: Fn() + Sync + Send + 'static>(f: F) {
+    let mut builder = loom::model::Builder::new();
+    if builder.preemption_bound.is_none() {
+        builder.preemption_bound = Some(3);
+    }
+    builder.check(f);
+}
+
+#[test]
+fn mutex_contention() {
+    model(|| {
+        let m = Arc::new(Mutex::new(0));
+        let thread = {
+            let m = m.clone();
+            thread::spawn(move || *m.lock() += 1)
+        };
+        *m.lock() += 1;
+        thread.join().unwrap();
+        assert_eq!(*m.lock(), 2);
+    });
+}
+
+#[test]
+fn rwlock_readers_and_writer() {
+    model(|| {
+        let l = Arc::new(RwLock::new(0));
+        let reader = {
+            let l = l.clone();
+            thread::spawn(move || {
+                let n = *l.read();
+                assert!(n == 0 || n == 1);
+            })
+        };
+        let writer = {
+            let l = l.clone();
+            thread::spawn(move || *l.write() += 1)
+        };
+        let n = *l.read();
+        assert!(n == 0 || n == 1);
+        reader.join().unwrap();
+        writer.join().unwrap();
+        assert_eq!(*l.read(), 1);
+    });
+}
+
+/// Exercise `wake_writer_or_readers`, where unlocking must wake a writer if
+/// one is waiting, and readers otherwise.
+#[test]
+fn rwlock_wake_writer_or_readers() {
+    model(|| {
+        let l = Arc::new(RwLock::new(0));
+        let guard = l.write();
+        let reader = {
+            let l = l.clone();
+            thread::spawn(move || drop(l.read()))
+        };
+        let writer = {
+            let l = l.clone();
+            thread::spawn(move || *l.write() += 1)
+        };
+        drop(guard);
+        reader.join().unwrap();
+        writer.join().unwrap();
+        assert_eq!(*l.read(), 1);
+    });
+}
+
+#[test]
+fn condvar_notify_one() {
+    model(|| {
+        let pair = Arc::new((Mutex::new(false), Condvar::new()));
+        let notifier = {
+            let pair = pair.clone();
+            thread::spawn(move || {
+                *pair.0.lock() = true;
+                pair.1.notify_one();
+            })
+        };
+        let mut ready = pair.0.lock();
+        while !*ready {
+            ready = pair.1.wait(ready);
+        }
+        drop(ready);
+        notifier.join().unwrap();
+    });
+}
+
+#[test]
+fn condvar_notify_all() {
+    model(|| {
+        let pair = Arc::new((Mutex::new(false), Condvar::new()));
+        let waiter = {
+            let pair = pair.clone();
+            thread::spawn(move || {
+                let mut ready = pair.0.lock();
+                while !*ready {
+                    ready = pair.1.wait(ready);
+                }
+            })
+        };
+        *pair.0.lock() = true;
+        pair.1.notify_all();
+        waiter.join().unwrap();
+    });
+}
+
+#[test]
+fn once_call_once() {
+    model(|| {
+        let once = Arc::new(Once::new());
+        let count = Arc::new(AtomicUsize::new(0));
+        let thread = {
+            let once = once.clone();
+            let count = count.clone();
+            thread::spawn(move || {
+                once.call_once(|| {
+                    count.fetch_add(1, Relaxed);
+                });
+                assert!(once.is_completed());
+            })
+        };
+        once.call_once(|| {
+            count.fetch_add(1, Relaxed);
+        });
+        assert!(once.is_completed());
+        thread.join().unwrap();
+        assert_eq!(count.load(Relaxed), 1);
+    });
+}
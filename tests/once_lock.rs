@@ -10,6 +10,7 @@ use std::{
         mpsc::channel,
     },
     thread,
+    time::Duration,
 };
 
 fn spawn_and_wait<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> R {
@@ -208,3 +209,57 @@ fn dropck() {
     }
 }
 */
+
+#[test]
+fn wait() {
+    let cell = OnceLock::new();
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(50));
+            cell.set(92).unwrap();
+        });
+
+        assert_eq!(cell.wait(), &92);
+    });
+}
+
+#[test]
+fn wait_timeout() {
+    let cell = OnceLock::new();
+
+    assert_eq!(cell.wait_timeout(Duration::from_millis(10)), None);
+
+    // A failed initializer doesn't complete the cell.
+    assert_eq!(cell.get_or_try_init(|| Err(())), Err(()));
+    assert_eq!(cell.wait_timeout(Duration::from_millis(10)), None);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(50));
+            cell.set(92).unwrap();
+        });
+
+        assert_eq!(cell.wait_timeout(Duration::from_secs(60)), Some(&92));
+    });
+}
+
+#[test]
+fn mut_access_after_wait_timeout() {
+    let mut cell = OnceLock::new();
+
+    assert_eq!(cell.wait_timeout(Duration::from_millis(10)), None);
+    assert_eq!(cell.get_mut(), None);
+    assert_eq!(cell.take(), None);
+
+    // A failed initializer poisons the cell's `Once` until it's set.
+    assert_eq!(cell.get_or_try_init(|| Err(())), Err(()));
+    assert_eq!(cell.wait_timeout(Duration::from_millis(10)), None);
+    assert_eq!(cell.get_mut(), None);
+
+    cell.set(92).unwrap();
+    assert_eq!(cell.get_mut(), Some(&mut 92));
+    assert_eq!(cell.take(), Some(92));
+    assert_eq!(cell.wait_timeout(Duration::from_millis(10)), None);
+    assert_eq!(cell.into_inner(), None);
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "alloc")]
+
+use rustix_futex_sync::oneshot::{channel, RecvError, RecvTimeoutError, SendError, TryRecvError};
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let (tx, rx) = channel();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    tx.send(1).unwrap();
+    assert_eq!(rx.try_recv(), Ok(1));
+    // The value can only be received once.
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(rx.recv(), Err(RecvError));
+}
+
+#[test]
+fn recv_blocks() {
+    let (tx, rx) = channel();
+    let t = thread::spawn(move || rx.recv());
+    thread::sleep(Duration::from_millis(10));
+    tx.send("hello").unwrap();
+    assert_eq!(t.join().unwrap(), Ok("hello"));
+}
+
+#[test]
+fn sender_dropped() {
+    let (tx, rx) = channel::<i32>();
+    drop(tx);
+    assert_eq!(rx.recv(), Err(RecvError));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+    let (tx, rx) = channel::<i32>();
+    let t = thread::spawn(move || rx.recv());
+    thread::sleep(Duration::from_millis(10));
+    drop(tx);
+    assert_eq!(t.join().unwrap(), Err(RecvError));
+}
+
+#[test]
+fn receiver_dropped() {
+    let (tx, rx) = channel();
+    assert!(!tx.is_closed());
+    drop(rx);
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(1), Err(SendError(1)));
+}
+
+#[test]
+fn recv_timeout() {
+    let (tx, rx) = channel();
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(7).unwrap();
+    });
+    assert_eq!(rx.recv_timeout(Duration::from_secs(60)), Ok(7));
+    t.join().unwrap();
+}
+
+#[test]
+fn unreceived_value_is_dropped() {
+    struct Counted(Arc<AtomicUsize>);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, SeqCst);
+        }
+    }
+
+    let drops = Arc::new(AtomicUsize::new(0));
+
+    let (tx, rx) = channel();
+    tx.send(Counted(drops.clone())).ok().unwrap();
+    assert_eq!(drops.load(SeqCst), 0);
+    drop(rx);
+    assert_eq!(drops.load(SeqCst), 1);
+
+    let (tx, rx) = channel();
+    drop(rx);
+    let err = tx.send(Counted(drops.clone())).err().unwrap();
+    assert_eq!(drops.load(SeqCst), 1);
+    drop(err);
+    assert_eq!(drops.load(SeqCst), 2);
+}
+
+#[test]
+fn many() {
+    for _ in 0..1000 {
+        let (tx, rx) = channel();
+        let t = thread::spawn(move || tx.send(1).unwrap());
+        assert_eq!(rx.recv(), Ok(1));
+        t.join().unwrap();
+    }
+}
+
+#[test]
+fn receiver_dropped_after_timeout() {
+    let (tx, rx) = channel();
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    drop(rx);
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(1), Err(SendError(1)));
+}
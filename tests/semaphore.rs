@@ -0,0 +1,167 @@
+use rustix_futex_sync::Semaphore;
+use std::sync::atomic::{AtomicU32, Ordering::SeqCst};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let sem = Semaphore::new(2);
+    assert_eq!(sem.available_permits(), 2);
+
+    let a = sem.acquire();
+    let b = sem.try_acquire().unwrap();
+    assert!(sem.try_acquire().is_none());
+    assert_eq!(sem.available_permits(), 0);
+
+    drop(a);
+    assert_eq!(sem.available_permits(), 1);
+    drop(b);
+    assert_eq!(sem.available_permits(), 2);
+}
+
+#[test]
+fn many() {
+    let sem = Semaphore::new(5);
+
+    let p = sem.acquire_many(3);
+    assert_eq!(p.num_permits(), 3);
+    assert!(sem.try_acquire_many(3).is_none());
+    assert_eq!(sem.available_permits(), 2);
+    assert_eq!(sem.try_acquire_many(0).unwrap().num_permits(), 0);
+
+    drop(p);
+    assert_eq!(sem.available_permits(), 5);
+}
+
+#[test]
+fn forget_and_release() {
+    let sem = Semaphore::default();
+    assert!(sem.try_acquire().is_none());
+
+    sem.release(2);
+    sem.acquire_many(2).forget();
+    assert_eq!(sem.available_permits(), 0);
+
+    sem.release(1);
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[test]
+#[should_panic]
+fn release_overflow() {
+    let sem = Semaphore::new(Semaphore::MAX_PERMITS);
+    sem.release(1);
+}
+
+#[test]
+fn timeout() {
+    let sem = Semaphore::new(1);
+    let _p = sem.acquire();
+
+    assert!(sem.acquire_timeout(Duration::from_millis(10)).is_none());
+    assert!(sem.acquire_many_timeout(2, Duration::from_millis(10)).is_none());
+    assert_eq!(sem.available_permits(), 0);
+}
+
+#[test]
+fn timeout_then_release() {
+    let sem = Arc::new(Semaphore::new(0));
+
+    let sem2 = sem.clone();
+    let t = thread::spawn(move || {
+        sem2.acquire_timeout(Duration::from_secs(60))
+            .map(|p| p.num_permits())
+    });
+
+    thread::sleep(Duration::from_millis(10));
+    sem.release(1);
+    assert_eq!(t.join().unwrap(), Some(1));
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[test]
+fn bounded_concurrency() {
+    const LIMIT: u32 = 3;
+    const THREADS: usize = 16;
+
+    let sem = Arc::new(Semaphore::new(LIMIT));
+    let active = Arc::new(AtomicU32::new(0));
+
+    let handles = (0..THREADS)
+        .map(|_| {
+            let sem = sem.clone();
+            let active = active.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    let _p = sem.acquire();
+                    assert!(active.fetch_add(1, SeqCst) < LIMIT);
+                    thread::yield_now();
+                    active.fetch_sub(1, SeqCst);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(sem.available_permits(), LIMIT);
+}
+
+#[test]
+fn mixed_waiters() {
+    // Waiters for different numbers of permits must all make progress.
+    let sem = Arc::new(Semaphore::new(0));
+
+    let handles = (1..=4)
+        .map(|n| {
+            let sem = sem.clone();
+            thread::spawn(move || {
+                for _ in 0..50 {
+                    drop(sem.acquire_many(n));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    thread::sleep(Duration::from_millis(10));
+    sem.release(4);
+
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(sem.available_permits(), 4);
+}
+
+#[test]
+fn back_to_back_releases() {
+    // Each release must reach a waiter, even if the waiter woken by an
+    // earlier release hasn't run yet.
+    let sem = Arc::new(Semaphore::new(0));
+
+    let (tx, rx) = mpsc::channel();
+    let handles = (0..3)
+        .map(|_| {
+            let sem = sem.clone();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                sem.acquire().forget();
+                tx.send(()).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    thread::sleep(Duration::from_millis(100));
+    sem.release(1);
+    sem.release(1);
+    sem.release(1);
+
+    for _ in 0..3 {
+        rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    }
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(sem.available_permits(), 0);
+}
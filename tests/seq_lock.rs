@@ -0,0 +1,104 @@
+use rustix_futex_sync::SeqLock;
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let lock = SeqLock::new(1_u64);
+    assert_eq!(lock.read(), 1);
+    assert_eq!(lock.try_read(), Some(1));
+
+    lock.write(2);
+    assert_eq!(lock.read(), 2);
+
+    *lock.lock() += 1;
+    assert_eq!(lock.read_blocking(), 3);
+
+    let mut lock = lock;
+    *lock.get_mut() = 4;
+    assert_eq!(lock.into_inner(), 4);
+}
+
+#[test]
+fn try_read_during_write() {
+    let lock = SeqLock::new(0_u32);
+    let guard = lock.lock();
+    assert_eq!(lock.try_read(), None);
+    assert_eq!(format!("{:?}", lock), "SeqLock { data: <locked> }");
+    drop(guard);
+    assert_eq!(lock.try_read(), Some(0));
+}
+
+#[test]
+fn no_torn_reads() {
+    // Writers always store a pair of equal values, so readers must never
+    // observe a pair of unequal values.
+    let lock = SeqLock::new([0_u64; 8]);
+    let done = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                for i in 0..10_000 {
+                    lock.write([i; 8]);
+                }
+            });
+        }
+
+        for blocking in [false, true] {
+            let (lock, done) = (&lock, &done);
+            s.spawn(move || {
+                while !done.load(SeqCst) {
+                    let val = if blocking {
+                        lock.read_blocking()
+                    } else {
+                        lock.read()
+                    };
+                    assert!(val.iter().all(|x| *x == val[0]));
+                }
+            });
+        }
+
+        thread::sleep(Duration::from_millis(50));
+        done.store(true, SeqCst);
+    });
+}
+
+#[test]
+fn read_blocking_waits_for_writer() {
+    let lock = SeqLock::new(0_u32);
+
+    thread::scope(|s| {
+        let mut guard = lock.lock();
+        let reader = s.spawn(|| lock.read_blocking());
+
+        thread::sleep(Duration::from_millis(10));
+        *guard = 7;
+        drop(guard);
+
+        assert_eq!(reader.join().unwrap(), 7);
+    });
+}
+
+#[test]
+fn chunk_sizes() {
+    // Values are copied in chunks as large as their alignment allows, so
+    // exercise each chunk size.
+    let bytes = SeqLock::new([1_u8; 3]);
+    bytes.write([2; 3]);
+    *bytes.lock() = [3; 3];
+    assert_eq!(bytes.read(), [3; 3]);
+
+    let halves = SeqLock::new([1_u16; 3]);
+    halves.lock()[1] = 2;
+    assert_eq!(halves.read(), [1, 2, 1]);
+
+    let words = SeqLock::new((1_u32, 2_u32, 3_u32));
+    words.lock().2 = 4;
+    assert_eq!(words.try_read(), Some((1, 2, 4)));
+
+    let unit = SeqLock::new(());
+    unit.write(());
+    assert_eq!(unit.read(), ());
+}
@@ -0,0 +1,93 @@
+use rustix_futex_sync::shm::{Latch, WaitGroup};
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn smoke() {
+    let latch = Latch::new(2);
+    assert_eq!(latch.count(), 2);
+    assert!(!latch.wait_timeout(Duration::from_millis(10)));
+
+    latch.count_down();
+    assert_eq!(latch.count(), 1);
+    latch.count_down();
+    assert_eq!(latch.count(), 0);
+    latch.wait();
+    assert!(latch.wait_timeout(Duration::from_millis(10)));
+}
+
+#[test]
+fn reuse() {
+    let latch = Latch::default();
+    latch.wait();
+
+    latch.add(3);
+    assert_eq!(latch.count(), 3);
+    for _ in 0..3 {
+        latch.count_down();
+    }
+    latch.wait();
+}
+
+#[test]
+#[should_panic]
+fn count_down_at_zero() {
+    Latch::new(0).count_down();
+}
+
+#[test]
+fn wait_group() {
+    const N: usize = 8;
+
+    let wg = Arc::new(WaitGroup::new(0));
+    let done = Arc::new(AtomicUsize::new(0));
+    for _ in 0..N {
+        wg.add(1);
+        let wg = wg.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            done.fetch_add(1, SeqCst);
+            wg.count_down();
+        });
+    }
+
+    wg.wait();
+    assert_eq!(done.load(SeqCst), N);
+}
+
+#[test]
+fn many_waiters() {
+    const N: usize = 8;
+
+    let latch = Arc::new(Latch::new(1));
+    let handles = (0..N)
+        .map(|_| {
+            let latch = latch.clone();
+            thread::spawn(move || {
+                latch.wait();
+                assert_eq!(latch.count(), 0);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    thread::sleep(Duration::from_millis(10));
+    latch.count_down();
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn timeout_then_count_down() {
+    let latch = Arc::new(Latch::new(1));
+
+    let latch2 = latch.clone();
+    let t = thread::spawn(move || latch2.wait_timeout(Duration::from_secs(60)));
+
+    thread::sleep(Duration::from_millis(10));
+    latch.count_down();
+    assert!(t.join().unwrap());
+}
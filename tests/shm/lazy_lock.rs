@@ -0,0 +1,101 @@
+//! The following is derived from Rust's
+//! library/std/src/sync/lazy_lock/tests.rs at revision
+//! 22a5267c83a3e17f2b763279eb24bb632c45dc6b.
+
+use rustix_futex_sync::shm::{LazyLock, Mutex};
+use std::{
+    panic,
+    sync::atomic::{AtomicUsize, Ordering::SeqCst},
+    thread,
+};
+
+fn spawn_and_wait<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> R {
+    thread::spawn(f).join().unwrap()
+}
+
+#[test]
+#[cfg_attr(target_os = "emscripten", ignore)]
+fn sync_lazy_new() {
+    static CALLED: AtomicUsize = AtomicUsize::new(0);
+    static SYNC_LAZY: LazyLock<i32> = LazyLock::new(|| {
+        CALLED.fetch_add(1, SeqCst);
+        92
+    });
+
+    assert_eq!(CALLED.load(SeqCst), 0);
+
+    spawn_and_wait(|| {
+        let y = *SYNC_LAZY - 30;
+        assert_eq!(y, 62);
+        assert_eq!(CALLED.load(SeqCst), 1);
+    });
+
+    let y = *SYNC_LAZY - 30;
+    assert_eq!(y, 62);
+    assert_eq!(CALLED.load(SeqCst), 1);
+}
+
+#[test]
+fn sync_lazy_default() {
+    static CALLED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Foo(u8);
+    impl Default for Foo {
+        fn default() -> Self {
+            CALLED.fetch_add(1, SeqCst);
+            Foo(42)
+        }
+    }
+
+    let lazy: LazyLock<Mutex<Foo>> = <_>::default();
+
+    assert_eq!(CALLED.load(SeqCst), 0);
+
+    assert_eq!(lazy.lock().0, 42);
+    assert_eq!(CALLED.load(SeqCst), 1);
+
+    lazy.lock().0 = 21;
+
+    assert_eq!(lazy.lock().0, 21);
+    assert_eq!(CALLED.load(SeqCst), 1);
+}
+
+#[test]
+#[cfg_attr(target_os = "emscripten", ignore)]
+fn static_sync_lazy() {
+    static XS: LazyLock<Vec<i32>> = LazyLock::new(|| vec![1, 2, 3]);
+
+    spawn_and_wait(|| {
+        assert_eq!(&*XS, &vec![1, 2, 3]);
+    });
+
+    assert_eq!(&*XS, &vec![1, 2, 3]);
+}
+
+#[test]
+fn sync_lazy_poisoning() {
+    let x: LazyLock<String> = LazyLock::new(|| panic!("kaboom"));
+    for _ in 0..2 {
+        let res = panic::catch_unwind(|| x.len());
+        assert!(res.is_err());
+    }
+}
+
+#[test]
+fn into_inner() {
+    let lazy = LazyLock::new(|| 92);
+    let f = LazyLock::into_inner(lazy).unwrap_err();
+    assert_eq!(f(), 92);
+
+    let lazy = LazyLock::new(|| 92);
+    assert_eq!(LazyLock::get(&lazy), None);
+    assert_eq!(LazyLock::force(&lazy), &92);
+    assert_eq!(LazyLock::get(&lazy), Some(&92));
+    assert_eq!(LazyLock::into_inner(lazy).ok(), Some(92));
+}
+
+#[test]
+fn is_sync_send() {
+    fn assert_traits<T: Send + Sync>() {}
+    assert_traits::<LazyLock<String>>();
+}
@@ -1,12 +1,27 @@
 #![cfg(feature = "shm")]
+// The std-derived tests keep std's `ignore` conditions as-is; this crate
+// has no `unwinding` feature.
+#![allow(unexpected_cfgs)]
+mod barrier;
 mod basic;
+mod event;
+mod latch;
+mod lazy_lock;
 mod mutex_examples;
 mod once_lock;
 mod once;
+mod parker;
 mod parking_lot_issue_203;
 mod parking_lot_issue_392;
+mod reentrant_mutex;
+mod region;
 mod repr;
+mod robust_once;
 mod rwlock_examples;
+mod semaphore;
+mod seq_lock;
 mod sync_condvar;
 mod sync_mutex;
 mod sync_rwlock;
+mod ticket_mutex;
+mod versioned;
@@ -3,9 +3,12 @@
 //! f42e96149dd03e816b8bc3c329e7b9a5d12fcdab.
 
 use rustix_futex_sync::shm::Once;
+use rustix_futex_sync::ExclusiveState;
 use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
 use std::sync::mpsc::channel;
 use std::thread;
+use std::time::Duration;
 
 #[test]
 fn smoke_once() {
@@ -63,21 +66,17 @@ fn poison_bad() {
     });
     assert!(t.is_err());
 
-    /*
     // poisoning propagates
     let t = panic::catch_unwind(|| {
         O.call_once(|| {});
     });
-    //assert!(t.is_err());
-    let _ = t;
-    */
+    assert!(t.is_err());
 
     // we can subvert poisoning, however
     let mut called = false;
     O.call_once_force(|p| {
         called = true;
-        //assert!(p.is_poisoned())
-        let _ = p;
+        assert!(p.is_poisoned())
     });
     assert!(called);
 
@@ -100,8 +99,7 @@ fn wait_for_force_to_finish() {
     let (tx2, rx2) = channel();
     let t1 = thread::spawn(move || {
         O.call_once_force(|p| {
-            //assert!(p.is_poisoned());
-            let _ = p;
+            assert!(p.is_poisoned());
             tx1.send(()).unwrap();
             rx2.recv().unwrap();
         });
@@ -123,3 +121,108 @@ fn wait_for_force_to_finish() {
     assert!(t1.join().is_ok());
     assert!(t2.join().is_ok());
 }
+
+#[test]
+fn exclusive_state() {
+    let mut o = Once::new();
+    assert_eq!(o.state(), ExclusiveState::Incomplete);
+
+    // poison the once
+    let t = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        o.call_once(|| panic!());
+    }));
+    assert!(t.is_err());
+    assert_eq!(o.state(), ExclusiveState::Poisoned);
+
+    // reset it, and initialize it normally
+    o.set_state(ExclusiveState::Incomplete);
+    let mut called = false;
+    o.call_once_force(|p| {
+        assert!(!p.is_poisoned());
+        called = true;
+    });
+    assert!(called);
+    assert_eq!(o.state(), ExclusiveState::Complete);
+}
+
+#[test]
+fn wait() {
+    for _ in 0..50 {
+        let val = AtomicBool::new(false);
+        let once = Once::new();
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    once.wait();
+                    assert!(val.load(Relaxed));
+                });
+            }
+
+            once.call_once(|| val.store(true, Relaxed));
+        });
+    }
+}
+
+#[test]
+fn wait_on_poisoned() {
+    let once = Once::new();
+
+    panic::catch_unwind(|| once.call_once(|| panic!())).unwrap_err();
+    panic::catch_unwind(|| once.wait()).unwrap_err();
+}
+
+#[test]
+fn wait_force_on_poisoned() {
+    let once = Once::new();
+
+    thread::scope(|s| {
+        panic::catch_unwind(|| once.call_once(|| panic!())).unwrap_err();
+
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(100));
+
+            once.call_once_force(|_| {});
+        });
+
+        once.wait_force();
+    })
+}
+
+#[test]
+fn wait_timeout() {
+    let once = Once::new();
+
+    assert!(!once.wait_timeout(Duration::from_millis(10)));
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(100));
+
+            once.call_once(|| {});
+        });
+
+        assert!(once.wait_timeout(Duration::from_secs(60)));
+    });
+
+    assert!(once.wait_timeout(Duration::ZERO));
+}
+
+#[test]
+fn exclusive_state_after_wait_timeout() {
+    let mut o = Once::new();
+
+    // A timed-out waiter leaves the once marked as having waiters.
+    assert!(!o.wait_timeout(Duration::from_millis(10)));
+    assert_eq!(o.state(), ExclusiveState::Incomplete);
+
+    assert!(!o.wait_timeout(Duration::from_millis(10)));
+    o.set_state(ExclusiveState::Poisoned);
+    assert_eq!(o.state(), ExclusiveState::Poisoned);
+
+    o.set_state(ExclusiveState::Incomplete);
+    assert!(!o.wait_timeout(Duration::from_millis(10)));
+    o.set_state(ExclusiveState::Complete);
+    assert_eq!(o.state(), ExclusiveState::Complete);
+    assert!(o.wait_timeout(Duration::ZERO));
+}
@@ -0,0 +1,116 @@
+use rustix_futex_sync::shm::Parker;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn unpark_before_park() {
+    let parker = Parker::new();
+    parker.unparker().unpark();
+    // The token is available, so this returns immediately.
+    unsafe { parker.park() };
+}
+
+#[test]
+fn token_does_not_accumulate() {
+    let parker = Parker::new();
+    let unparker = parker.unparker();
+    unparker.unpark();
+    unparker.unpark();
+    unsafe { parker.park() };
+
+    // The second `unpark` didn't leave another token.
+    let start = Instant::now();
+    unsafe { parker.park_timeout(Duration::from_millis(50)) };
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn park_timeout() {
+    let parker = Parker::new();
+    let start = Instant::now();
+    unsafe { parker.park_timeout(Duration::from_millis(20)) };
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
+
+#[test]
+fn park_deadline() {
+    use rustix::time::{clock_gettime, ClockId, Timespec};
+
+    let parker = Parker::new();
+    let now = clock_gettime(ClockId::Monotonic);
+    let deadline = Timespec {
+        tv_sec: now.tv_sec,
+        tv_nsec: now.tv_nsec,
+    };
+    // The deadline has already passed.
+    unsafe { parker.park_deadline(&deadline) };
+}
+
+#[test]
+fn unpark_from_other_thread() {
+    let parker = Parker::new();
+    let unparker = parker.unparker();
+    let flag = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(10));
+            flag.store(true, SeqCst);
+            unparker.unpark();
+        });
+
+        while !flag.load(SeqCst) {
+            unsafe { parker.park() };
+        }
+    });
+}
+
+#[test]
+fn park_timeout_unpark() {
+    let parker = Parker::new();
+    let unparker = parker.unparker();
+    let flag = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(10));
+            flag.store(true, SeqCst);
+            unparker.unpark();
+        });
+
+        while !flag.load(SeqCst) {
+            unsafe { parker.park_timeout(Duration::from_secs(60)) };
+        }
+    });
+}
+
+#[test]
+fn ping_pong() {
+    const N: usize = 1000;
+
+    let a = Parker::new();
+    let b = Parker::new();
+    let (ua, ub) = (a.unparker(), b.unparker());
+    let turn = AtomicUsize::new(0);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 0..N {
+                while turn.load(SeqCst) != 2 * i + 1 {
+                    unsafe { b.park() };
+                }
+                turn.store(2 * i + 2, SeqCst);
+                ua.unpark();
+            }
+        });
+
+        for i in 0..N {
+            turn.store(2 * i + 1, SeqCst);
+            ub.unpark();
+            while turn.load(SeqCst) != 2 * i + 2 {
+                unsafe { a.park() };
+            }
+        }
+    });
+}
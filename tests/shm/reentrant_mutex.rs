@@ -0,0 +1,86 @@
+#![cfg(feature = "atomic_usize")]
+
+use rustix_futex_sync::lock_api::GetThreadId as _;
+use rustix_futex_sync::shm::{RawThreadId, TidReentrantMutex};
+use std::cell::Cell;
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn smoke() {
+    let m: TidReentrantMutex<Cell<i32>> = TidReentrantMutex::new(Cell::new(0));
+    assert!(!m.is_locked());
+    {
+        let a = m.lock();
+        let b = m.lock();
+        let c = m.try_lock().unwrap();
+        a.set(1);
+        assert_eq!(b.get() + c.get(), 2);
+        assert!(m.is_owned_by_current_thread());
+    }
+    assert!(!m.is_locked());
+    assert!(!m.is_owned_by_current_thread());
+    assert_eq!(m.into_inner().get(), 1);
+}
+
+#[test]
+fn static_mutex() {
+    static M: TidReentrantMutex<i32> = TidReentrantMutex::new(7);
+    let a = M.lock();
+    assert_eq!(*a, *M.lock());
+}
+
+#[test]
+fn try_lock_other_thread() {
+    let m = Arc::new(TidReentrantMutex::<()>::new(()));
+    let g = m.lock();
+
+    let m2 = m.clone();
+    thread::spawn(move || {
+        assert!(m2.is_locked());
+        assert!(!m2.is_owned_by_current_thread());
+        assert!(m2.try_lock().is_none());
+    })
+    .join()
+    .unwrap();
+
+    drop(g);
+    let m2 = m.clone();
+    thread::spawn(move || assert!(m2.try_lock().is_some()))
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn contention() {
+    const N: usize = 8;
+    const M: usize = 1000;
+
+    let m = Arc::new(TidReentrantMutex::<Cell<usize>>::new(Cell::new(0)));
+    let threads: Vec<_> = (0..N)
+        .map(|_| {
+            let m = m.clone();
+            thread::spawn(move || {
+                for _ in 0..M {
+                    let a = m.lock();
+                    let b = m.lock();
+                    a.set(b.get() + 1);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(m.lock().get(), N * M);
+}
+
+#[test]
+fn thread_ids() {
+    let id = RawThreadId::INIT.nonzero_thread_id();
+    assert_eq!(id, RawThreadId::INIT.nonzero_thread_id());
+    let other = thread::spawn(|| RawThreadId::INIT.nonzero_thread_id())
+        .join()
+        .unwrap();
+    assert_ne!(id, other);
+}
@@ -0,0 +1,148 @@
+// Miri doesn't support `memfd_create` or mapping files.
+#![cfg(not(miri))]
+
+use rustix_futex_sync::shm::{Once, OnceLock, Placeable, Region, ReprCMutex};
+use std::ffi::CString;
+use std::sync::atomic::{AtomicU32, Ordering::Relaxed};
+use std::thread;
+
+fn unique_name(base: &str) -> CString {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    CString::new(format!(
+        "/rustix-futex-sync-test-{}-{}-{}",
+        base,
+        std::process::id(),
+        COUNTER.fetch_add(1, Relaxed)
+    ))
+    .unwrap()
+}
+
+#[test]
+fn anonymous() {
+    let name = unique_name("anonymous");
+    let region = Region::create_anonymous(&name, 4096).unwrap();
+    assert_eq!(region.data_len(), 4096);
+    assert!(!region.is_initialized());
+
+    // Map the same memfd a second time, as another process would.
+    let other = Region::from_fd(rustix::io::dup(&region).unwrap()).unwrap();
+    assert_eq!(other.data_len(), 4096);
+
+    let (mutex, lock) = unsafe {
+        (
+            region.place::<ReprCMutex<u64>>(0),
+            region.place::<OnceLock<u32>>(16),
+        )
+    };
+    region.init(|| {
+        *mutex.lock() = 42;
+        lock.set(7).unwrap();
+    });
+    assert!(region.is_initialized());
+    assert!(other.is_initialized());
+
+    // The second mapping sees the initialization and doesn't redo it.
+    other.init(|| unreachable!());
+    let (mutex, lock) = unsafe {
+        (
+            other.place::<ReprCMutex<u64>>(0),
+            other.place::<OnceLock<u32>>(16),
+        )
+    };
+    assert_eq!(*mutex.lock(), 42);
+    assert_eq!(lock.get(), Some(&7));
+}
+
+#[test]
+fn named() {
+    let name = unique_name("named");
+    let region = Region::create(&name, 256).unwrap();
+    assert_eq!(
+        Region::create(&name, 256).unwrap_err(),
+        rustix::io::Errno::EXIST
+    );
+
+    let other = Region::open(&name).unwrap();
+    let third = Region::create_or_open(&name, 256).unwrap();
+    Region::unlink(&name).unwrap();
+
+    let onces = unsafe {
+        [
+            region.place::<Once>(0),
+            other.place::<Once>(0),
+            third.place::<Once>(0),
+        ]
+    };
+    onces[0].call_once(|| {});
+    assert!(onces[1].is_completed());
+    assert!(onces[2].is_completed());
+}
+
+#[test]
+fn wait_initialized() {
+    let name = unique_name("wait");
+    let region = Region::create_anonymous(&name, 64).unwrap();
+    let other = Region::from_fd(rustix::io::dup(&region).unwrap()).unwrap();
+
+    let mutex = unsafe { region.place::<ReprCMutex<u32>>(0) };
+    let other_mutex = unsafe { other.place::<ReprCMutex<u32>>(0) };
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            other.wait_initialized();
+            assert_eq!(*other_mutex.lock(), 1);
+        });
+
+        region.init(|| *mutex.lock() = 1);
+    });
+}
+
+#[test]
+fn contended_across_mappings() {
+    let name = unique_name("contended");
+    let region = Region::create_anonymous(&name, 64).unwrap();
+    let other = Region::from_fd(rustix::io::dup(&region).unwrap()).unwrap();
+
+    // The two mappings are at different addresses, so this only works
+    // because the `shm` types don't use `FUTEX_PRIVATE_FLAG`.
+    thread::scope(|s| {
+        for r in [&region, &other, &region, &other] {
+            let mutex = unsafe { r.place::<ReprCMutex<u64>>(0) };
+            s.spawn(move || {
+                for _ in 0..1000 {
+                    *mutex.lock() += 1;
+                }
+            });
+        }
+    });
+
+    assert_eq!(*unsafe { region.place::<ReprCMutex<u64>>(0) }.lock(), 4000);
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn out_of_bounds() {
+    let name = unique_name("oob");
+    let region = Region::create_anonymous(&name, 64).unwrap();
+    let _ = unsafe { region.place::<u64>(64) };
+}
+
+#[test]
+#[should_panic(expected = "overaligned")]
+fn overaligned() {
+    #[repr(align(128))]
+    struct Overaligned(#[allow(dead_code)] AtomicU32);
+    unsafe impl Placeable for Overaligned {}
+
+    let name = unique_name("overaligned");
+    let region = Region::create_anonymous(&name, 256).unwrap();
+    let _ = unsafe { region.place::<Overaligned>(0) };
+}
+
+#[test]
+#[should_panic(expected = "misaligned")]
+fn misaligned() {
+    let name = unique_name("misaligned");
+    let region = Region::create_anonymous(&name, 64).unwrap();
+    let _ = unsafe { region.place::<u64>(4) };
+}
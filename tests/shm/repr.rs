@@ -2,8 +2,9 @@
 //! its public types.
 
 use core::mem::{align_of, size_of, transmute};
+use core::ptr::addr_of;
 use rustix_futex_sync::shm::lock_api::{RawMutex as _, RawRwLock as _};
-use rustix_futex_sync::shm::{Condvar, Once, RawCondvar, RawMutex, RawRwLock};
+use rustix_futex_sync::shm::{Condvar, Once, OnceLock, RawCondvar, RawMutex, RawRwLock, ReprCMutex, ReprCRwLock, TicketMutex};
 
 #[test]
 fn repr_raw_mutex() {
@@ -53,13 +54,142 @@ fn repr_raw_rwlock() {
     }
 }
 
+#[test]
+fn repr_ticket_mutex() {
+    assert_eq!(size_of::<TicketMutex>(), size_of::<[u32; 2]>());
+    assert_eq!(align_of::<TicketMutex>(), align_of::<[u32; 2]>());
+    unsafe {
+        assert_eq!(
+            transmute::<TicketMutex, [u32; 2]>(TicketMutex::INIT),
+            [0_u32; 2]
+        );
+    }
+}
+
+#[test]
+fn repr_repr_c_mutex() {
+    let m = ReprCMutex::new(0x1234_u64);
+    let base = addr_of!(m) as usize;
+    assert_eq!(ReprCMutex::<u64>::DATA_OFFSET, 8);
+    assert_eq!(m.data_ptr() as usize - base, ReprCMutex::<u64>::DATA_OFFSET);
+    assert_eq!(size_of::<ReprCMutex<u64>>(), 16);
+    assert_eq!(align_of::<ReprCMutex<u64>>(), align_of::<u64>());
+
+    let m = ReprCMutex::new(0x12_u8);
+    let base = addr_of!(m) as usize;
+    assert_eq!(ReprCMutex::<u8>::DATA_OFFSET, 4);
+    assert_eq!(m.data_ptr() as usize - base, ReprCMutex::<u8>::DATA_OFFSET);
+
+    unsafe {
+        assert_eq!(transmute::<ReprCMutex<u32>, [u32; 2]>(ReprCMutex::new(0)), [0_u32; 2]);
+    }
+}
+
+#[test]
+fn repr_repr_c_rwlock() {
+    let l = ReprCRwLock::new(0x1234_u64);
+    let base = addr_of!(l) as usize;
+    assert_eq!(ReprCRwLock::<u64>::DATA_OFFSET, 8);
+    assert_eq!(l.data_ptr() as usize - base, ReprCRwLock::<u64>::DATA_OFFSET);
+    assert_eq!(size_of::<ReprCRwLock<u64>>(), 16);
+
+    let l = ReprCRwLock::new(0x12_u8);
+    let base = addr_of!(l) as usize;
+    assert_eq!(ReprCRwLock::<u8>::DATA_OFFSET, 8);
+    assert_eq!(l.data_ptr() as usize - base, ReprCRwLock::<u8>::DATA_OFFSET);
+
+    unsafe {
+        assert_eq!(transmute::<ReprCRwLock<u32>, [u32; 3]>(ReprCRwLock::new(0)), [0_u32; 3]);
+    }
+}
+
+#[test]
+fn repr_once_lock() {
+    let c = OnceLock::from(0x1234_u64);
+    let base = addr_of!(c) as usize;
+    assert_eq!(OnceLock::<u64>::VALUE_OFFSET, 8);
+    assert_eq!(c.get().unwrap() as *const u64 as usize - base, OnceLock::<u64>::VALUE_OFFSET);
+    assert_eq!(size_of::<OnceLock<u64>>(), 16);
+
+    let c = OnceLock::from(0x1234_u16);
+    let base = addr_of!(c) as usize;
+    assert_eq!(OnceLock::<u16>::VALUE_OFFSET, 4);
+    assert_eq!(c.get().unwrap() as *const u16 as usize - base, OnceLock::<u16>::VALUE_OFFSET);
+}
+
+#[cfg(feature = "bytemuck")]
+#[test]
+fn zeroed_bytemuck() {
+    use bytemuck::Zeroable;
+
+    let m = ReprCMutex::<u32>::zeroed();
+    assert!(!m.is_locked());
+    *m.lock() += 1;
+    assert_eq!(*m.lock(), 1);
+
+    let l = ReprCRwLock::<u32>::zeroed();
+    assert!(!l.is_locked());
+    *l.write() += 1;
+    assert_eq!(*l.read(), 1);
+
+    let c = OnceLock::<String>::zeroed();
+    assert_eq!(c.get(), None);
+    assert_eq!(c.get_or_init(|| "hello".to_owned()), "hello");
+
+    let o = Once::zeroed();
+    assert!(!o.is_completed());
+    o.call_once(|| {});
+    assert!(o.is_completed());
+
+    let _ = RawMutex::zeroed();
+    let _ = RawRwLock::zeroed();
+    let _ = TicketMutex::zeroed();
+    let _ = RawCondvar::zeroed();
+    let _ = Condvar::zeroed();
+}
+
+#[cfg(feature = "zerocopy")]
+#[test]
+fn zeroed_zerocopy() {
+    use zerocopy::FromZeros;
+
+    let m = ReprCMutex::<u32>::new_zeroed();
+    assert!(!m.is_locked());
+    *m.lock() += 1;
+    assert_eq!(*m.lock(), 1);
+
+    let l = ReprCRwLock::<u32>::new_zeroed();
+    assert!(!l.is_locked());
+    *l.write() += 1;
+    assert_eq!(*l.read(), 1);
+
+    let c = OnceLock::<u32>::new_zeroed();
+    assert_eq!(c.get(), None);
+    assert_eq!(c.get_or_init(|| 92), &92);
+
+    let o = Once::new_zeroed();
+    assert!(!o.is_completed());
+    o.call_once(|| {});
+    assert!(o.is_completed());
+
+    let _ = RawMutex::new_zeroed();
+    let _ = RawRwLock::new_zeroed();
+    let _ = TicketMutex::new_zeroed();
+    let _ = RawCondvar::new_zeroed();
+    let _ = Condvar::new_zeroed();
+}
+
 // Test that the types are FFI-safe.
 #[allow(dead_code)]
 #[deny(improper_ctypes)]
 extern "C" {
     fn use_raw_mutex(x: RawMutex);
     fn use_raw_rwlock(x: RawRwLock);
+    fn use_ticket_mutex(x: TicketMutex);
     fn use_condvar(x: Condvar);
     fn use_raw_condvar(x: RawCondvar);
     fn use_once(x: Once);
+    fn use_once_lock(x: OnceLock<u32>);
+    fn use_repr_c_mutex(x: ReprCMutex<u32>);
+    fn use_repr_c_rwlock(x: ReprCRwLock<u32>);
 }
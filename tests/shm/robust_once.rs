@@ -0,0 +1,118 @@
+// Miri doesn't support `memfd_create` or mapping files.
+#![cfg(not(miri))]
+
+use rustix::fd::{AsFd, AsRawFd};
+use rustix::fs::{Mode, OFlags};
+use rustix_futex_sync::shm::{Region, RobustOnce};
+use std::ffi::CStr;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+use std::{env, panic, process, thread};
+
+/// The environment variable telling [`initializer_dies`] which region to
+/// open.
+const REGION_VAR: &str = "RUSTIX_FUTEX_SYNC_ROBUST_ONCE_REGION";
+
+fn region() -> Region {
+    let name = CStr::from_bytes_with_nul(b"robust_once\0").unwrap();
+    Region::create_anonymous(name, 64).unwrap()
+}
+
+/// Runs [`initializer_dies`] in a child process, which exits while
+/// initializing the `RobustOnce` in `region`.
+fn kill_initializer(region: &Region) {
+    let path = format!(
+        "/proc/{}/fd/{}",
+        process::id(),
+        region.as_fd().as_raw_fd()
+    );
+    let status = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "robust_once::initializer_dies", "--nocapture"])
+        .env(REGION_VAR, path)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(7));
+}
+
+/// When run by [`kill_initializer`], exits while initializing the
+/// `RobustOnce`. Otherwise, does nothing.
+#[test]
+fn initializer_dies() {
+    let Ok(path) = env::var(REGION_VAR) else {
+        return;
+    };
+    let fd = rustix::fs::open(path.as_str(), OFlags::RDWR, Mode::empty()).unwrap();
+    let region = Region::from_fd(fd).unwrap();
+    let once = unsafe { region.place::<RobustOnce>(0) };
+    once.call_once(|| process::exit(7));
+    unreachable!();
+}
+
+#[test]
+fn smoke_once() {
+    let region = region();
+    let other = Region::from_fd(rustix::io::dup(&region).unwrap()).unwrap();
+    let mut a = 0;
+    let once = unsafe { region.place::<RobustOnce>(0) };
+    let other_once = unsafe { other.place::<RobustOnce>(0) };
+    once.call_once(|| a += 1);
+    other_once.call_once(|| a += 1);
+    assert_eq!(a, 1);
+    assert!(other_once.is_completed());
+}
+
+#[test]
+fn recover() {
+    let region = region();
+    let once = unsafe { region.place::<RobustOnce>(0) };
+    kill_initializer(&region);
+    assert!(!once.is_completed());
+
+    let mut called = false;
+    once.call_once_force(|state| {
+        assert!(state.is_poisoned());
+        assert!(state.is_recovered());
+        called = true;
+    });
+    assert!(called);
+    assert!(once.is_completed());
+}
+
+#[test]
+fn dead_initializer_poisons() {
+    let region = region();
+    let once = unsafe { region.place::<RobustOnce>(0) };
+    kill_initializer(&region);
+
+    assert!(panic::catch_unwind(|| once.wait()).is_err());
+    assert!(panic::catch_unwind(|| once.call_once(|| {})).is_err());
+
+    // A panic after recovering leaves it poisoned, but no longer recovering.
+    assert!(panic::catch_unwind(|| once.call_once_force(|_| panic!())).is_err());
+    once.call_once_force(|state| {
+        assert!(state.is_poisoned());
+        assert!(!state.is_recovered());
+    });
+    once.call_once(|| {});
+}
+
+#[test]
+fn live_initializer() {
+    static O: RobustOnce = RobustOnce::new();
+
+    // Run for several liveness checks, which find this process alive.
+    let (tx, rx) = channel();
+    let t = thread::spawn(move || {
+        O.call_once(|| {
+            tx.send(()).unwrap();
+            thread::sleep(Duration::from_millis(500));
+        })
+    });
+    rx.recv().unwrap();
+    assert!(!O.wait_timeout(Duration::from_millis(10)));
+    O.call_once(|| panic!());
+    assert!(O.is_completed());
+    t.join().unwrap();
+}
@@ -142,13 +142,13 @@ fn test_mutex_arc_condvar() {
     let _t = thread::spawn(move || {
         // wait until parent gets in
         rx.recv().unwrap();
-        let &(ref lock, ref cvar) = &*packet2.0;
+        let (lock, cvar) = &*packet2.0;
         let mut lock = lock.lock();
         *lock = true;
         cvar.notify_one();
     });
 
-    let &(ref lock, ref cvar) = &*packet.0;
+    let (lock, cvar) = &*packet.0;
     let mut lock = lock.lock();
     tx.send(()).unwrap();
     assert!(!*lock);
@@ -166,14 +166,14 @@ fn test_arc_condvar_poison() {
 
     let _t = thread::spawn(move || -> () {
         rx.recv().unwrap();
-        let &(ref lock, ref cvar) = &*packet2.0;
+        let (lock, cvar) = &*packet2.0;
         let _g = lock.lock();
         cvar.notify_one();
         // Parent should fail when it wakes up.
         panic!();
     });
 
-    let &(ref lock, ref cvar) = &*packet.0;
+    let (lock, cvar) = &*packet.0;
     let mut lock = lock.lock();
     tx.send(()).unwrap();
     while *lock == 1 {
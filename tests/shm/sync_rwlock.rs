@@ -242,10 +242,7 @@ fn test_rwlock_try_write() {
     let write_result = lock.try_write();
     match write_result {
         None => (),
-        Some(_) => assert!(
-            false,
-            "try_write should not succeed while read_guard is in scope"
-        ),
+        Some(_) => panic!("try_write should not succeed while read_guard is in scope"),
     }
 
     drop(read_guard);
@@ -254,7 +251,7 @@ fn test_rwlock_try_write() {
     let write_result = lock.try_write();
     match write_result {
         None => (),
-        Some(_) => assert!(false, "try_write should not succeed while mapped_read_guard is in scope"),
+        Some(_) => panic!("try_write should not succeed while mapped_read_guard is in scope"),
     }
 
     drop(mapped_read_guard);
@@ -346,6 +343,7 @@ fn test_read_guard_covariance() {
 */
 
 #[test]
+#[allow(clippy::drop_non_drop)]
 fn test_mapped_read_guard_covariance() {
     fn do_stuff<'a>(_: MappedRwLockReadGuard<'_, &'a i32>, _: &'a i32) {}
     let j: i32 = 5;
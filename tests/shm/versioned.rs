@@ -0,0 +1,128 @@
+// Miri doesn't support `memfd_create` or mapping files.
+#![cfg(not(miri))]
+
+use rustix_futex_sync::shm::{
+    LayoutError, Once, OnceLock, RawMutex, Region, ReprCMutex, Versioned, LAYOUT_VERSION,
+};
+use std::alloc::Layout;
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+fn region() -> (Region, Region) {
+    let name = CStr::from_bytes_with_nul(b"versioned\0").unwrap();
+    let region = Region::create_anonymous(name, 64).unwrap();
+    let other = Region::from_fd(rustix::io::dup(&region).unwrap()).unwrap();
+    (region, other)
+}
+
+#[test]
+fn new() {
+    let mutex = Versioned::new(ReprCMutex::new(1_u32));
+    *mutex.attach().unwrap().lock() += 1;
+    assert_eq!(*mutex.attach().unwrap().lock(), 2);
+    assert_eq!(Versioned::<ReprCMutex<u32>>::VALUE_OFFSET, 16);
+}
+
+#[test]
+fn attach_across_mappings() {
+    let (region, other) = region();
+
+    // The first attach writes the header, and later ones check it.
+    let (mutex, other_mutex) = unsafe {
+        (
+            region.place::<Versioned<ReprCMutex<u64>>>(0),
+            other.place::<Versioned<ReprCMutex<u64>>>(0),
+        )
+    };
+    *mutex.attach().unwrap().lock() = 42;
+    assert_eq!(*other_mutex.attach().unwrap().lock(), 42);
+    assert_ne!(unsafe { other.place::<AtomicU64>(0) }.load(Relaxed), 0);
+}
+
+#[test]
+fn kind_mismatch() {
+    let (region, other) = region();
+
+    unsafe { region.place::<Versioned<RawMutex>>(0) }.attach().unwrap();
+    assert!(matches!(
+        unsafe { other.place::<Versioned<Once>>(0) }.attach(),
+        Err(LayoutError::Kind { .. })
+    ));
+}
+
+#[test]
+fn size_mismatch() {
+    let (region, other) = region();
+
+    unsafe { region.place::<Versioned<OnceLock<u32>>>(0) }.attach().unwrap();
+    assert_eq!(
+        unsafe { other.place::<Versioned<OnceLock<u64>>>(0) }.attach(),
+        Err(LayoutError::Size {
+            ours: Layout::new::<OnceLock<u64>>(),
+            theirs: Layout::new::<OnceLock<u32>>(),
+        })
+    );
+}
+
+#[test]
+fn data_size_mismatch() {
+    let (region, other) = region();
+
+    unsafe { region.place::<Versioned<ReprCMutex<[u8; 4]>>>(0) }
+        .attach()
+        .unwrap();
+    let err = unsafe { other.place::<Versioned<ReprCMutex<[u8; 32]>>>(0) }
+        .attach()
+        .unwrap_err();
+    assert!(matches!(err, LayoutError::Size { .. }));
+    assert_eq!(
+        err.to_string(),
+        "size 8 and alignment 4 do not match 36 and 4"
+    );
+}
+
+#[test]
+fn header_mismatch() {
+    let (region, other) = region();
+    let header = unsafe { region.place::<AtomicU64>(0) };
+    let versioned = unsafe { other.place::<Versioned<Once>>(0) };
+
+    versioned.attach().unwrap();
+    let good = header.load(Relaxed);
+
+    // Simulate a header written by a different version of this crate.
+    header.store(good ^ (0xffff << 16), Relaxed);
+    assert_eq!(
+        versioned.attach().unwrap_err(),
+        LayoutError::Version {
+            ours: LAYOUT_VERSION,
+            theirs: !LAYOUT_VERSION,
+        }
+    );
+
+    // Simulate a header written for a non-shm primitive.
+    header.store(good & !0xff, Relaxed);
+    assert_eq!(
+        versioned.attach().unwrap_err(),
+        LayoutError::Shm {
+            ours: true,
+            theirs: false,
+        }
+    );
+
+    // Simulate memory which doesn't hold a `Versioned`.
+    header.store(u64::MAX, Relaxed);
+    assert_eq!(versioned.attach().unwrap_err(), LayoutError::Magic);
+
+    header.store(good, Relaxed);
+    versioned.attach().unwrap();
+
+    // Simulate a second header word which doesn't hold a valid layout.
+    let layout = unsafe { region.place::<AtomicU64>(8) };
+    let good = layout.load(Relaxed);
+    layout.store(u64::MAX, Relaxed);
+    assert_eq!(versioned.attach().unwrap_err(), LayoutError::Magic);
+
+    layout.store(good, Relaxed);
+    versioned.attach().unwrap();
+}
@@ -2,6 +2,10 @@
 //! library/std/src/sync/mutex/tests.rs at revision
 //! 3ef4b083ac03fd25339be009e3ae525adab30d78.
 
+// std's `ignore` conditions are kept as-is; this crate has no `unwinding`
+// feature.
+#![allow(unexpected_cfgs)]
+
 use rustix_futex_sync::Condvar;
 use rustix_futex_sync::{Mutex, MutexGuard, MappedMutexGuard};
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -142,13 +146,13 @@ fn test_mutex_arc_condvar() {
     let _t = thread::spawn(move || {
         // wait until parent gets in
         rx.recv().unwrap();
-        let &(ref lock, ref cvar) = &*packet2.0;
+        let (lock, cvar) = &*packet2.0;
         let mut lock = lock.lock();
         *lock = true;
         cvar.notify_one();
     });
 
-    let &(ref lock, ref cvar) = &*packet.0;
+    let (lock, cvar) = &*packet.0;
     let mut lock = lock.lock();
     tx.send(()).unwrap();
     assert!(!*lock);
@@ -166,14 +170,14 @@ fn test_arc_condvar_poison() {
 
     let _t = thread::spawn(move || -> () {
         rx.recv().unwrap();
-        let &(ref lock, ref cvar) = &*packet2.0;
+        let (lock, cvar) = &*packet2.0;
         let _g = lock.lock();
         cvar.notify_one();
         // Parent should fail when it wakes up.
         panic!();
     });
 
-    let &(ref lock, ref cvar) = &*packet.0;
+    let (lock, cvar) = &*packet.0;
     let mut lock = lock.lock();
     tx.send(()).unwrap();
     while *lock == 1 {
@@ -2,6 +2,10 @@
 //! library/std/src/sync/rwlock/tests.rs at revision
 //! 3ef4b083ac03fd25339be009e3ae525adab30d78.
 
+// std's `ignore` conditions are kept as-is; this crate has no `unwinding`
+// feature.
+#![allow(unexpected_cfgs)]
+
 use rand::{self, Rng};
 use rustix_futex_sync::{RwLock, RwLockReadGuard, MappedRwLockReadGuard, RwLockWriteGuard, MappedRwLockWriteGuard};
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -242,10 +246,7 @@ fn test_rwlock_try_write() {
     let write_result = lock.try_write();
     match write_result {
         None => (),
-        Some(_) => assert!(
-            false,
-            "try_write should not succeed while read_guard is in scope"
-        ),
+        Some(_) => panic!("try_write should not succeed while read_guard is in scope"),
     }
 
     drop(read_guard);
@@ -254,7 +255,7 @@ fn test_rwlock_try_write() {
     let write_result = lock.try_write();
     match write_result {
         None => (),
-        Some(_) => assert!(false, "try_write should not succeed while mapped_read_guard is in scope"),
+        Some(_) => panic!("try_write should not succeed while mapped_read_guard is in scope"),
     }
 
     drop(mapped_read_guard);
@@ -346,6 +347,7 @@ fn test_read_guard_covariance() {
 */
 
 #[test]
+#[allow(clippy::drop_non_drop)]
 fn test_mapped_read_guard_covariance() {
     fn do_stuff<'a>(_: MappedRwLockReadGuard<'_, &'a i32>, _: &'a i32) {}
     let j: i32 = 5;
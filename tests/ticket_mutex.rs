@@ -0,0 +1,114 @@
+use rustix_futex_sync::lock_api::{RawMutex as _, RawMutexFair as _};
+use rustix_futex_sync::TicketMutex;
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+struct Locked<T> {
+    lock: TicketMutex,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for Locked<T> {}
+
+impl<T> Locked<T> {
+    fn new(data: T) -> Self {
+        Self {
+            lock: TicketMutex::INIT,
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.lock.lock();
+        let r = f(unsafe { &mut *self.data.get() });
+        unsafe { self.lock.unlock() };
+        r
+    }
+}
+
+#[test]
+fn smoke() {
+    let lock = TicketMutex::INIT;
+    assert!(!lock.is_locked());
+    lock.lock();
+    assert!(lock.is_locked());
+    assert!(!lock.try_lock());
+    unsafe { lock.unlock() };
+    assert!(!lock.is_locked());
+
+    assert!(lock.try_lock());
+    unsafe { lock.unlock_fair() };
+    assert!(!lock.is_locked());
+
+    lock.lock();
+    unsafe { lock.bump() };
+    assert!(lock.is_locked());
+    unsafe { lock.unlock() };
+}
+
+#[test]
+fn contention() {
+    const N: usize = 8;
+    const M: usize = 1000;
+
+    let counter = Arc::new(Locked::new(0));
+    let threads: Vec<_> = (0..N)
+        .map(|_| {
+            let counter = counter.clone();
+            thread::spawn(move || {
+                for _ in 0..M {
+                    counter.with(|c| *c += 1);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(counter.with(|c| *c), N * M);
+}
+
+#[test]
+fn fifo() {
+    const N: usize = 5;
+
+    let order = Arc::new(Locked::new(Vec::new()));
+    order.lock.lock();
+
+    // Queue up the threads one at a time, so that they take their tickets
+    // in order.
+    let threads: Vec<_> = (0..N)
+        .map(|i| {
+            let order = order.clone();
+            let t = thread::spawn(move || order.with(|order| order.push(i)));
+            thread::sleep(Duration::from_millis(50));
+            t
+        })
+        .collect();
+
+    unsafe { order.lock.unlock() };
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(order.with(|order| order.clone()), (0..N).collect::<Vec<_>>());
+}
+
+#[test]
+fn lock_api_mutex() {
+    let mutex = rustix_futex_sync::lock_api::Mutex::<TicketMutex, _>::new(0);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..100 {
+                    *mutex.lock() += 1;
+                }
+            });
+        }
+    });
+    let mut guard = mutex.lock();
+    *guard += 1;
+    rustix_futex_sync::lock_api::MutexGuard::unlock_fair(guard);
+    assert_eq!(*mutex.lock(), 401);
+}